@@ -0,0 +1,79 @@
+//! Checks the embedded server registry so a broken entry fails the build
+//! instead of the app at startup.
+
+#[path = "src/servers/spec.rs"]
+#[allow(dead_code)]
+mod spec;
+
+use std::fs;
+
+const REGISTRY: &str = "src/servers/registry.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", REGISTRY);
+    println!("cargo:rerun-if-changed=src/servers/spec.rs");
+
+    let registry = fs::read_to_string(REGISTRY).expect("the registry should be readable");
+    let specs: Vec<spec::Spec> = match serde_json::from_str(&registry) {
+        Ok(specs) => specs,
+        Err(err) => panic!("{} is invalid: {}", REGISTRY, err),
+    };
+
+    let mut names = std::collections::HashSet::new();
+    for spec in &specs {
+        let meta = match spec {
+            spec::Spec::GithubRelease(server) => &server.meta,
+            spec::Spec::Npm(server) => &server.meta,
+            spec::Spec::Pip(server) => &server.meta,
+            spec::Spec::Cargo(server) => &server.meta,
+            spec::Spec::Go(server) => &server.meta,
+            spec::Spec::Composer(server) => &server.meta,
+            spec::Spec::Jdtls(server) => &server.meta,
+            spec::Spec::Ghcup(server) => &server.meta,
+            spec::Spec::Opam(server) => &server.meta,
+            spec::Spec::Gem(server) => &server.meta,
+            spec::Spec::DotnetTool(server) => &server.meta,
+            spec::Spec::Zls(server) => &server.meta,
+        };
+        if !names.insert(&meta.name) {
+            panic!("{} defines {} twice", REGISTRY, meta.name);
+        }
+        if meta.languages.is_empty() {
+            panic!("{} gives {} no language", REGISTRY, meta.name);
+        }
+        if meta.conform.is_some() && meta.category != spec::Category::Formatter {
+            panic!(
+                "{} names {} in conform.nvim, but it isn't a formatter",
+                REGISTRY, meta.name
+            );
+        }
+        if meta.nvim_lint.is_some() && meta.category != spec::Category::Linter {
+            panic!(
+                "{} names {} in nvim-lint, but it isn't a linter",
+                REGISTRY, meta.name
+            );
+        }
+        if meta.dap.is_some() && meta.category != spec::Category::DebugAdapter {
+            panic!(
+                "{} gives {} an nvim-dap adapter, but it isn't a debug adapter",
+                REGISTRY, meta.name
+            );
+        }
+        for language in meta.language_aliases.keys() {
+            if !meta.languages.contains(language) {
+                panic!(
+                    "{} gives aliases to {}, which {} doesn't serve",
+                    REGISTRY, language, meta.name
+                );
+            }
+        }
+        for language in meta.project_files.keys() {
+            if !meta.languages.contains(language) {
+                panic!(
+                    "{} gives project files to {}, which {} doesn't serve",
+                    REGISTRY, language, meta.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,2366 @@
+use crate::{
+    browser, cache,
+    config::{Config, Origin, Settings, SortMode},
+    disk,
+    doctor::{self, Check},
+    download,
+    error::{self, Error},
+    export::Editor,
+    fuzzy,
+    keys::Keymap,
+    lock::{self, Lock},
+    logfile, manifest, metadata, paths, project,
+    queue::{JobState, Queue},
+    servers::{self, external, remote, Category, Plan, Server, Status},
+    theme::Theme,
+    updates, version,
+    worker::{self, Failure, Report, Task},
+};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet, VecDeque},
+    env, fs, io,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tui::widgets::ListState;
+use unicode_segmentation::UnicodeSegmentation;
+
+mod input;
+
+pub use input::Effect;
+
+pub enum InputMode {
+    Normal,
+    Editing,
+    /// Waiting for the user to confirm removing the selected server
+    ConfirmUninstall,
+    /// Waiting for the user to confirm quitting with jobs unfinished
+    ConfirmQuit,
+    /// Waiting for the user to confirm building the selected server from
+    /// source, nothing prebuilt being published for this platform
+    ConfirmBuild,
+    /// Showing the settings in effect
+    Settings,
+    /// Showing every key binding
+    Help,
+    /// Showing the last failure
+    Error,
+    /// Showing the health checks of the installed servers
+    Doctor,
+    /// Showing what installing or updating the chosen servers would do,
+    /// waiting for the user to confirm
+    Plan,
+    /// Picking the version of the selected server to install
+    Versions,
+    /// Typing the path of the file to install the selected server from
+    LocalFile,
+    /// Typing the path of the file to export the installed servers to
+    StateFile,
+}
+
+/// Pane receiving the navigation keys
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Languages,
+    Servers,
+}
+
+/// View of the registry the panes show, each keeping its own selection
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    All,
+    Installed,
+    /// Installed servers with a newer version out
+    Updates,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 3] = [Tab::All, Tab::Installed, Tab::Updates];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Tab::All => "All",
+            Tab::Installed => "Installed",
+            Tab::Updates => "Updates",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Selection of both panes, kept for a tab while another one is shown
+#[derive(Clone, Default)]
+struct Place {
+    language: Option<LanguageRow>,
+    server: Option<usize>,
+}
+
+/// Outcome of the last action, shown below the list
+pub enum Message {
+    Info(String),
+    Warning(String),
+    Error(String),
+}
+
+/// How long an info message stays, warnings and errors staying until
+/// replaced
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lines kept in the log pane, older ones get dropped
+const LOG_LIMIT: usize = 5000;
+
+/// Line of the log pane
+pub struct LogLine {
+    /// Name of the server whose job logged the line, `config` for problems
+    /// found at startup
+    pub server: String,
+    pub message: Message,
+}
+
+pub struct StatefulList<T> {
+    pub state: ListState,
+    pub items: Vec<T>,
+    /// Index of the first visible item, as scrolled by tui's `List`, which
+    /// keeps its own copy private
+    pub offset: usize,
+    /// Rows the list was last drawn in
+    pub height: usize,
+    /// Whether an item can be selected, headers being skipped over
+    selectable: fn(&T) -> bool,
+}
+
+impl<T> StatefulList<T> {
+    pub fn with_items(items: Vec<T>) -> StatefulList<T> {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        StatefulList {
+            state,
+            items,
+            offset: 0,
+            height: 0,
+            selectable: |_| true,
+        }
+    }
+
+    /// Same list, the items `selectable` rejects never getting selected
+    pub fn skipping(mut self, selectable: fn(&T) -> bool) -> StatefulList<T> {
+        self.selectable = selectable;
+        self.settle(true);
+        self
+    }
+
+    fn is_selectable(&self, i: usize) -> bool {
+        self.items.get(i).is_some_and(self.selectable)
+    }
+
+    /// Select the item at `i`, unless it can't be
+    pub fn select(&mut self, i: usize) -> bool {
+        if !self.is_selectable(i) {
+            return false;
+        }
+        self.state.select(Some(i));
+        true
+    }
+
+    /// Move the selection off an item that can't be selected, to the
+    /// nearest one after it when `forward` is set and before it otherwise,
+    /// trying the other way once there's none
+    fn settle(&mut self, forward: bool) {
+        let Some(i) = self.state.selected() else {
+            return;
+        };
+        if self.is_selectable(i) {
+            return;
+        }
+        let after = (i..self.items.len()).find(|&j| self.is_selectable(j));
+        let before = (0..i.min(self.items.len()))
+            .rev()
+            .find(|&j| self.is_selectable(j));
+        let found = if forward {
+            after.or(before)
+        } else {
+            before.or(after)
+        };
+        self.state.select(found);
+    }
+
+    /// Scroll `offset` like `List` does for one row items, keeping the
+    /// selection within `height` rows
+    pub fn fit(&mut self, height: usize) {
+        self.height = height;
+        let Some(selected) = self.state.selected() else {
+            self.offset = 0;
+            return;
+        };
+        self.offset = self.offset.min(self.items.len().saturating_sub(1));
+        if selected >= self.offset + height {
+            self.offset = selected + 1 - height;
+        } else if selected < self.offset {
+            self.offset = selected;
+        }
+    }
+
+    /// Item shown `row` rows below the top of the list
+    pub fn at_row(&self, row: usize) -> Option<usize> {
+        let i = self.offset + row;
+        (i < self.items.len()).then_some(i)
+    }
+
+    pub fn next(&mut self) {
+        if self.items.is_empty() {
+            self.state.select(None);
+            return;
+        }
+        let mut i = self.state.selected();
+        for _ in 0..self.items.len() {
+            let next = match i {
+                Some(i) if i < self.items.len() - 1 => i + 1,
+                _ => 0,
+            };
+            i = Some(next);
+            if self.is_selectable(next) {
+                break;
+            }
+        }
+        self.state.select(i);
+        self.settle(true);
+    }
+
+    pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            self.state.select(None);
+            return;
+        }
+        let mut i = self.state.selected();
+        for _ in 0..self.items.len() {
+            let previous = match i {
+                Some(0) => self.items.len() - 1,
+                Some(i) => i - 1,
+                None => 0,
+            };
+            i = Some(previous);
+            if self.is_selectable(previous) {
+                break;
+            }
+        }
+        self.state.select(i);
+        self.settle(false);
+    }
+
+    /// Move the selection `delta` items down, or up when negative,
+    /// stopping at either end
+    pub fn jump(&mut self, delta: isize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = self.state.selected().unwrap_or(0);
+        let i = i.saturating_add_signed(delta).min(self.items.len() - 1);
+        self.state.select(Some(i));
+        self.settle(delta >= 0);
+    }
+
+    /// Show `items` instead, keeping the selected item selected when it's
+    /// still there
+    pub fn replace(&mut self, items: Vec<T>)
+    where
+        T: PartialEq,
+    {
+        let selected = reselected(self.state.selected(), &self.items, &items);
+        self.items = items;
+        self.state.select(selected);
+        self.settle(true);
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.state.selected().and_then(|i| self.items.get(i))
+    }
+}
+
+/// Row of the language pane
+#[derive(Clone, PartialEq, Eq)]
+pub enum LanguageRow {
+    /// The servers suggested for the project in the current directory that
+    /// aren't installed, above the favorites
+    Suggested,
+    /// The servers marked as favorites, above the languages while the
+    /// search is empty
+    Favorites,
+    Language(String),
+}
+
+/// Row of the server pane
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ServerRow {
+    /// Heading of the servers of a category below it, which can't be
+    /// selected
+    Header(Category),
+    /// Index into `App::servers`
+    Server(usize),
+}
+
+impl StatefulList<ServerRow> {
+    /// Index into `App::servers` of the selected server
+    pub fn server(&self) -> Option<usize> {
+        match self.selected() {
+            Some(&ServerRow::Server(i)) => Some(i),
+            _ => None,
+        }
+    }
+}
+
+/// Index in `new` of the item at `old` in `old_items`, falling back to the
+/// first item when it's gone
+fn reselected<T: PartialEq>(old: Option<usize>, old_items: &[T], new: &[T]) -> Option<usize> {
+    old.and_then(|i| old_items.get(i))
+        .and_then(|item| new.iter().position(|candidate| candidate == item))
+        .or_else(|| (!new.is_empty()).then_some(0))
+}
+
+/// Prefix of a search restricted to language names
+const LANGUAGE_PREFIX: &str = "lang:";
+
+/// What a language got found through by the search, better matches first
+#[derive(Clone, PartialEq, Eq)]
+pub enum Match {
+    /// The name of the server it holds
+    Server(String),
+    Language,
+    /// Another name of the language
+    Alias(String),
+    /// A word of the description of one of its servers
+    Description,
+}
+
+/// Plan resolved for a server about to be installed
+pub struct Planned {
+    pub plan: Plan,
+    /// Programs the install runs that aren't on `PATH`, which keep it from
+    /// being queued
+    pub missing: Vec<String>,
+}
+
+impl Planned {
+    fn resolve(server: &dyn Server) -> Result<Planned, String> {
+        let plan = servers::planned(server, None).map_err(|err| err.to_string())?;
+        let missing = plan.missing();
+        Ok(Planned { plan, missing })
+    }
+}
+
+/// What's known once the network is found unreachable
+pub struct Offline {
+    /// Time since the metadata in use got fetched, `None` when it never was
+    pub age: Option<Duration>,
+    /// Whether each server can be installed from the download cache
+    pub installable: Vec<bool>,
+}
+
+/// App holds the state of the application
+pub struct App {
+    /// Current value of the input box
+    pub input: String,
+    /// Byte offset of the cursor in `input`, always between two graphemes
+    pub cursor: usize,
+    /// Current input mode
+    pub input_mode: InputMode,
+    /// Pane the arrow keys move in
+    pub focus: Focus,
+
+    /// Content of the config file
+    pub config: Config,
+    /// Values in effect, config file included
+    pub settings: Settings,
+    pub theme: Theme,
+    pub keys: Keymap,
+
+    pub servers: Vec<Arc<dyn Server>>,
+    /// Every language handled by at least one server, sorted
+    pub languages: Vec<String>,
+    /// Other names of the languages, as the servers give them
+    pub language_aliases: BTreeMap<String, Vec<String>>,
+
+    /// Install status of each entry in `servers`, refreshed after every action
+    pub statuses: Vec<Status>,
+    /// Installed version of each entry in `servers`
+    pub installed_versions: Vec<Option<String>>,
+    /// Version of each entry in `servers` installed globally, while
+    /// installing into a project
+    pub global_versions: Vec<Option<String>>,
+    /// Whether each entry in `servers` is held at its installed version
+    pub held: Vec<bool>,
+    /// Version each entry in `servers` can roll back to
+    pub previous_versions: Vec<Option<String>>,
+    /// Whether each entry in `servers` is an adopted external install
+    pub adopted: Vec<bool>,
+    /// Path editors start each entry in `servers` from
+    pub launchers: Vec<PathBuf>,
+    /// Extra facts about the install of each entry in `servers`
+    pub details: Vec<Vec<(&'static str, String)>>,
+    /// Executable found on `PATH` for each entry in `servers` that isn't
+    /// installed, as of the last scan
+    pub found: Vec<Option<PathBuf>>,
+    /// Indices into `servers` of the entries suggested by the files of the
+    /// project in the current directory, found at startup
+    pub project: Vec<usize>,
+    /// What each installed entry in `servers` printed when verified
+    pub reported_versions: Vec<Option<String>>,
+    /// When each entry in `servers` was last installed or acted on, as RFC 3339
+    pub last_used: Vec<Option<String>>,
+    /// Latest upstream version of each installed entry in `servers`, fetched at startup
+    pub latest_versions: Vec<Option<String>>,
+    /// Size of the last download of each entry in `servers`, once one told it
+    pub download_sizes: Vec<Option<u64>>,
+    /// Bytes taken by the install of each entry in `servers`, once measured
+    pub disk_sizes: Vec<Option<u64>>,
+
+    /// Indices into `servers` of the entries marked for a batch install
+    pub marked: HashSet<usize>,
+    /// Jobs requested on the servers, run one at a time
+    pub queue: Queue,
+
+    /// Languages matching the search
+    pub language_list: StatefulList<LanguageRow>,
+    /// What each language in `language_list` matched the search through
+    pub matches: BTreeMap<String, Match>,
+    /// Servers handling the selected language, grouped by category when
+    /// there's more than one
+    pub server_list: StatefulList<ServerRow>,
+    /// Category the panes are restricted to
+    pub category: Option<Category>,
+    pub tab: Tab,
+    /// Where the selection was in each tab when it was last left
+    places: [Place; 3],
+    /// Text to copy, which the event loop writes to the terminal between
+    /// two frames
+    pub clipboard: Option<String>,
+    /// Order of the panes while nothing is searched
+    pub sort: SortMode,
+
+    /// When Enter was last pressed in a search matching nothing
+    pub search_flash: Option<Instant>,
+    /// Time and position of the last click on a list item
+    last_click: Option<(Instant, u16, u16)>,
+    /// Result of the last action
+    pub message: Option<Message>,
+    /// Info message shown, with when it first was, to clear it once it's
+    /// been there for `MESSAGE_TIMEOUT`
+    message_seen: Option<(String, Instant)>,
+    /// Last failure of a job, shown in a popup until dismissed
+    pub error: Option<Failure>,
+    /// Set once the network is found unreachable
+    pub offline: Option<Offline>,
+    /// Bytes taken by the cached downloads and their directory, measured
+    /// when the settings get shown
+    pub cache: (u64, PathBuf),
+    /// Results of the last health check, `None` until it's done
+    pub checks: Option<Vec<Check>>,
+    /// Health check running in the background
+    pending_checks: Option<Receiver<Vec<Check>>>,
+    /// Whether installs show their plan and wait for a confirmation
+    pub dry_run: bool,
+    /// Index into `servers` of each server about to be installed, with its
+    /// plan once resolved
+    pub plans: Vec<(usize, Option<Result<Planned, String>>)>,
+    /// Plans being resolved in the background
+    pending_plans: Option<Receiver<(usize, Result<Planned, String>)>>,
+    /// What confirming the plans queues
+    pub plan_task: Task,
+    /// Servers that were marked when the plans got shown, `None` when they
+    /// are for the selected one
+    pub plan_marked: Option<usize>,
+    /// Index into `servers` of the server the version picker is open for
+    pub versions_of: usize,
+    /// Path typed in the prompt for the file to install from, or to export
+    /// the installed servers to
+    pub local_file: String,
+    /// Versions offered by the picker, `None` until they're fetched
+    pub versions: Option<Result<StatefulList<String>, String>>,
+    /// Versions being fetched in the background
+    pending_versions: Option<Receiver<Result<Vec<String>, String>>>,
+    /// Latest versions being checked in the background, by index into
+    /// `servers`
+    pending_latest: Option<Receiver<(usize, Result<String, String>)>>,
+    /// Installs made before their size got recorded, being measured
+    pending_disk: Option<Receiver<(usize, u64)>>,
+    /// Registry being refreshed in the background
+    pending_registry: Option<Receiver<error::Result<usize>>>,
+
+    /// Everything reported by jobs, oldest first
+    pub log: VecDeque<LogLine>,
+    /// Whether the log pane is shown
+    pub show_log: bool,
+    /// Lines the log pane is scrolled up from the bottom, 0 following new
+    /// lines as they come
+    pub log_scroll: usize,
+
+    /// Spinner frames elapsed since startup
+    pub ticks: usize,
+    /// Time of the frame being drawn, set by the event loop along with
+    /// `ticks` so drawing doesn't read the clock itself
+    pub now: Instant,
+    /// Whether the spinner sticks to ASCII, for the Linux console
+    pub ascii_spinner: bool,
+    reports: Receiver<(usize, Report)>,
+    sender: Sender<(usize, Report)>,
+    workers: worker::Pool,
+    /// Held on the install root while jobs run
+    lock: Option<Lock>,
+}
+
+impl App {
+    pub fn selected_server(&self) -> Option<&dyn Server> {
+        self.server_list.server().map(|i| self.servers[i].as_ref())
+    }
+
+    /// Queue `task` on the selected server. Installing what has nothing
+    /// prebuilt for this platform asks whether to build it from source.
+    pub fn start(&mut self, task: Task) {
+        if let Some(i) = self.server_list.server() {
+            let server = &self.servers[i];
+            if matches!(task, Task::Install | Task::Update)
+                && !server.is_available()
+                && server.can_build()
+            {
+                self.input_mode = InputMode::ConfirmBuild;
+                return;
+            }
+            self.enqueue(i, task);
+            self.start_next();
+        }
+    }
+
+    fn enqueue(&mut self, i: usize, task: Task) {
+        let fetches = matches!(task, Task::Install | Task::Pin(_) | Task::Update);
+        let unavailable = match task {
+            Task::Uninstall | Task::Local { .. } => false,
+            Task::Build => !self.servers[i].can_build(),
+            _ => !self.servers[i].is_available(),
+        };
+        if unavailable {
+            self.message = Some(Message::Error(format!(
+                "{} is unavailable on this platform",
+                self.servers[i].name()
+            )));
+        } else if fetches
+            && self
+                .offline
+                .as_ref()
+                .is_some_and(|offline| !offline.installable[i])
+        {
+            self.message = Some(Message::Error(format!(
+                "{} is unavailable offline, its download isn't cached",
+                self.servers[i].name()
+            )));
+        } else if !self.queue.push(i, task) {
+            self.message = Some(Message::Error(format!(
+                "{} is already queued",
+                self.servers[i].name()
+            )));
+        }
+    }
+
+    /// Run queued jobs until the limit of jobs running together is reached,
+    /// locking the install root until they're all done. The queued jobs get
+    /// dropped when another process holds it.
+    fn start_next(&mut self) {
+        if self.queue.unfinished() == 0 {
+            self.lock = None;
+            return;
+        }
+        if self.lock.is_none() {
+            match lock::acquire("jobs of the TUI") {
+                Ok(lock) => {
+                    if let Some(holder) = &lock.broken {
+                        let text = format!("{} exited without releasing its lock", holder);
+                        self.log_as("lsp_installer", Message::Warning, &text);
+                    }
+                    self.lock = Some(lock);
+                }
+                Err(err) => {
+                    let queued: Vec<_> =
+                        self.queue.unfinished_jobs().map(|job| job.server).collect();
+                    for server in queued {
+                        self.queue.cancel(server);
+                    }
+                    self.message =
+                        Some(Message::Error(format!("{}, try again once it's done", err)));
+                    return;
+                }
+            }
+        }
+        while let Some(job) = self.queue.start_next() {
+            self.workers.spawn(
+                job.server,
+                self.servers[job.server].clone(),
+                job.task.clone(),
+                self.sender.clone(),
+                job.cancelled.clone(),
+            );
+        }
+    }
+
+    /// Stop the jobs still running, returning once their workers are done
+    pub fn shutdown(self) {
+        self.workers.shutdown();
+    }
+
+    /// Workers which haven't stopped yet
+    pub fn running_workers(&self) -> usize {
+        self.workers.running()
+    }
+
+    /// Cancel the job of the selected server
+    pub fn cancel(&mut self) {
+        if let Some(i) = self.server_list.server() {
+            if !self.queue.cancel(i) {
+                self.message = Some(Message::Error(format!(
+                    "{} has nothing to cancel",
+                    self.servers[i].name()
+                )));
+            }
+        }
+    }
+
+    /// Write the settings in effect to the config file
+    pub fn save_settings(&mut self) {
+        self.config = self.settings.persist(&self.config);
+        self.message = Some(match self.config.save() {
+            Ok(()) => Message::Info(format!("Saved {}", Config::path().display())),
+            Err(err) => Message::Error(format!(
+                "Failed to save {}: {}",
+                Config::path().display(),
+                err
+            )),
+        });
+    }
+
+    pub fn show_settings(&mut self) {
+        self.cache = (cache::size(), cache::dir());
+        self.input_mode = InputMode::Settings;
+    }
+
+    /// Check the installed servers in the background and show the results
+    pub fn doctor(&mut self) {
+        self.input_mode = InputMode::Doctor;
+        if self.pending_checks.is_some() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let servers = self.servers.clone();
+        let pending = self.pending_downloads();
+        thread::spawn(move || {
+            let _ = tx.send(doctor::run(&servers, pending));
+        });
+        self.checks = None;
+        self.pending_checks = Some(rx);
+    }
+
+    /// Open the homepage of the selected server in the browser, or its
+    /// releases page with `releases`
+    pub fn open_page(&mut self, releases: bool) {
+        let Some(i) = self.server_list.server() else {
+            return;
+        };
+        let server = self.servers[i].clone();
+        let (page, url) = if releases {
+            ("releases page", server.releases())
+        } else {
+            ("homepage", server.homepage())
+        };
+        let Some(url) = url else {
+            self.message = Some(Message::Info(format!(
+                "{} has no {} in the registry",
+                server.name(),
+                page
+            )));
+            return;
+        };
+        self.message = Some(match browser::open(&url) {
+            Ok(()) => Message::Info(format!("Opened {}", url)),
+            Err(err) => Message::Error(format!(
+                "Open {} by hand, no browser could be started: {}",
+                url, err
+            )),
+        });
+    }
+
+    /// Copy the path editors start the selected server from, when it's
+    /// installed
+    pub fn copy_path(&mut self) {
+        let Some(i) = self.server_list.server() else {
+            return;
+        };
+        let server = &self.servers[i];
+        if self.statuses[i] != Status::Installed {
+            self.message = Some(Message::Info(format!(
+                "{} is not installed, nothing to copy",
+                server.name()
+            )));
+            return;
+        }
+        let path = self.launchers[i].display().to_string();
+        self.message = Some(Message::Info(format!("Copied {}", path)));
+        self.clipboard = Some(path);
+    }
+
+    /// Write the configuration of the editor set in the settings
+    pub fn export(&mut self) {
+        let Some(editor) = Editor::from_name(&self.settings.editor.0) else {
+            self.message = Some(Message::Error(format!(
+                "Can't export for the editor `{}`",
+                self.settings.editor.0
+            )));
+            return;
+        };
+        self.message = Some(match editor.write(&self.servers) {
+            Ok(export) if export.skipped.is_empty() => Message::Info(format!(
+                "Wrote the setup of {} servers to {}",
+                export.count,
+                editor.path().display()
+            )),
+            Ok(export) => Message::Warning(format!(
+                "Wrote the setup of {} servers to {}, {} has no setting for {}",
+                export.count,
+                editor.path().display(),
+                editor.title(),
+                export.skipped.join(", ")
+            )),
+            Err(err) => Message::Error(format!(
+                "Failed to write {}: {}",
+                editor.path().display(),
+                err
+            )),
+        });
+    }
+
+    /// Mark or unmark the selected server for a batch install
+    pub fn toggle_mark(&mut self) {
+        if let Some(i) = self.server_list.server() {
+            if !self.marked.remove(&i) {
+                self.marked.insert(i);
+            }
+        }
+    }
+
+    /// Show what installing the marked servers that aren't installed yet, or
+    /// updating the installed ones, would do, waiting for a confirmation
+    pub fn plan_marked(&mut self, task: Task) {
+        // Kept until the plan runs, for closing it to keep the selection
+        let mut marked: Vec<usize> = self.marked.iter().copied().collect();
+        marked.sort_unstable();
+        let count = marked.len();
+        let update = matches!(task, Task::Update);
+        marked.retain(|&i| matches!(self.statuses[i], Status::Installed) == update);
+        self.plan(marked, task);
+        self.plan_marked = Some(count);
+    }
+
+    pub fn toggle_dry_run(&mut self) {
+        self.dry_run = !self.dry_run;
+        self.message = Some(Message::Info(String::from(if self.dry_run {
+            "Dry run on, installs show their plan first"
+        } else {
+            "Dry run off"
+        })));
+    }
+
+    /// Install into the project in the current directory from now on, or
+    /// back into the global root, once nothing is running
+    pub fn toggle_project(&mut self) {
+        if self.queue.unfinished() > 0 {
+            self.message = Some(Message::Error(String::from(
+                "Wait for the jobs to finish before switching the install root",
+            )));
+            return;
+        }
+        paths::set_project(paths::project().is_none());
+        let notices = servers::recover();
+        self.settings.install_root = (paths::root().to_path_buf(), paths::root_origin());
+        self.checks = None;
+        self.refresh_statuses();
+        self.scan_path();
+        self.measure_disk();
+        if self.settings.check_updates.0 {
+            self.check_updates();
+        }
+        self.resort();
+        let text = match paths::project() {
+            Some(project) => format!("Installing into the project at {}", project.display()),
+            None => format!("Installing globally into {}", paths::root().display()),
+        };
+        self.message = Some(if notices.is_empty() {
+            Message::Info(text)
+        } else {
+            Message::Warning(format!("{}, {}", text, notices.join("; ")))
+        });
+    }
+
+    /// Fetch the recent versions of the selected server in the background
+    /// and offer them in a picker
+    pub fn pick_version(&mut self) {
+        let Some(i) = self.server_list.server() else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        let server = self.servers[i].clone();
+        thread::spawn(move || {
+            let versions = servers::compatible_versions(server.as_ref());
+            let _ = tx.send(versions.map_err(|err| err.to_string()));
+        });
+        self.versions_of = i;
+        self.versions = None;
+        self.pending_versions = Some(rx);
+        self.input_mode = InputMode::Versions;
+    }
+
+    /// Install the version picked in the picker, holding the server at it
+    pub fn install_picked(&mut self) {
+        let Some(Ok(versions)) = &self.versions else {
+            return;
+        };
+        if let Some(version) = versions.selected() {
+            self.enqueue(self.versions_of, Task::Pin(version.clone()));
+            self.start_next();
+        }
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Ask for the file to install the selected server from
+    pub fn ask_local_file(&mut self) {
+        if self.server_list.server().is_some() {
+            self.local_file.clear();
+            self.input_mode = InputMode::LocalFile;
+        }
+    }
+
+    /// Install the selected server from the file typed in the prompt
+    pub fn install_local_file(&mut self) {
+        let path = self.local_file.trim();
+        if path.is_empty() {
+            return;
+        }
+        let file = paths::expand(path);
+        self.input_mode = InputMode::Normal;
+        self.start(Task::Local { file, verify: true });
+    }
+
+    /// Ask for the file to export the installed servers to
+    pub fn ask_state_file(&mut self) {
+        self.local_file.clear();
+        self.input_mode = InputMode::StateFile;
+    }
+
+    /// Write the installed servers to the file typed in the prompt, the
+    /// way `export-state` prints them
+    pub fn export_state(&mut self) {
+        let path = self.local_file.trim();
+        if path.is_empty() {
+            return;
+        }
+        let file = paths::expand(path);
+        self.input_mode = InputMode::Normal;
+        let state = manifest::state();
+        let written = serde_json::to_string_pretty(&state)
+            .map_err(io::Error::from)
+            .and_then(|text| fs::write(&file, text + "\n"));
+        self.message = Some(match written {
+            Ok(()) => Message::Info(format!(
+                "Exported {} servers to {}",
+                state.servers.len(),
+                file.display()
+            )),
+            Err(err) => Message::Error(format!("Couldn't write {}: {}", file.display(), err)),
+        });
+    }
+
+    /// Show what installing the selected server would do, waiting for a
+    /// confirmation
+    pub fn plan_install(&mut self) {
+        let selected = self.server_list.server().into_iter().collect();
+        self.plan(selected, Task::Install);
+    }
+
+    /// Resolve in the background what `task` would do to each of `chosen`,
+    /// showing the plans as they come
+    fn plan(&mut self, chosen: Vec<usize>, task: Task) {
+        if chosen.is_empty() {
+            self.message = Some(Message::Info(format!(
+                "Nothing marked {}",
+                match task {
+                    Task::Update => "is installed",
+                    _ => "needs installing",
+                }
+            )));
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        self.plans.clear();
+        for i in chosen {
+            let server = self.servers[i].clone();
+            let plan = if server.is_available() {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let _ = tx.send((i, Planned::resolve(server.as_ref())));
+                });
+                None
+            } else {
+                Some(Err(String::from("unavailable on this platform")))
+            };
+            self.plans.push((i, plan));
+        }
+        self.pending_plans = Some(rx);
+        self.plan_task = task;
+        self.plan_marked = None;
+        self.input_mode = InputMode::Plan;
+    }
+
+    /// Servers of the plans that will run once confirmed, leaving out the
+    /// ones still resolving or that can't be done
+    pub fn runnable_plans(&self) -> impl Iterator<Item = usize> + '_ {
+        self.plans.iter().filter_map(|(i, plan)| match plan {
+            Some(Ok(planned)) if planned.missing.is_empty() => Some(*i),
+            _ => None,
+        })
+    }
+
+    /// Queue the task of the plans for every server that can run it
+    pub fn run_planned(&mut self) {
+        if self.plan_marked.is_some() {
+            self.marked.clear();
+        }
+        self.pending_plans = None;
+        for i in self.runnable_plans().collect::<Vec<_>>() {
+            self.enqueue(i, self.plan_task.clone());
+        }
+        self.plans.clear();
+        self.start_next();
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Apply what background tasks reported since the last tick
+    pub fn drain_reports(&mut self) {
+        if self.offline.is_none() && download::is_offline() {
+            self.offline = Some(Offline {
+                age: metadata::age(),
+                installable: self
+                    .servers
+                    .iter()
+                    .map(|server| servers::installable_offline(server.as_ref()))
+                    .collect(),
+            });
+        }
+        if let Some(checks) = self
+            .pending_checks
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        {
+            self.checks = Some(checks);
+            self.pending_checks = None;
+        }
+        if let Some(versions) = self
+            .pending_versions
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        {
+            self.versions = Some(versions.map(StatefulList::with_items));
+            self.pending_versions = None;
+        }
+        if let Some(rx) = &self.pending_plans {
+            loop {
+                match rx.try_recv() {
+                    Ok((server, plan)) => {
+                        if let Ok(Planned {
+                            plan:
+                                Plan {
+                                    size: Some(size), ..
+                                },
+                            ..
+                        }) = &plan
+                        {
+                            self.download_sizes[server] = Some(*size);
+                        }
+                        if let Some(entry) = self.plans.iter_mut().find(|(i, _)| *i == server) {
+                            entry.1 = Some(plan);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.pending_plans = None;
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(result) = self
+            .pending_registry
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        {
+            match result {
+                Ok(count) => self.log_as(
+                    "registry",
+                    Message::Info,
+                    &format!(
+                        "Fetched {} servers, servers added since this version show up after a restart",
+                        count
+                    ),
+                ),
+                Err(Error::Offline { .. }) => {}
+                Err(err) => self.log_as(
+                    "registry",
+                    Message::Warning,
+                    &format!("Couldn't refresh the registry: {}", err),
+                ),
+            }
+            self.pending_registry = None;
+        }
+        if let Some(rx) = &self.pending_disk {
+            loop {
+                match rx.try_recv() {
+                    Ok((server, size)) => self.disk_sizes[server] = Some(size),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.pending_disk = None;
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(rx) = &self.pending_latest {
+            let mut latest = Vec::new();
+            loop {
+                match rx.try_recv() {
+                    Ok(result) => latest.push(result),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.pending_latest = None;
+                        break;
+                    }
+                }
+            }
+            let checked = !latest.is_empty();
+            for (server, result) in latest {
+                match result {
+                    Ok(version) => self.latest_versions[server] = Some(version),
+                    // Most likely offline, which only matters once updating
+                    Err(err) => self.log(
+                        server,
+                        Message::Warning,
+                        &format!("Couldn't check for updates: {}", err),
+                    ),
+                }
+            }
+            if checked && (self.sort == SortMode::UpdatesFirst || self.tab == Tab::Updates) {
+                self.search();
+            }
+        }
+        while let Ok((server, report)) = self.reports.try_recv() {
+            match &report {
+                Report::Started | Report::Progress { .. } => {}
+                Report::Log(text) | Report::Completed(text) | Report::Cancelled(text) => {
+                    self.log(server, Message::Info, text)
+                }
+                Report::Warning(text) => self.log(server, Message::Warning, text),
+                Report::Failed(failure) => {
+                    self.log(server, Message::Error, &failure.message);
+                    for cause in &failure.causes {
+                        self.log(server, Message::Error, &format!("caused by: {}", cause));
+                    }
+                    if let Some(hint) = &failure.hint {
+                        self.log(server, Message::Error, &format!("hint: {}", hint));
+                    }
+                }
+            }
+            match report {
+                Report::Started => {
+                    if let Some(job) = self.queue.running_mut(server) {
+                        job.started = Some(Instant::now());
+                    }
+                }
+                Report::Progress { done, total } => {
+                    if total.is_some() {
+                        self.download_sizes[server] = total;
+                    }
+                    if let Some(job) = self.queue.running_mut(server) {
+                        job.progress = Some((done, total));
+                    }
+                }
+                Report::Log(line) => {
+                    if let Some(job) = self.queue.running_mut(server) {
+                        job.log = Some(line);
+                    }
+                }
+                Report::Warning(text) => {
+                    if let Some(job) = self.queue.running_mut(server) {
+                        job.warnings.push(text);
+                    }
+                }
+                Report::Completed(text) => {
+                    let warnings = self
+                        .queue
+                        .running_mut(server)
+                        .map(|job| std::mem::take(&mut job.warnings))
+                        .unwrap_or_default();
+                    self.finish(server, JobState::Done);
+                    self.message = Some(if warnings.is_empty() {
+                        Message::Info(text)
+                    } else {
+                        Message::Warning(format!("{}, warning: {}", text, warnings.join("; ")))
+                    });
+                }
+                Report::Failed(failure) => {
+                    self.finish(server, JobState::Failed);
+                    self.message = Some(Message::Error(format!(
+                        "{}, see {} for details",
+                        failure.message.lines().next().unwrap_or_default(),
+                        logfile::path().display()
+                    )));
+                    // Popping up while typing would swallow the next keys
+                    if let InputMode::Normal = self.input_mode {
+                        self.input_mode = InputMode::Error;
+                    }
+                    self.error = Some(failure);
+                }
+                Report::Cancelled(text) => {
+                    self.finish(server, JobState::Cancelled);
+                    self.message = Some(Message::Info(text));
+                }
+            }
+        }
+    }
+
+    /// Clear the info message once it's been shown long enough, returning
+    /// whether it got cleared
+    pub fn expire_message(&mut self) -> bool {
+        match (&self.message, &self.message_seen) {
+            (Some(Message::Info(text)), Some((seen, at))) if text == seen => {
+                if at.elapsed() >= MESSAGE_TIMEOUT {
+                    self.message = None;
+                    self.message_seen = None;
+                    return true;
+                }
+            }
+            (Some(Message::Info(text)), _) => {
+                self.message_seen = Some((text.clone(), Instant::now()))
+            }
+            _ => self.message_seen = None,
+        }
+        false
+    }
+
+    /// Whether a job is running or something is being fetched in the
+    /// background, so the screen may change without input
+    pub fn is_busy(&self) -> bool {
+        self.queue.running().next().is_some()
+            || self.pending_checks.is_some()
+            || self.pending_versions.is_some()
+            || self.pending_plans.is_some()
+            || self.pending_registry.is_some()
+            || self.pending_latest.is_some()
+            || self.pending_disk.is_some()
+            // Until the search box is drawn without its flash
+            || self
+                .search_flash
+                .is_some_and(|at| at.elapsed() < Duration::from_secs(1))
+    }
+
+    /// Add `text` to the log pane as coming from `server`
+    fn log(&mut self, server: usize, kind: fn(String) -> Message, text: &str) {
+        let name = self.servers[server].name().to_string();
+        self.log_as(&name, kind, text)
+    }
+
+    /// Add `text` to the log pane as coming from `source`, one line per
+    /// line of text
+    fn log_as(&mut self, source: &str, kind: fn(String) -> Message, text: &str) {
+        for line in text.lines() {
+            if self.log.len() == LOG_LIMIT {
+                self.log.pop_front();
+            }
+            self.log.push_back(LogLine {
+                server: source.to_string(),
+                message: kind(line.to_string()),
+            });
+            // Keep the lines in view where they are
+            if self.log_scroll > 0 {
+                self.log_scroll = (self.log_scroll + 1).min(self.log.len().saturating_sub(1));
+            }
+        }
+    }
+
+    /// Scroll the log pane up by `lines`, or down when negative
+    pub fn scroll_log(&mut self, lines: isize) {
+        self.log_scroll = self
+            .log_scroll
+            .saturating_add_signed(lines)
+            .min(self.log.len().saturating_sub(1));
+    }
+
+    fn finish(&mut self, server: usize, state: JobState) {
+        self.queue.finish(server, state);
+        self.refresh_statuses();
+        self.resort();
+        self.start_next();
+    }
+
+    /// Check the latest versions of the installed servers in the
+    /// background
+    fn check_updates(&mut self) {
+        let installed = self
+            .servers
+            .iter()
+            .enumerate()
+            .filter(|&(i, server)| {
+                matches!(self.statuses[i], Status::Installed) && !server.is_custom()
+            })
+            .map(|(i, server)| (i, server.clone()))
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        updates::spawn(installed, tx);
+        self.pending_latest = Some(rx);
+    }
+
+    /// Installed servers with a newer version out, leaving out the held,
+    /// custom and external ones
+    pub fn outdated(&self) -> Vec<usize> {
+        (0..self.servers.len())
+            .filter(|&i| self.has_update(i))
+            .collect()
+    }
+
+    /// Whether the server at `i` has a newer version out it would update to
+    fn has_update(&self, i: usize) -> bool {
+        let newer = match (&self.installed_versions[i], &self.latest_versions[i]) {
+            (Some(installed), Some(latest)) => version::is_newer(latest, installed),
+            _ => false,
+        };
+        newer && !self.held[i] && !self.adopted[i]
+    }
+
+    /// Queue an update of every outdated server
+    pub fn update_all(&mut self) {
+        let outdated = self.outdated();
+        if outdated.is_empty() {
+            self.message = Some(Message::Info(String::from("Everything is up to date")));
+            return;
+        }
+        for i in outdated {
+            self.enqueue(i, Task::Update);
+        }
+        self.start_next();
+    }
+
+    /// Look for the servers that aren't installed on `PATH`, saying how
+    /// many were found
+    pub fn rescan(&mut self) {
+        self.scan_path();
+        let found = self.found.iter().flatten().count();
+        self.message = Some(Message::Info(format!(
+            "Found {} servers installed outside lsp_installer",
+            found
+        )));
+    }
+
+    fn scan_path(&mut self) {
+        self.found = self
+            .servers
+            .iter()
+            .zip(&self.statuses)
+            .map(|(server, status)| match status {
+                Status::Installed => None,
+                _ => external::find(server.as_ref()),
+            })
+            .collect();
+    }
+
+    /// Adopt the external install found for the selected server, or stop
+    /// tracking it once adopted
+    pub fn adopt(&mut self) {
+        let Some(i) = self.server_list.server() else {
+            return;
+        };
+        let name = self.servers[i].name();
+        let _lock = match lock::acquire("adopt") {
+            Ok(lock) => lock,
+            Err(err) => {
+                self.message = Some(Message::Error(format!("{}, try again once it's done", err)));
+                return;
+            }
+        };
+        self.message = Some(match (self.servers[i].external(), &self.found[i]) {
+            (Some(bin), _) => match manifest::forget(name) {
+                Ok(()) => Message::Info(format!("Stopped tracking {} at {}", name, bin.display())),
+                Err(err) => Message::Error(format!("Failed to update the manifest: {}", err)),
+            },
+            (None, Some(bin)) => match external::adopt(self.servers[i].as_ref(), bin) {
+                Ok(version) => {
+                    Message::Info(format!("Adopted {} {} at {}", name, version, bin.display()))
+                }
+                Err(err) => Message::Error(format!("Failed to adopt {}: {}", name, err)),
+            },
+            (None, None) => Message::Error(format!("{} wasn't found on PATH", name)),
+        });
+        self.refresh_statuses();
+        self.scan_path();
+    }
+
+    pub fn selected_status(&self) -> Option<Status> {
+        self.server_list.server().map(|i| self.statuses[i])
+    }
+
+    fn refresh_statuses(&mut self) {
+        self.statuses = self.servers.iter().map(|server| server.status()).collect();
+        self.installed_versions = self
+            .servers
+            .iter()
+            .map(|server| server.installed_version())
+            .collect();
+        self.reported_versions = self
+            .servers
+            .iter()
+            .map(|server| server.reported_version())
+            .collect();
+        let global = manifest::global();
+        self.global_versions = self
+            .servers
+            .iter()
+            .map(|server| {
+                global
+                    .get(server.name())
+                    .map(|record| record.version.clone())
+            })
+            .collect();
+        self.held = self.servers.iter().map(|server| server.is_held()).collect();
+        self.last_used = self
+            .servers
+            .iter()
+            .map(|server| server.last_used())
+            .collect();
+        self.previous_versions = self
+            .servers
+            .iter()
+            .map(|server| server.previous_version())
+            .collect();
+        self.adopted = self
+            .servers
+            .iter()
+            .map(|server| server.external().is_some())
+            .collect();
+        self.launchers = self
+            .servers
+            .iter()
+            .map(|server| server.launcher())
+            .collect();
+        self.details = self.servers.iter().map(|server| server.details()).collect();
+        self.disk_sizes = self
+            .servers
+            .iter()
+            .map(|server| server.disk_size())
+            .collect();
+    }
+
+    /// Measure in the background the installs with no size recorded,
+    /// keeping it in the manifest
+    fn measure_disk(&mut self) {
+        let unmeasured: Vec<_> = self
+            .servers
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| {
+                matches!(self.statuses[i], Status::Installed)
+                    && !self.adopted[i]
+                    && self.disk_sizes[i].is_none()
+            })
+            .map(|(i, server)| (i, server.clone()))
+            .collect();
+        if unmeasured.is_empty() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for (i, server) in unmeasured {
+                let size = disk::size(&server.dir());
+                // Measured again next time when another process is changing
+                // the manifest
+                if let Ok(_lock) = lock::acquire("record disk sizes") {
+                    let _ = manifest::set_disk_size(server.name(), size);
+                }
+                if tx.send((i, size)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.pending_disk = Some(rx);
+    }
+
+    /// Bytes taken by every install lsp_installer made
+    pub fn installed_disk(&self) -> u64 {
+        // Servers sharing an install directory count it once
+        let mut dirs = HashSet::new();
+        self.disk_sizes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, size)| Some((i, (*size)?)))
+            .filter(|&(i, _)| dirs.insert(self.servers[i].dir()))
+            .map(|(_, size)| size)
+            .sum()
+    }
+
+    /// Bytes the queued and running installs and updates download, as far
+    /// as their sizes are known
+    fn pending_downloads(&self) -> u64 {
+        self.queue
+            .unfinished_jobs()
+            .filter(|job| matches!(job.task, Task::Install | Task::Pin(_) | Task::Update))
+            .filter_map(|job| self.download_sizes[job.server])
+            .sum()
+    }
+
+    /// Focus `pane` and select the item shown `row` rows below its top,
+    /// returning whether there was one
+    pub fn click(&mut self, pane: Focus, row: usize) -> bool {
+        self.focus = pane;
+        let list = match pane {
+            Focus::Languages => self.language_list.at_row(row),
+            Focus::Servers => self.server_list.at_row(row),
+        };
+        let Some(i) = list else {
+            return false;
+        };
+        match pane {
+            Focus::Languages => {
+                self.language_list.state.select(Some(i));
+                self.select_language();
+            }
+            Focus::Servers => return self.server_list.select(i),
+        }
+        true
+    }
+
+    /// Rows of the focused pane, a page for the paging keys
+    pub fn page(&self) -> isize {
+        let height = match self.focus {
+            Focus::Languages => self.language_list.height,
+            Focus::Servers => self.server_list.height,
+        };
+        height.max(1) as isize
+    }
+
+    /// Move the selection `delta` items in the focused pane, see
+    /// `StatefulList::jump`
+    pub fn jump(&mut self, delta: isize) {
+        match self.focus {
+            Focus::Languages => {
+                self.language_list.jump(delta);
+                self.select_language();
+            }
+            Focus::Servers => self.server_list.jump(delta),
+        }
+    }
+
+    /// Leave the search with the best matching server selected, or flash
+    /// the search box when nothing matches
+    pub fn pick_match(&mut self) {
+        if self.server_list.server().is_none() {
+            self.search_flash = Some(Instant::now());
+            return;
+        }
+        self.input_mode = InputMode::Normal;
+        self.focus = Focus::Servers;
+        if self.settings.enter_installs.0 {
+            self.start(Task::Install);
+        }
+    }
+
+    /// Move the selection down in the focused pane
+    pub fn next(&mut self) {
+        match self.focus {
+            Focus::Languages => {
+                self.language_list.next();
+                self.select_language();
+            }
+            Focus::Servers => self.server_list.next(),
+        }
+    }
+
+    /// Move the selection up in the focused pane
+    pub fn previous(&mut self) {
+        match self.focus {
+            Focus::Languages => {
+                self.language_list.previous();
+                self.select_language();
+            }
+            Focus::Servers => self.server_list.previous(),
+        }
+    }
+
+    /// Fill the server pane with the servers of the selected language
+    fn select_language(&mut self) {
+        let mut servers = match self.language_list.selected() {
+            Some(LanguageRow::Suggested) => self.suggested_servers(),
+            Some(LanguageRow::Favorites) => self.favorite_servers(),
+            Some(LanguageRow::Language(language)) => self.servers_for(language),
+            None => Vec::new(),
+        };
+        // Servers whose name matches the search come first in their group
+        servers.sort_by_key(|&i| {
+            (
+                self.servers[i].category(),
+                Reverse(self.name_score(i)),
+                self.sort_key(i),
+            )
+        });
+        let grouped = servers
+            .windows(2)
+            .any(|pair| self.servers[pair[0]].category() != self.servers[pair[1]].category());
+
+        let mut rows = Vec::new();
+        for i in servers {
+            let category = self.servers[i].category();
+            if grouped
+                && rows
+                    .last()
+                    .is_none_or(|&row| self.category_of(row) != category)
+            {
+                rows.push(ServerRow::Header(category));
+            }
+            rows.push(ServerRow::Server(i));
+        }
+        self.server_list.replace(rows);
+    }
+
+    fn category_of(&self, row: ServerRow) -> Category {
+        match row {
+            ServerRow::Header(category) => category,
+            ServerRow::Server(i) => self.servers[i].category(),
+        }
+    }
+
+    /// Indices of the servers handling `language`, among those shown
+    pub fn servers_for(&self, language: &str) -> Vec<usize> {
+        (0..self.servers.len())
+            .filter(|&i| self.servers[i].languages().iter().any(|l| l == language))
+            .filter(|&i| self.is_shown(i))
+            .collect()
+    }
+
+    /// Whether the server at `i` is in the category and the tab shown
+    fn is_shown(&self, i: usize) -> bool {
+        let in_tab = match self.tab {
+            Tab::All => true,
+            Tab::Installed => self.statuses[i] == Status::Installed,
+            Tab::Updates => self.has_update(i),
+        };
+        in_tab
+            && self
+                .category
+                .is_none_or(|category| self.servers[i].category() == category)
+    }
+
+    /// Number of servers the tab would show, the category aside
+    pub fn tab_count(&self, tab: Tab) -> usize {
+        match tab {
+            Tab::All => self.servers.len(),
+            Tab::Installed => self
+                .statuses
+                .iter()
+                .filter(|&&status| status == Status::Installed)
+                .count(),
+            Tab::Updates => self.outdated().len(),
+        }
+    }
+
+    /// Show `tab`, back where its selection was when it was left
+    pub fn switch_tab(&mut self, tab: Tab) {
+        if tab == self.tab {
+            return;
+        }
+        self.places[self.tab.index()] = Place {
+            language: self.language_list.selected().cloned(),
+            server: self.server_list.server(),
+        };
+        self.tab = tab;
+        self.search();
+
+        let place = self.places[tab.index()].clone();
+        if let Some(row) = place.language.and_then(|language| {
+            self.language_list
+                .items
+                .iter()
+                .position(|row| *row == language)
+        }) {
+            self.language_list.state.select(Some(row));
+            self.select_language();
+        }
+        if let Some(row) = place.server.and_then(|i| {
+            self.server_list
+                .items
+                .iter()
+                .position(|&row| row == ServerRow::Server(i))
+        }) {
+            self.server_list.select(row);
+        }
+    }
+
+    /// Show the tab after the current one, the first after the last
+    pub fn next_tab(&mut self) {
+        let next = Tab::ALL[(self.tab.index() + 1) % Tab::ALL.len()];
+        self.switch_tab(next);
+    }
+
+    pub fn is_favorite(&self, i: usize) -> bool {
+        self.config
+            .favorites
+            .iter()
+            .any(|name| name == self.servers[i].name())
+    }
+
+    /// Indices of the favorite servers, among those shown
+    pub fn favorite_servers(&self) -> Vec<usize> {
+        (0..self.servers.len())
+            .filter(|&i| self.is_favorite(i) && self.is_shown(i))
+            .collect()
+    }
+
+    /// Find the servers whose project files are in the current directory,
+    /// or a few levels below it
+    fn scan_project(&mut self) {
+        let Ok(root) = env::current_dir() else {
+            return;
+        };
+        let names = project::file_names(&root);
+        self.project = (0..self.servers.len())
+            .filter(|&i| {
+                self.servers[i]
+                    .project_files()
+                    .values()
+                    .flatten()
+                    .any(|pattern| names.iter().any(|name| project::matches(pattern, name)))
+            })
+            .collect();
+        self.search();
+        // Starting on the suggestions, which sit above everything else
+        if self.language_list.items.first() == Some(&LanguageRow::Suggested) {
+            self.language_list.select(0);
+            self.select_language();
+        }
+    }
+
+    /// Indices of the servers suggested for the project that aren't
+    /// installed, on `PATH` either, among those shown
+    pub fn suggested_servers(&self) -> Vec<usize> {
+        self.project
+            .iter()
+            .copied()
+            .filter(|&i| self.statuses[i] == Status::NotInstalled && self.found[i].is_none())
+            .filter(|&i| self.is_shown(i))
+            .collect()
+    }
+
+    /// Show what installing every server suggested for the project would
+    /// do, waiting for a confirmation
+    pub fn install_suggested(&mut self) {
+        let suggested = self.suggested_servers();
+        if suggested.is_empty() {
+            self.message = Some(Message::Info(String::from(
+                "Every server suggested for this project is installed",
+            )));
+            return;
+        }
+        self.plan(suggested, Task::Install);
+    }
+
+    /// Make the selected server a favorite or stop it being one, saving
+    /// the favorites to the config file
+    pub fn toggle_favorite(&mut self) {
+        let Some(i) = self.server_list.server() else {
+            return;
+        };
+        let name = self.servers[i].name().to_string();
+        let favorite = !self.is_favorite(i);
+        if favorite {
+            self.config.favorites.push(name.clone());
+            self.config.favorites.sort();
+        } else {
+            self.config.favorites.retain(|known| *known != name);
+        }
+
+        // Leaving the favorites, the server stays selected under its language
+        let follow = if !favorite && self.language_list.selected() == Some(&LanguageRow::Favorites)
+        {
+            self.servers[i].languages().to_vec()
+        } else {
+            Vec::new()
+        };
+        self.search();
+        let row = self.language_list.items.iter().position(
+            |row| matches!(row, LanguageRow::Language(language) if follow.contains(language)),
+        );
+        if let Some(row) = row {
+            self.language_list.state.select(Some(row));
+            self.select_language();
+        }
+        if let Some(row) = self
+            .server_list
+            .items
+            .iter()
+            .position(|&row| row == ServerRow::Server(i))
+        {
+            self.server_list.select(row);
+        }
+
+        self.message = Some(match self.config.save() {
+            Ok(()) if favorite => Message::Info(format!("Added {} to the favorites", name)),
+            Ok(()) => Message::Info(format!("Removed {} from the favorites", name)),
+            Err(err) => Message::Error(format!(
+                "Failed to save {}: {}",
+                Config::path().display(),
+                err
+            )),
+        });
+    }
+
+    /// Where the server at `i` goes in the sort mode, lower first
+    fn sort_key(&self, i: usize) -> (bool, Reverse<Option<&str>>) {
+        match self.sort {
+            SortMode::Alphabetical => (false, Reverse(None)),
+            SortMode::InstalledFirst => (self.statuses[i] != Status::Installed, Reverse(None)),
+            SortMode::RecentlyUsed => (false, Reverse(self.last_used[i].as_deref())),
+            SortMode::UpdatesFirst => (!self.has_update(i), Reverse(None)),
+        }
+    }
+
+    /// Sort the panes the next way, see `SortMode`
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        self.search();
+    }
+
+    /// Sort and filter the panes again after what they go by changed
+    fn resort(&mut self) {
+        if self.sort != SortMode::Alphabetical || self.tab != Tab::All {
+            self.search();
+        }
+    }
+
+    /// Show the next category alone, or every category again after the last
+    pub fn cycle_category(&mut self) {
+        let next = match self.category {
+            None => Some(0),
+            Some(category) => Category::ALL
+                .iter()
+                .position(|&known| known == category)
+                .map(|i| i + 1)
+                .filter(|&i| i < Category::ALL.len()),
+        };
+        self.category = next.map(|i| Category::ALL[i]);
+        self.search();
+        self.message = Some(Message::Info(match self.category {
+            Some(category) => format!("Showing {} only", category.title().to_lowercase()),
+            None => String::from("Showing every category"),
+        }));
+    }
+
+    /// Filter the language pane with the search input
+    pub fn search(&mut self) {
+        let matches = self.matching_languages();
+        let mut languages: Vec<LanguageRow> = matches
+            .iter()
+            .map(|(language, _)| LanguageRow::Language(language.clone()))
+            .collect();
+        if self.input.is_empty() && !self.favorite_servers().is_empty() {
+            languages.insert(0, LanguageRow::Favorites);
+        }
+        if self.input.is_empty() && !self.suggested_servers().is_empty() {
+            languages.insert(0, LanguageRow::Suggested);
+        }
+        self.matches = matches.into_iter().collect();
+        self.language_list.replace(languages);
+        self.select_language();
+    }
+
+    /// Text searched for, and whether it's restricted to language names by
+    /// starting with `lang:`
+    pub fn query(&self) -> (&str, bool) {
+        match self.input.strip_prefix(LANGUAGE_PREFIX) {
+            Some(query) => (query.trim_start(), true),
+            None => (self.input.as_str(), false),
+        }
+    }
+
+    /// Empty the search input, showing every language again
+    pub fn clear_search(&mut self) {
+        self.input.clear();
+        self.cursor = 0;
+        self.search();
+    }
+
+    /// Type `c` at the cursor
+    pub fn insert(&mut self, c: char) {
+        self.input.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        self.search();
+    }
+
+    /// Move the cursor one grapheme to the left, or to the right when
+    /// `right` is set
+    pub fn move_cursor(&mut self, right: bool) {
+        self.cursor = if right {
+            self.next_boundary()
+        } else {
+            self.previous_boundary()
+        };
+    }
+
+    /// Put the cursor at the start of the input, or its end when `end` is set
+    pub fn cursor_to(&mut self, end: bool) {
+        self.cursor = if end { self.input.len() } else { 0 };
+    }
+
+    /// Delete the grapheme before the cursor
+    pub fn delete_back(&mut self) {
+        let start = self.previous_boundary();
+        self.delete(start, self.cursor);
+    }
+
+    /// Delete the grapheme under the cursor
+    pub fn delete_forward(&mut self) {
+        let end = self.next_boundary();
+        self.delete(self.cursor, end);
+    }
+
+    /// Delete the word before the cursor, with the spaces following it
+    pub fn delete_word(&mut self) {
+        let before = &self.input[..self.cursor];
+        let trimmed = before.trim_end();
+        let start = trimmed
+            .grapheme_indices(true)
+            .rev()
+            .find(|(_, grapheme)| grapheme.chars().all(char::is_whitespace))
+            .map_or(0, |(i, grapheme)| i + grapheme.len());
+        self.delete(start, self.cursor);
+    }
+
+    fn delete(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        self.input.replace_range(start..end, "");
+        self.cursor = start;
+        self.search();
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.input[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.input[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// Languages matching the search, with what they matched through,
+    /// those matching a server name first, then their own name, then a
+    /// description. Aliases count as the name they stand for.
+    fn matching_languages(&self) -> Vec<(String, Match)> {
+        let (query, languages_only) = self.query();
+        let mut matches: Vec<(Match, i64, &String)> = self
+            .languages
+            .iter()
+            .filter_map(|language| {
+                let filtered = self.category.is_some() || self.tab != Tab::All;
+                if filtered && self.servers_for(language).is_empty() {
+                    return None;
+                }
+                let by_language = || {
+                    let by_alias = self
+                        .language_aliases
+                        .get(language)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|alias| {
+                            Some((Match::Alias(alias.clone()), score(query, alias)?))
+                        });
+                    // The language itself wins a tie with its aliases
+                    score(query, language)
+                        .map(|score| (Match::Language, score))
+                        .into_iter()
+                        .chain(by_alias)
+                        .reduce(|best, next| if next.1 > best.1 { next } else { best })
+                };
+                if languages_only || query.is_empty() {
+                    return by_language().map(|(source, score)| (source, score, language));
+                }
+                let servers = self.servers_for(language);
+                let by_name = servers
+                    .iter()
+                    .filter_map(|&i| {
+                        let name = self.servers[i].name();
+                        self.name_score(i).map(|score| (score, name))
+                    })
+                    .max_by_key(|&(score, _)| score)
+                    .map(|(score, name)| (Match::Server(name.to_string()), score));
+                // A language spelled out exactly, like `ml` for OCaml, isn't
+                // shadowed by its servers matching loosely
+                by_language()
+                    .filter(|&(_, score)| score >= EXACT)
+                    .or(by_name)
+                    .or_else(by_language)
+                    .or_else(|| {
+                        servers
+                            .iter()
+                            .any(|&i| describes(self.servers[i].description(), query))
+                            .then_some((Match::Description, 0))
+                    })
+                    .map(|(source, score)| (source, score, language))
+            })
+            .collect();
+
+        // Stable, so equally good matches stay in alphabetical order.
+        // Spelling a name exactly beats the order of what matched.
+        matches.sort_by(|a, b| {
+            let rank = |(source, score): (&Match, i64)| {
+                let rank = match source {
+                    Match::Server(_) => 0,
+                    Match::Language | Match::Alias(_) => 1,
+                    Match::Description => 2,
+                };
+                (score < EXACT, rank)
+            };
+            rank((&a.0, a.1))
+                .cmp(&rank((&b.0, b.1)))
+                .then(b.1.cmp(&a.1))
+        });
+        // Only without a search, its scores winning otherwise
+        if query.is_empty() {
+            matches.sort_by_cached_key(|&(_, _, language)| {
+                self.servers_for(language)
+                    .into_iter()
+                    .map(|i| self.sort_key(i))
+                    .min()
+            });
+        }
+        matches
+            .into_iter()
+            .map(|(source, _, language)| (language.clone(), source))
+            .collect()
+    }
+
+    /// Best score of the search against the name or an alias of the server
+    /// at `i`
+    fn name_score(&self, i: usize) -> Option<i64> {
+        let (query, _) = self.query();
+        let server = &self.servers[i];
+        std::iter::once(server.name())
+            .chain(server.aliases().iter().map(String::as_str))
+            .filter_map(|name| score(query, name))
+            .max()
+            .map(|score| {
+                if self.is_favorite(i) {
+                    score + FAVORITE
+                } else {
+                    score
+                }
+            })
+    }
+}
+
+/// Extra score of a name or alias spelled exactly like the search, so `js`
+/// lists javascript before json
+const EXACT: i64 = 1000;
+
+/// Extra score of a favorite server, ahead of the others matching as well
+const FAVORITE: i64 = 8;
+
+/// Score of `query` against `candidate`, a name or alias
+fn score(query: &str, candidate: &str) -> Option<i64> {
+    let (score, _) = fuzzy::find(query, candidate)?;
+    let exact = !query.is_empty() && query.to_lowercase() == candidate.to_lowercase();
+    Some(if exact { score + EXACT } else { score })
+}
+
+/// Whether a word of `description` starts with `query`, ignoring case
+fn describes(description: &str, query: &str) -> bool {
+    let query = query.to_lowercase();
+    description
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.to_lowercase().starts_with(&query))
+}
+
+impl App {
+    /// Load the registry and check installed servers. `warnings` are the
+    /// problems found while loading `config`.
+    pub fn new(config: Config, settings: Settings, mut warnings: Vec<String>) -> App {
+        let (servers, registry_warnings) = servers::registry();
+        warnings.extend(registry_warnings);
+        let mut app = App::with_servers(config, settings, servers, warnings);
+        app.ascii_spinner = std::env::var("TERM").is_ok_and(|term| term == "linux");
+        app.scan_path();
+        if app.settings.suggest.0 {
+            app.scan_project();
+        }
+        let sizes = metadata::download_sizes();
+        app.download_sizes = app
+            .servers
+            .iter()
+            .map(|server| sizes.get(server.name()).copied())
+            .collect();
+        app.measure_disk();
+        if app.settings.check_updates.0 {
+            app.check_updates();
+            let (tx, rx) = mpsc::channel();
+            remote::refresh(tx);
+            app.pending_registry = Some(rx);
+        }
+        app
+    }
+
+    /// App offering `servers` alone, without looking for them on `PATH` or
+    /// checking for updates, so it can be drawn in a known state
+    pub fn with_servers(
+        config: Config,
+        settings: Settings,
+        mut servers: Vec<Arc<dyn Server>>,
+        mut warnings: Vec<String>,
+    ) -> App {
+        let jobs = settings.jobs.0;
+        let sort = settings.sort.0;
+        // NO_COLOR wins over any color
+        let colors = match settings.theme.1 {
+            Origin::Env(_) => BTreeMap::new(),
+            _ => config.colors.clone(),
+        };
+        let (theme, theme_warnings) = Theme::load(&settings.theme.0, &colors);
+        warnings.extend(theme_warnings);
+        let (keys, key_warnings) = Keymap::load(&config.keys);
+        warnings.extend(key_warnings);
+        let (sender, reports) = mpsc::channel();
+
+        servers.sort_by(|a, b| a.name().cmp(b.name()));
+        let server_count = servers.len();
+
+        let mut languages: Vec<String> = servers
+            .iter()
+            .flat_map(|server| server.languages().iter().cloned())
+            .collect();
+        languages.sort();
+        languages.dedup();
+
+        let mut language_aliases: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for server in &servers {
+            for (language, aliases) in server.language_aliases() {
+                let known = language_aliases.entry(language.clone()).or_default();
+                for alias in aliases {
+                    if !known.iter().any(|known| known.eq_ignore_ascii_case(alias)) {
+                        known.push(alias.clone());
+                    }
+                }
+            }
+        }
+
+        let mut app = App {
+            input: String::new(),
+            cursor: 0,
+            input_mode: InputMode::Normal,
+            focus: Focus::Languages,
+            config,
+            settings,
+            theme,
+            keys,
+            language_list: StatefulList::with_items(Vec::new()),
+            matches: BTreeMap::new(),
+            server_list: StatefulList::with_items(Vec::new())
+                .skipping(|row| matches!(row, ServerRow::Server(_))),
+            category: None,
+            tab: Tab::All,
+            places: Default::default(),
+            clipboard: None,
+            sort,
+            marked: HashSet::new(),
+            queue: Queue::new(jobs),
+            servers,
+            languages,
+            language_aliases,
+            statuses: Vec::new(),
+            installed_versions: Vec::new(),
+            global_versions: Vec::new(),
+            held: Vec::new(),
+            last_used: Vec::new(),
+            previous_versions: Vec::new(),
+            adopted: Vec::new(),
+            launchers: Vec::new(),
+            details: Vec::new(),
+            found: vec![None; server_count],
+            project: Vec::new(),
+            reported_versions: Vec::new(),
+            latest_versions: vec![None; server_count],
+            download_sizes: vec![None; server_count],
+            disk_sizes: Vec::new(),
+            message: if warnings.is_empty() {
+                None
+            } else {
+                Some(Message::Error(warnings.join("; ")))
+            },
+            message_seen: None,
+            error: None,
+            offline: None,
+            cache: (0, PathBuf::new()),
+            checks: None,
+            pending_checks: None,
+            dry_run: false,
+            plans: Vec::new(),
+            pending_plans: None,
+            plan_task: Task::Install,
+            plan_marked: None,
+            versions_of: 0,
+            local_file: String::new(),
+            versions: None,
+            pending_versions: None,
+            pending_latest: None,
+            pending_disk: None,
+            pending_registry: None,
+            search_flash: None,
+            last_click: None,
+            // Startup problems stay readable once the message is replaced
+            log: warnings
+                .iter()
+                .map(|warning| LogLine {
+                    server: String::from("config"),
+                    message: Message::Warning(warning.clone()),
+                })
+                .collect(),
+            show_log: false,
+            log_scroll: 0,
+            ticks: 0,
+            now: Instant::now(),
+            ascii_spinner: false,
+            reports,
+            sender,
+            workers: worker::Pool::default(),
+            lock: None,
+        };
+        app.refresh_statuses();
+        app.search();
+        app
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::keys::Action;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::sync::OnceLock;
+
+    /// Embedded servers in `names`, offline and over an empty install root
+    /// shared by the tests
+    pub fn registry(names: &[&str]) -> Vec<Arc<dyn Server>> {
+        static ROOT: OnceLock<PathBuf> = OnceLock::new();
+        ROOT.get_or_init(|| {
+            let root = tempfile::tempdir().unwrap().keep();
+            paths::configure_root(root.clone());
+            download::set_offline();
+            root
+        });
+        let (servers, _) = servers::registry();
+        servers
+            .into_iter()
+            .filter(|server| !server.is_custom() && names.contains(&server.name()))
+            .collect()
+    }
+
+    /// App listing the servers of `registry(names)`
+    pub fn app(names: &[&str]) -> App {
+        let config = Config::default();
+        let settings = Settings::resolve(&config, None, None, None);
+        App::with_servers(config, settings, registry(names), Vec::new())
+    }
+
+    pub fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn exact_names_rank_first() {
+        // `js` matches both, the one spelled like it winning by far
+        let exact = score("js", "js").unwrap();
+        let prefix = score("js", "json").unwrap();
+        assert!(exact > prefix + FAVORITE);
+        assert_eq!(score("JS", "js"), Some(exact));
+        assert_eq!(score("", "json"), Some(0));
+        assert_eq!(score("jsx", "json"), None);
+    }
+
+    #[test]
+    fn moving_through_an_empty_search_result() {
+        let mut app = app(&["gopls", "pyright"]);
+        app.input = String::from("nothing matches this");
+        app.search();
+        assert!(app.language_list.items.is_empty());
+        assert!(app.server_list.items.is_empty());
+
+        for focus in [Focus::Languages, Focus::Servers] {
+            app.focus = focus;
+            for code in [KeyCode::Down, KeyCode::Up, KeyCode::PageDown, KeyCode::End] {
+                assert_eq!(app.handle_key(key(code)), None);
+            }
+            assert_eq!(app.language_list.state.selected(), None);
+            assert_eq!(app.server_list.server(), None);
+        }
+    }
+
+    #[test]
+    fn reselects_the_same_item() {
+        let old = ["go", "python", "rust"];
+        assert_eq!(reselected(Some(2), &old, &["python", "rust"]), Some(1));
+        // Falling back to the first item once the selected one is gone
+        assert_eq!(reselected(Some(0), &old, &["python", "rust"]), Some(0));
+        assert_eq!(reselected(None, &old, &["rust"]), Some(0));
+        assert_eq!(reselected(Some(1), &old, &[]), None);
+    }
+
+    #[test]
+    fn searching_keeps_the_selected_language() {
+        let mut app = app(&["gopls", "pyright", "rust-analyzer"]);
+        let rust = LanguageRow::Language(String::from("rust"));
+        app.focus = Focus::Languages;
+        app.handle_action(Action::Bottom);
+        assert!(app.language_list.selected() == Some(&rust));
+
+        app.handle_key(key(KeyCode::Char('e')));
+        app.handle_key(key(KeyCode::Char('r')));
+        assert!(app.language_list.items.len() < 3);
+        assert!(app.language_list.selected() == Some(&rust));
+        assert_eq!(
+            app.server_list.server().map(|i| app.servers[i].name()),
+            Some("rust-analyzer")
+        );
+
+        app.handle_key(key(KeyCode::Backspace));
+        assert_eq!(app.language_list.items.len(), 3);
+        assert!(app.language_list.selected() == Some(&rust));
+    }
+
+    #[test]
+    fn language_aliases_find_their_servers() {
+        let mut app = app(&[
+            "clangd",
+            "gopls",
+            "rust-analyzer",
+            "typescript-language-server",
+            "vscode-json-language-server",
+        ]);
+        let cases = [
+            ("js", "javascript", "typescript-language-server"),
+            ("c++", "cpp", "clangd"),
+            ("golang", "go", "gopls"),
+        ];
+        for (query, language, server) in cases {
+            app.input = String::from(query);
+            app.search();
+            let first = app.language_list.items.first();
+            assert!(
+                first == Some(&LanguageRow::Language(String::from(language))),
+                "{} should list {} first",
+                query,
+                language
+            );
+            assert!(app.matches.get(language) == Some(&Match::Alias(String::from(query))));
+            let selected = app.server_list.server().map(|i| app.servers[i].name());
+            assert_eq!(selected, Some(server), "{}", query);
+        }
+    }
+
+    #[test]
+    fn marks_stay_until_the_plan_runs() {
+        let mut app = app(&["gopls", "pyright"]);
+        app.focus = Focus::Servers;
+        app.handle_key(key(KeyCode::Char(' ')));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.input_mode, InputMode::Plan));
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.marked.len(), 1);
+
+        app.handle_key(key(KeyCode::Enter));
+        app.handle_key(key(KeyCode::Char('y')));
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(app.marked.is_empty());
+    }
+
+    /// Server rows under two headers: `Header, 0, 1, Header, 2`
+    fn grouped() -> StatefulList<ServerRow> {
+        StatefulList::with_items(vec![
+            ServerRow::Header(Category::LanguageServer),
+            ServerRow::Server(0),
+            ServerRow::Server(1),
+            ServerRow::Header(Category::Formatter),
+            ServerRow::Server(2),
+        ])
+        .skipping(|row| matches!(row, ServerRow::Server(_)))
+    }
+
+    #[test]
+    fn moving_through_a_single_item() {
+        let mut list = StatefulList::with_items(vec!["only"]);
+        for forward in [true, false, true] {
+            if forward {
+                list.next();
+            } else {
+                list.previous();
+            }
+            assert_eq!(list.selected(), Some(&"only"));
+        }
+        list.jump(isize::MAX);
+        list.jump(isize::MIN);
+        assert_eq!(list.selected(), Some(&"only"));
+    }
+
+    #[test]
+    fn moving_wraps_around_skipping_headers() {
+        let mut list = grouped();
+        // Settled on the first server, below its header
+        assert_eq!(list.server(), Some(0));
+        let mut down = Vec::new();
+        for _ in 0..4 {
+            list.next();
+            down.push(list.server());
+        }
+        assert_eq!(down, [Some(1), Some(2), Some(0), Some(1)]);
+
+        let mut up = Vec::new();
+        for _ in 0..4 {
+            list.previous();
+            up.push(list.server());
+        }
+        assert_eq!(up, [Some(0), Some(2), Some(1), Some(0)]);
+        assert_ne!(list.state.selected(), Some(0));
+        assert_ne!(list.state.selected(), Some(3));
+    }
+
+    #[test]
+    fn jumping_stops_at_the_ends_off_headers() {
+        let mut list = grouped();
+        list.jump(2);
+        // Onto the second header, moving on to the server below it
+        assert_eq!(list.server(), Some(2));
+        list.jump(isize::MIN);
+        assert_eq!(list.server(), Some(0));
+        list.jump(isize::MAX);
+        assert_eq!(list.server(), Some(2));
+    }
+}
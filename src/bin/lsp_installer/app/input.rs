@@ -0,0 +1,379 @@
+//! What keys and the mouse do in each input mode. Kept apart from the
+//! event loop, which hands events over and draws.
+
+use super::{App, Focus, InputMode, Message, Tab};
+use crate::{
+    keys::{self, Action},
+    servers::Status,
+    ui,
+    worker::Task,
+};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use std::time::{Duration, Instant};
+use tui::layout::Rect;
+
+/// Longest time between the two clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Lines scrolled in the log pane by PgUp and PgDn
+const LOG_PAGE: isize = 10;
+
+/// What the event loop has to do after a key, besides drawing again
+#[derive(Debug, PartialEq, Eq)]
+pub enum Effect {
+    Quit,
+}
+
+impl App {
+    /// Handle `key` in the current mode, returning what the event loop has to
+    /// do about it
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Effect> {
+        match self.input_mode {
+            InputMode::Normal => {
+                // Keys acting on the selection win while there is one
+                let tables = if self.marked.is_empty() {
+                    [&self.keys.normal, &self.keys.queue]
+                } else {
+                    [&self.keys.queue, &self.keys.normal]
+                };
+                let action = tables
+                    .into_iter()
+                    .find_map(|table| keys::action(table, key));
+                match action {
+                    Some(Action::Quit) => {
+                        if self.queue.unfinished() == 0 {
+                            return Some(Effect::Quit);
+                        }
+                        self.input_mode = InputMode::ConfirmQuit;
+                    }
+                    Some(action) => self.handle_action(action),
+                    None => {}
+                }
+            }
+            InputMode::Editing => match keys::action(&self.keys.editing, key) {
+                Some(Action::Close) => {
+                    self.clear_search();
+                    self.input_mode = InputMode::Normal;
+                }
+                Some(Action::ClearSearch) => self.clear_search(),
+                Some(Action::Confirm) => self.pick_match(),
+                Some(Action::DeleteChar) => self.delete_back(),
+                Some(Action::DeleteForward) => self.delete_forward(),
+                Some(Action::DeleteWord) => self.delete_word(),
+                Some(Action::CursorLeft) => self.move_cursor(false),
+                Some(Action::CursorRight) => self.move_cursor(true),
+                Some(Action::CursorStart) => self.cursor_to(false),
+                Some(Action::CursorEnd) => self.cursor_to(true),
+                Some(action) => self.handle_action(action),
+                None => {
+                    if let Some(c) = keys::is_typing(key) {
+                        self.insert(c);
+                    }
+                }
+            },
+            // Anything but closing the overlay gets ignored
+            InputMode::Help => {
+                if keys::action(&self.keys.popup, key) == Some(Action::Close)
+                    || keys::action(&self.keys.normal, key) == Some(Action::Help)
+                {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::ConfirmUninstall => match keys::action(&self.keys.popup, key) {
+                Some(Action::Confirm) => {
+                    self.start(Task::Uninstall);
+                    self.input_mode = InputMode::Normal;
+                }
+                Some(Action::Close) => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+            InputMode::ConfirmBuild => match keys::action(&self.keys.popup, key) {
+                Some(Action::Confirm) => {
+                    self.input_mode = InputMode::Normal;
+                    self.start(Task::Build);
+                }
+                Some(Action::Close) => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+            InputMode::Error => match keys::action(&self.keys.popup, key) {
+                Some(Action::ViewLog) => {
+                    self.show_log = true;
+                    self.error = None;
+                    self.input_mode = InputMode::Normal;
+                }
+                Some(Action::Close) => {
+                    self.error = None;
+                    self.input_mode = InputMode::Normal;
+                }
+                _ => {}
+            },
+            InputMode::ConfirmQuit => match keys::action(&self.keys.popup, key) {
+                Some(Action::Confirm) => return Some(Effect::Quit),
+                Some(Action::Close) => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+            InputMode::Doctor => {
+                if keys::action(&self.keys.popup, key) == Some(Action::Close)
+                    || keys::action(&self.keys.normal, key) == Some(Action::Doctor)
+                {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::Versions => match keys::action(&self.keys.popup, key) {
+                Some(Action::Confirm) => self.install_picked(),
+                Some(Action::Close) => self.input_mode = InputMode::Normal,
+                _ => match (keys::action(&self.keys.normal, key), &mut self.versions) {
+                    (Some(Action::Open), _) => self.install_picked(),
+                    (Some(Action::Next), Some(Ok(versions))) => versions.next(),
+                    (Some(Action::Previous), Some(Ok(versions))) => versions.previous(),
+                    _ => {}
+                },
+            },
+            InputMode::LocalFile | InputMode::StateFile => {
+                match keys::action(&self.keys.editing, key) {
+                    Some(Action::Close) => self.input_mode = InputMode::Normal,
+                    Some(Action::Confirm) if matches!(self.input_mode, InputMode::StateFile) => {
+                        self.export_state()
+                    }
+                    Some(Action::Confirm) => self.install_local_file(),
+                    Some(Action::DeleteChar) => {
+                        self.local_file.pop();
+                    }
+                    Some(Action::ClearSearch) => self.local_file.clear(),
+                    _ => {
+                        if let Some(c) = keys::is_typing(key) {
+                            self.local_file.push(c);
+                        }
+                    }
+                }
+            }
+            InputMode::Plan => match keys::action(&self.keys.popup, key) {
+                Some(Action::Confirm) => self.run_planned(),
+                Some(Action::Close) => self.input_mode = InputMode::Normal,
+                _ => {
+                    if keys::action(&self.keys.normal, key) == Some(Action::Open) {
+                        self.run_planned();
+                    }
+                }
+            },
+            InputMode::Settings => match keys::action(&self.keys.popup, key) {
+                Some(Action::SaveSettings) => self.save_settings(),
+                Some(Action::Close) => self.input_mode = InputMode::Normal,
+                // The key opening the settings closes them too
+                _ if keys::action(&self.keys.normal, key) == Some(Action::Settings) => {
+                    self.input_mode = InputMode::Normal
+                }
+                _ => {}
+            },
+        }
+        None
+    }
+
+    /// Handle `event` from the normal or editing mode: the wheel scrolls the
+    /// pane under the pointer, a click selects, a double click opens like Enter
+    pub fn handle_mouse(&mut self, areas: &ui::Areas, event: MouseEvent) {
+        if !matches!(self.input_mode, InputMode::Normal | InputMode::Editing) {
+            return;
+        }
+        let (x, y) = (event.column, event.row);
+        let inside = |area: Rect| {
+            x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
+        };
+        let pane = if inside(areas.languages) {
+            Some((Focus::Languages, areas.languages))
+        } else if inside(areas.servers) {
+            Some((Focus::Servers, areas.servers))
+        } else {
+            None
+        };
+
+        match (event.kind, pane) {
+            (MouseEventKind::ScrollDown, Some((focus, _))) => {
+                self.focus = focus;
+                self.next();
+            }
+            (MouseEventKind::ScrollUp, Some((focus, _))) => {
+                self.focus = focus;
+                self.previous();
+            }
+            (MouseEventKind::Down(MouseButton::Left), _) if inside(areas.search) => {
+                self.input_mode = InputMode::Editing;
+            }
+            (MouseEventKind::Down(MouseButton::Left), _) if inside(areas.tabs) => {
+                if let Some(tab) = ui::tab_at(areas.tabs, self, x) {
+                    self.switch_tab(tab);
+                }
+            }
+            (MouseEventKind::Down(MouseButton::Left), Some((focus, area))) => {
+                // Skip the borders
+                if y == area.y || y + 1 == area.y + area.height {
+                    return;
+                }
+                self.input_mode = InputMode::Normal;
+                if !self.click(focus, (y - area.y - 1) as usize) {
+                    return;
+                }
+                let double = self.last_click.is_some_and(|(at, last_x, last_y)| {
+                    at.elapsed() < DOUBLE_CLICK && (last_x, last_y) == (x, y)
+                });
+                if double {
+                    self.last_click = None;
+                    self.handle_action(Action::Open);
+                } else {
+                    self.last_click = Some((Instant::now(), x, y));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Carry out `action` from the normal mode, or the moves shared with the
+    /// editing mode
+    pub fn handle_action(&mut self, action: Action) {
+        match action {
+            Action::Search => self.input_mode = InputMode::Editing,
+            Action::Help => self.input_mode = InputMode::Help,
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::SwitchPane => {
+                self.focus = match self.focus {
+                    Focus::Languages => Focus::Servers,
+                    Focus::Servers => Focus::Languages,
+                }
+            }
+            Action::TabAll => self.switch_tab(Tab::All),
+            Action::TabInstalled => self.switch_tab(Tab::Installed),
+            Action::TabUpdates => self.switch_tab(Tab::Updates),
+            Action::NextTab => self.next_tab(),
+            Action::OpenHomepage => self.open_page(false),
+            Action::OpenReleases => self.open_page(true),
+            Action::CopyPath => self.copy_path(),
+            Action::FocusLanguages => self.focus = Focus::Languages,
+            Action::FocusServers => self.focus = Focus::Servers,
+            Action::Open if self.focus == Focus::Languages => self.focus = Focus::Servers,
+            Action::Install if !self.marked.is_empty() => self.plan_marked(Task::Install),
+            Action::Open | Action::Install if self.dry_run => self.plan_install(),
+            Action::Open | Action::Install => self.start(Task::Install),
+            Action::Update if !self.marked.is_empty() => self.plan_marked(Task::Update),
+            Action::Update => match self.selected_status() {
+                Some(Status::Installed) => self.start(Task::Update),
+                Some(_) => {
+                    if let Some(server) = self.selected_server() {
+                        self.message = Some(Message::Error(format!(
+                            "{} is not installed",
+                            server.name()
+                        )));
+                    }
+                }
+                None => {}
+            },
+            Action::UpdateAll => self.update_all(),
+            Action::PickVersion => self.pick_version(),
+            Action::InstallFile => self.ask_local_file(),
+            Action::ExportState => self.ask_state_file(),
+            Action::Adopt => self.adopt(),
+            Action::Rescan => self.rescan(),
+            Action::Rollback => match self.selected_server() {
+                Some(server) if server.previous_dir().is_dir() => self.start(Task::Rollback),
+                Some(server) => {
+                    self.message = Some(Message::Error(format!(
+                        "{} has no previous version to roll back to",
+                        server.name()
+                    )))
+                }
+                None => {}
+            },
+            Action::Uninstall => match self.selected_status() {
+                Some(Status::NotInstalled) => {
+                    if let Some(server) = self.selected_server() {
+                        self.message = Some(Message::Error(format!(
+                            "{} is not installed",
+                            server.name()
+                        )));
+                    }
+                }
+                Some(_) => self.input_mode = InputMode::ConfirmUninstall,
+                None => {}
+            },
+            Action::ToggleMark => self.toggle_mark(),
+            Action::Cancel => self.cancel(),
+            Action::CycleCategory => self.cycle_category(),
+            Action::ToggleFavorite => self.toggle_favorite(),
+            Action::InstallSuggested => self.install_suggested(),
+            Action::CycleSort => self.cycle_sort(),
+            Action::Settings => self.show_settings(),
+            Action::ToggleLog => self.show_log = !self.show_log,
+            Action::Export => self.export(),
+            Action::Doctor => self.doctor(),
+            Action::ToggleDryRun => self.toggle_dry_run(),
+            Action::ToggleProject => self.toggle_project(),
+            Action::PageUp if self.show_log => self.scroll_log(LOG_PAGE),
+            Action::PageDown if self.show_log => self.scroll_log(-LOG_PAGE),
+            Action::Bottom if self.show_log => self.log_scroll = 0,
+            Action::PageUp => self.jump(-self.page()),
+            Action::PageDown => self.jump(self.page()),
+            Action::HalfPageUp => self.jump(-self.page() / 2),
+            Action::HalfPageDown => self.jump(self.page() / 2),
+            Action::Top => self.jump(isize::MIN),
+            Action::Bottom => self.jump(isize::MAX),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::{app, key};
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn quit_asks_first_while_jobs_are_unfinished() {
+        let mut app = app(&["gopls"]);
+        assert_eq!(app.handle_key(key(KeyCode::Char('q'))), Some(Effect::Quit));
+
+        app.queue.push(0, Task::Install);
+        assert_eq!(app.handle_key(key(KeyCode::Char('q'))), None);
+        assert!(matches!(app.input_mode, InputMode::ConfirmQuit));
+        assert_eq!(app.handle_key(key(KeyCode::Esc)), None);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+
+        app.handle_key(key(KeyCode::Char('q')));
+        assert_eq!(app.handle_key(key(KeyCode::Char('y'))), Some(Effect::Quit));
+    }
+
+    #[test]
+    fn esc_leaves_the_search_cleared() {
+        let mut app = app(&["gopls", "pyright", "rust-analyzer"]);
+        let every = app.language_list.items.len();
+        app.handle_key(key(KeyCode::Char('e')));
+        assert!(matches!(app.input_mode, InputMode::Editing));
+        for c in "rust".chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(app.input, "rust");
+        assert_eq!(app.language_list.items.len(), 1);
+
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!((app.input.as_str(), app.cursor), ("", 0));
+        assert_eq!(app.language_list.items.len(), every);
+    }
+
+    #[test]
+    fn queue_keys_win_while_servers_are_marked() {
+        let mut app = app(&["gopls", "pyright"]);
+        // Opening the language moves to its servers
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.focus == Focus::Servers);
+
+        app.handle_key(key(KeyCode::Char(' ')));
+        assert_eq!(app.marked.len(), 1);
+        app.focus = Focus::Languages;
+        // Installing the marked servers instead
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.focus == Focus::Languages);
+        assert!(matches!(app.input_mode, InputMode::Plan));
+        assert_eq!(app.plans.len(), 1);
+    }
+}
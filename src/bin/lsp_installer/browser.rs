@@ -0,0 +1,32 @@
+//! Web pages opened with the opener of the platform, in the background so
+//! it never writes over the TUI.
+
+use std::{
+    io,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Open `url` in the default browser, failing only when the opener can't
+/// be started
+pub fn open(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        // The empty title keeps `start` from taking the URL for one
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let mut child = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reaped once the browser got started, which it may take a while to
+    thread::spawn(move || child.wait());
+    Ok(())
+}
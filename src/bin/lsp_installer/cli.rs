@@ -0,0 +1,1154 @@
+//! Subcommands running tasks without the TUI, printing one line per event.
+
+use crate::{
+    cache,
+    config::{Config, Settings},
+    doctor::{self, Outcome},
+    download,
+    error::Error,
+    export::{self, Editor},
+    lock::{self, Lock},
+    logfile, manifest, paths, project,
+    queue::{JobState, Queue},
+    servers::{self, external, Server, Status},
+    version,
+    worker::{self, Failure, Report, Task},
+};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Install servers by name, `name@version` picking a version to hold
+    /// the server at
+    Install {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Print one JSON event per line
+        #[arg(long)]
+        json: bool,
+        /// Show what would be installed, without installing anything
+        #[arg(long, conflicts_with = "json")]
+        dry_run: bool,
+        /// Build from source, for servers with nothing prebuilt for this
+        /// platform
+        #[arg(long, conflicts_with = "dry_run")]
+        from_source: bool,
+        /// Install the one server named from an archive or binary downloaded
+        /// by other means
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["dry_run", "from_source"])]
+        from: Option<PathBuf>,
+        /// Install the file given with --from even without a checksum match
+        #[arg(long, requires = "from")]
+        no_verify: bool,
+    },
+    /// List the known servers
+    List {
+        /// Only show installed servers
+        #[arg(long)]
+        installed: bool,
+        /// Print a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove installed servers by name
+    Remove {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Print one JSON event per line
+        #[arg(long)]
+        json: bool,
+    },
+    /// Go back to the version the last update of each server replaced
+    Rollback {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Print one JSON event per line
+        #[arg(long)]
+        json: bool,
+    },
+    /// Track servers installed outside lsp_installer, found on PATH, without
+    /// copying them. Every server found gets adopted when none is named.
+    Adopt { names: Vec<String> },
+    /// Make the installed servers match the ones declared in the project's
+    /// lsp_installer.toml, or else in the `servers` table of the config
+    /// file. Asks before applying the plan, and exits with the number of
+    /// servers that couldn't be synced, one more when the plan wasn't
+    /// applied.
+    Sync {
+        /// Also remove the installed servers that aren't declared
+        #[arg(long)]
+        prune: bool,
+        /// Apply the plan without asking
+        #[arg(long)]
+        yes: bool,
+        /// Print one JSON event per line
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the installed servers with their version and backend as JSON,
+    /// the `servers` of the manifest without what only holds on this
+    /// machine
+    ExportState,
+    /// Install the servers of a file written by `export-state`, or of a
+    /// manifest. The ones held at a version get it again, the others the
+    /// latest one. Servers that can't be installed here are skipped with a
+    /// warning.
+    ImportState {
+        file: PathBuf,
+        /// Print one JSON event per line
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that the installed servers can start
+    Doctor,
+    /// Manage the registry fetched on top of the built-in one
+    Registry {
+        #[command(subcommand)]
+        command: RegistryCommand,
+    },
+    /// Manage the downloads kept for reinstalling
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Print the editor configuration starting the installed servers
+    Export {
+        #[arg(value_enum)]
+        editor: Editor,
+        /// Update this VS Code settings.json instead of printing, keeping
+        /// its other keys
+        #[arg(long, value_name = "FILE")]
+        merge_into: Option<PathBuf>,
+        /// Merge into Helix's languages.toml instead of printing, after
+        /// backing it up
+        #[arg(long, conflicts_with = "merge_into")]
+        write: bool,
+        /// Add the nvim-dap adapters of the installed debug adapters, for
+        /// nvim
+        #[arg(long)]
+        dap: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RegistryCommand {
+    /// Fetch the latest registry, for servers added since this version
+    ///
+    /// It's checked against the checksum published next to it, which only
+    /// catches a broken download: the servers it lists are trusted as much
+    /// as the GitHub release it comes from.
+    Update,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Remove the cached downloads
+    Clean {
+        /// Only remove the ones unused for this long, like `30d`
+        #[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+        older_than: Option<Duration>,
+    },
+}
+
+/// A server as printed by `list --json`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub languages: Vec<String>,
+    pub installed: bool,
+    pub installed_version: Option<String>,
+    /// Whether the installed version got picked, keeping updates away
+    pub held: bool,
+    /// Only looked up for installed servers
+    pub latest_version: Option<String>,
+    pub bin: PathBuf,
+    /// Version installed globally, when listing the servers of a project
+    pub global_version: Option<String>,
+}
+
+/// Something that happened to a server while running a task, printed by
+/// `install --json`, `remove --json`, `rollback --json` and `sync --json`
+/// as one object per line
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    Log {
+        server: String,
+        message: String,
+    },
+    Progress {
+        server: String,
+        done: u64,
+        total: Option<u64>,
+    },
+    Warning {
+        server: String,
+        message: String,
+    },
+    Completed {
+        server: String,
+        message: String,
+    },
+    Failed {
+        server: String,
+        message: String,
+    },
+    /// What `sync` is about to do to a server, printed before it asks to
+    /// go ahead
+    Planned {
+        server: String,
+        /// `add`, `update` or `remove`
+        change: String,
+        from: Option<String>,
+        /// `None` for the latest version
+        to: Option<String>,
+    },
+}
+
+/// Run `command`, exiting with the number of servers it failed on
+pub fn run(command: Command, settings: &Settings, warnings: Vec<String>, wait: bool) -> ExitCode {
+    let (mut servers, registry_warnings) = servers::registry();
+    servers.sort_by(|a, b| a.name().cmp(b.name()));
+    for warning in warnings.iter().chain(&registry_warnings) {
+        eprintln!("warning: {}", warning);
+    }
+    // Held until the command is done
+    let _lock = match changes_root(&command).then(|| lock_root(wait)) {
+        Some(Err(err)) => {
+            eprintln!("error: {}", Failure::new(err.to_string(), &err));
+            return ExitCode::FAILURE;
+        }
+        lock => lock,
+    };
+
+    let jobs = settings.jobs.0;
+    let failed = match command {
+        Command::Install {
+            names,
+            dry_run: true,
+            ..
+        } => dry_run(&servers, &names),
+        Command::Install {
+            names,
+            json,
+            from: Some(file),
+            no_verify,
+            ..
+        } => {
+            if names.len() > 1 {
+                eprintln!(
+                    "error: --from installs one server, {} are named",
+                    names.len()
+                );
+                return ExitCode::FAILURE;
+            }
+            let task = Task::Local {
+                file,
+                verify: !no_verify,
+            };
+            run_tasks(&mut io::stdout(), &servers, &names, task, jobs, json)
+        }
+        Command::Install {
+            names,
+            json,
+            from_source,
+            ..
+        } => {
+            let task = if from_source {
+                Task::Build
+            } else {
+                Task::Install
+            };
+            run_tasks(&mut io::stdout(), &servers, &names, task, jobs, json)
+        }
+        Command::Remove { names, json } => run_tasks(
+            &mut io::stdout(),
+            &servers,
+            &names,
+            Task::Uninstall,
+            jobs,
+            json,
+        ),
+        Command::Rollback { names, json } => run_tasks(
+            &mut io::stdout(),
+            &servers,
+            &names,
+            Task::Rollback,
+            jobs,
+            json,
+        ),
+        Command::List { installed, json } => {
+            list(&mut io::stdout().lock(), &servers, installed, json);
+            0
+        }
+        Command::Sync { prune, yes, json } => sync(&servers, prune, yes, jobs, json),
+        Command::ExportState => export_state(),
+        Command::ImportState { file, json } => import_state(&servers, &file, jobs, json),
+        Command::Adopt { names } => adopt(&servers, &names),
+        Command::Doctor => doctor(&servers),
+        Command::Registry {
+            command: RegistryCommand::Update,
+        } => update_registry(servers.len()),
+        Command::Cache {
+            command: CacheCommand::Clean { older_than },
+        } => clean_cache(older_than),
+        Command::Export {
+            editor,
+            merge_into,
+            write,
+            dap,
+        } => {
+            let target = match (merge_into, write) {
+                (Some(path), _) => Some(path),
+                (None, true) => Some(paths::helix_languages()),
+                (None, false) => None,
+            };
+            export(&servers, editor, target, write, dap)
+        }
+    };
+    ExitCode::from(failed.min(u8::MAX as usize) as u8)
+}
+
+/// Whether `command` changes the install root, which it then locks
+fn changes_root(command: &Command) -> bool {
+    match command {
+        Command::Install { dry_run, .. } => !dry_run,
+        Command::Remove { .. }
+        | Command::Rollback { .. }
+        | Command::Sync { .. }
+        | Command::ImportState { .. }
+        | Command::Adopt { .. } => true,
+        _ => false,
+    }
+}
+
+/// Lock the install root as held by this command line, waiting for the
+/// process holding it to be done when `wait` is set
+fn lock_root(wait: bool) -> Result<Lock, Error> {
+    let operation = env::args()
+        .skip(1)
+        .fold(String::from("lsp_installer"), |line, arg| line + " " + &arg);
+    let lock = match lock::acquire(&operation) {
+        Err(Error::Locked { holder, .. }) if wait => {
+            let holder = holder.unwrap_or_else(|| String::from("another lsp_installer"));
+            eprintln!("Waiting for {} to finish", holder);
+            lock::wait(&operation)?
+        }
+        lock => lock?,
+    };
+    if let Some(holder) = &lock.broken {
+        eprintln!("warning: {} exited without releasing its lock", holder);
+    }
+    Ok(lock)
+}
+
+/// Print the plan of installing each server in `names`, failing on each
+/// server that couldn't be planned
+fn dry_run(servers: &[Arc<dyn Server>], names: &[String]) -> usize {
+    let mut failed = 0;
+    let mut out = io::stdout().lock();
+    for name in names {
+        let (name, version) = split_version(name);
+        let plan = match servers.iter().find(|server| server.name() == name) {
+            None => Err(String::from("unknown server")),
+            Some(server) if !server.is_available() => {
+                Err(String::from("unavailable on this platform"))
+            }
+            Some(server) => {
+                servers::planned(server.as_ref(), version).map_err(|err| err.to_string())
+            }
+        };
+        let res = match plan {
+            Ok(plan) => writeln!(out, "{}", name).and_then(|()| {
+                plan.lines()
+                    .iter()
+                    .try_for_each(|(label, value)| writeln!(out, "  {:<8} {}", label, value))
+            }),
+            Err(err) => {
+                failed += 1;
+                writeln!(out, "{}: {}", name, err)
+            }
+        };
+        if res.is_err() {
+            break;
+        }
+    }
+    failed
+}
+
+/// Make the installed servers match the declared ones, failing on each
+/// that couldn't be synced, and once more when the plan isn't applied
+fn sync(servers: &[Arc<dyn Server>], prune: bool, yes: bool, jobs: usize, json: bool) -> usize {
+    let (source, declared) = match declared() {
+        Ok(declared) => declared,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return 1;
+        }
+    };
+
+    let mut failed = 0;
+    let mut plan = Vec::new();
+    let mut installs = Vec::new();
+    for (name, version) in &declared {
+        let Some(server) = servers.iter().find(|server| server.name() == name) else {
+            let message = format!("{}: unknown server", name);
+            emit(
+                &mut io::stdout(),
+                Event::Failed {
+                    server: name.clone(),
+                    message,
+                },
+                json,
+            );
+            failed += 1;
+            continue;
+        };
+        let current = server.installed_version();
+        let change = match (server.status(), version, &current) {
+            (Status::Installed, None, _) => continue,
+            (Status::Installed, Some(version), Some(current))
+                if version::compare(version, current).is_eq() =>
+            {
+                continue
+            }
+            (Status::Installed, Some(_), _) => "update",
+            _ => "add",
+        };
+        installs.push(match version {
+            Some(version) => format!("{}@{}", name, version),
+            None => name.clone(),
+        });
+        plan.push(Event::Planned {
+            server: name.clone(),
+            change: change.to_string(),
+            from: current.filter(|_| change == "update"),
+            to: version.clone(),
+        });
+    }
+    let mut removals = Vec::new();
+    if prune {
+        // Installs made outside lsp_installer are the user's to remove
+        let undeclared = servers.iter().filter(|server| {
+            server.status() != Status::NotInstalled
+                && server.external().is_none()
+                && !declared.contains_key(server.name())
+        });
+        for server in undeclared {
+            removals.push(server.name().to_string());
+            plan.push(Event::Planned {
+                server: server.name().to_string(),
+                change: String::from("remove"),
+                from: server.installed_version(),
+                to: None,
+            });
+        }
+    }
+
+    if plan.is_empty() {
+        if !json {
+            println!("Nothing to do, the servers match {}", source.display());
+        }
+        return failed;
+    }
+    if !json {
+        println!("Plan to match {}:", source.display());
+    }
+    let count = plan.len();
+    for change in plan {
+        emit(&mut io::stdout(), change, json);
+    }
+    if !yes && !confirm(count) {
+        eprintln!("Nothing was changed");
+        return failed + 1;
+    }
+    failed += run_tasks(
+        &mut io::stdout(),
+        servers,
+        &installs,
+        Task::Install,
+        jobs,
+        json,
+    );
+    failed
+        + run_tasks(
+            &mut io::stdout(),
+            servers,
+            &removals,
+            Task::Uninstall,
+            jobs,
+            json,
+        )
+}
+
+/// Servers to sync with the file declaring them: the project's
+/// `lsp_installer.toml` when installing into the project, else the config
+/// file
+fn declared() -> Result<(PathBuf, BTreeMap<String, Option<String>>), String> {
+    if paths::project().is_some() {
+        let file = paths::project_file().ok_or_else(|| {
+            format!(
+                "no {} in {} or the directories above it",
+                paths::PROJECT_FILE,
+                paths::project_root().display()
+            )
+        })?;
+        return project::declared(&file).map(|declared| (file, declared));
+    }
+    // Its problems were already warned about
+    let (config, _) = Config::load();
+    if config.servers.is_empty() {
+        return Err(format!(
+            "{} declares no servers, list them in its [servers] table",
+            Config::path().display()
+        ));
+    }
+    Ok((Config::path(), project::wanted(config.servers)))
+}
+
+/// Ask on the terminal whether to apply a plan of `count` changes, which
+/// can't be done without one
+fn confirm(count: usize) -> bool {
+    if !io::stdin().is_terminal() {
+        eprintln!("error: not on a terminal, --yes applies the plan without asking");
+        return false;
+    }
+    eprint!("Apply these {} changes? [y/N] ", count);
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Print the installed servers, failing once when that can't be done
+fn export_state() -> usize {
+    match serde_json::to_string_pretty(&manifest::state()) {
+        Ok(text) => {
+            println!("{}", text);
+            0
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            1
+        }
+    }
+}
+
+/// Install the servers of the state in `file` that this machine can,
+/// failing on each that couldn't be installed
+fn import_state(servers: &[Arc<dyn Server>], file: &Path, jobs: usize, json: bool) -> usize {
+    let state = fs::read_to_string(file)
+        .map_err(|err| err.to_string())
+        .and_then(|text| {
+            serde_json::from_str::<manifest::State>(&text).map_err(|err| err.to_string())
+        });
+    let state = match state {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("error: {}: {}", file.display(), err);
+            return 1;
+        }
+    };
+
+    let mut names = Vec::new();
+    for (name, installed) in state.servers {
+        let skip = |message: &str| {
+            let message = format!("{}, skipped", message);
+            emit(
+                &mut io::stdout(),
+                Event::Warning {
+                    server: name.clone(),
+                    message,
+                },
+                json,
+            );
+        };
+        let Some(server) = servers.iter().find(|server| server.name() == name) else {
+            skip("unknown server");
+            continue;
+        };
+        if installed.backend == external::BACKEND {
+            skip("installed outside lsp_installer");
+            continue;
+        }
+        if !server.is_available() {
+            skip("unavailable on this platform");
+            continue;
+        }
+        if server.is_installed()
+            && (!installed.held || server.installed_version().as_ref() == Some(&installed.version))
+        {
+            let message = String::from("already installed");
+            emit(
+                &mut io::stdout(),
+                Event::Log {
+                    server: name,
+                    message,
+                },
+                json,
+            );
+            continue;
+        }
+        if installed.backend != server.backend() {
+            let message = format!(
+                "installed with {} there, with {} here",
+                installed.backend,
+                server.backend()
+            );
+            emit(
+                &mut io::stdout(),
+                Event::Warning {
+                    server: name.clone(),
+                    message,
+                },
+                json,
+            );
+        }
+        names.push(if installed.held {
+            format!("{}@{}", name, installed.version)
+        } else {
+            name
+        });
+    }
+    run_tasks(
+        &mut io::stdout(),
+        servers,
+        &names,
+        Task::Install,
+        jobs,
+        json,
+    )
+}
+
+/// Adopt the servers in `names`, or every server found on `PATH`, failing
+/// on each named server that couldn't be adopted
+fn adopt(servers: &[Arc<dyn Server>], names: &[String]) -> usize {
+    let mut failed = 0;
+    let chosen: Vec<&Arc<dyn Server>> = if names.is_empty() {
+        servers
+            .iter()
+            .filter(|server| !server.is_installed())
+            .collect()
+    } else {
+        let mut chosen = Vec::new();
+        for name in names {
+            match servers.iter().find(|server| server.name() == name) {
+                Some(server) => chosen.push(server),
+                None => {
+                    eprintln!("{}: unknown server", name);
+                    failed += 1;
+                }
+            }
+        }
+        chosen
+    };
+
+    for server in chosen {
+        let name = server.name();
+        if server.is_installed() {
+            eprintln!("{}: already installed", name);
+            failed += 1;
+            continue;
+        }
+        match external::find(server.as_ref()) {
+            Some(bin) => match external::adopt(server.as_ref(), &bin) {
+                Ok(version) => println!("Adopted {} {} at {}", name, version, bin.display()),
+                Err(err) => {
+                    eprintln!("{}: {}", name, err);
+                    failed += 1;
+                }
+            },
+            None if names.is_empty() => {}
+            None => {
+                eprintln!("{}: not found on PATH", name);
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Fetch the registry, failing once when that didn't work out
+fn update_registry(known: usize) -> usize {
+    match servers::remote::update() {
+        Ok(count) => {
+            let (servers, _) = servers::registry();
+            println!(
+                "Fetched {} servers into {}, {} new",
+                count,
+                servers::remote::path().display(),
+                servers.len().saturating_sub(known)
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("error: couldn't update the registry: {}", err);
+            1
+        }
+    }
+}
+
+/// Remove the cached downloads, failing once when that didn't work out
+fn clean_cache(older_than: Option<Duration>) -> usize {
+    match cache::clean(older_than) {
+        Ok((count, freed)) => {
+            println!(
+                "Removed {} cached downloads, {} freed",
+                count,
+                download::format_bytes(freed)
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("error: {}: {}", cache::dir().display(), err);
+            1
+        }
+    }
+}
+
+/// Print a table of health checks, failing on each check that fails
+fn doctor(servers: &[Arc<dyn Server>]) -> usize {
+    let checks = doctor::run(servers, 0);
+    let width = checks
+        .iter()
+        .filter_map(|check| check.server.as_ref().map(String::len))
+        .max()
+        .unwrap_or_default();
+    let mut out = io::stdout().lock();
+    for check in &checks {
+        let res = writeln!(
+            out,
+            "{:<4}  {:<width$}  {:<7}  {}",
+            check.outcome.name(),
+            check.server.as_deref().unwrap_or("-"),
+            check.what,
+            check.detail,
+        )
+        .and_then(|()| match &check.hint {
+            Some(hint) => writeln!(out, "      hint: {}", hint),
+            None => Ok(()),
+        });
+        if res.is_err() {
+            break;
+        }
+    }
+    checks
+        .iter()
+        .filter(|check| check.outcome == Outcome::Fail)
+        .count()
+}
+
+/// Print the configuration of `editor`, or merge it into `target`, failing
+/// when that can't be done
+fn export(
+    servers: &[Arc<dyn Server>],
+    editor: Editor,
+    target: Option<PathBuf>,
+    write: bool,
+    dap: bool,
+) -> usize {
+    if dap && editor != Editor::Nvim {
+        eprintln!("error: --dap only works with nvim");
+        return 1;
+    }
+    let merged = match (&target, editor) {
+        (None, _) => Ok(editor.render(servers)),
+        (Some(path), Editor::Vscode) if !write => export::merge_into(path, servers),
+        (Some(path), Editor::Helix) if write => export::write_helix(path, servers),
+        (Some(_), _) if write => {
+            eprintln!("error: --write only works with helix");
+            return 1;
+        }
+        (Some(_), _) => {
+            eprintln!("error: --merge-into only works with vscode");
+            return 1;
+        }
+    };
+    let mut export = match merged {
+        Ok(export) => export,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return 1;
+        }
+    };
+    if dap {
+        let adapters = export::nvim_dap(servers);
+        export.text.push_str(&adapters.text);
+        export.count += adapters.count;
+        export.skipped.extend(adapters.skipped);
+    }
+
+    match &target {
+        Some(path) => println!(
+            "Set the path of {} servers in {}",
+            export.count,
+            path.display()
+        ),
+        None => {
+            let _ = io::stdout().lock().write_all(export.text.as_bytes());
+        }
+    }
+    // On stderr, so the output stays valid for the editor
+    if !export.skipped.is_empty() {
+        eprintln!(
+            "{} Not set up, {} has no setting for them: {}",
+            editor.comment(),
+            editor.title(),
+            export.skipped.join(", ")
+        );
+    }
+    0
+}
+
+fn list(out: &mut dyn Write, servers: &[Arc<dyn Server>], installed_only: bool, json: bool) {
+    let global = manifest::global();
+    let servers = servers
+        .iter()
+        .filter(|server| !installed_only || server.is_installed());
+
+    if json {
+        let entries: Vec<Entry> = servers
+            .map(|server| {
+                let installed = server.is_installed();
+                Entry {
+                    name: server.name().to_string(),
+                    languages: server.languages().to_vec(),
+                    installed,
+                    installed_version: server.installed_version(),
+                    held: server.is_held(),
+                    latest_version: installed.then(|| server.latest_version().ok()).flatten(),
+                    bin: server.bin(),
+                    global_version: global
+                        .get(server.name())
+                        .map(|record| record.version.clone()),
+                }
+            })
+            .collect();
+        let _ = serde_json::to_writer_pretty(&mut *out, &entries);
+        let _ = writeln!(out);
+        return;
+    }
+
+    for server in servers {
+        let mut status = match server.status() {
+            Status::Installed if server.external().is_some() => format!(
+                "installed {} (external)",
+                server.installed_version().unwrap_or_default()
+            ),
+            Status::Installed => match server.installed_version() {
+                Some(version) if server.is_held() => format!("installed {} (held)", version),
+                Some(version) => format!("installed {}", version),
+                None => String::from("installed"),
+            },
+            Status::NotInstalled if !server.is_available() => String::from("unavailable"),
+            Status::NotInstalled => match external::find(server.as_ref()) {
+                Some(bin) => format!("external {}, not adopted", bin.display()),
+                None => String::from("not installed"),
+            },
+            Status::Broken => String::from("broken"),
+        };
+        // Telling apart the install of the project from the global one
+        if paths::project().is_some() && server.is_available() {
+            status.push_str(" in the project");
+        }
+        if let Some(record) = global.get(server.name()) {
+            status.push_str(&format!(", installed {} globally", record.version));
+        }
+        let line = writeln!(
+            out,
+            "{}\t{}\t{}",
+            server.name(),
+            server.languages().join(","),
+            status
+        );
+        // Stop quietly once the reader, like `head`, is gone
+        if line.is_err() {
+            return;
+        }
+    }
+}
+
+/// Write `event` to `out` as a line of JSON, or print it as text for
+/// people
+fn emit(out: &mut dyn Write, event: Event, json: bool) {
+    if json {
+        if let Ok(line) = serde_json::to_string(&event) {
+            let _ = writeln!(out, "{}", line);
+        }
+        return;
+    }
+    match event {
+        // Progress bars would only clutter logs
+        Event::Progress { .. } => {}
+        Event::Log { server, message } => println!("{}: {}", server, message),
+        Event::Warning { server, message } => eprintln!("{}: warning: {}", server, message),
+        Event::Completed { message, .. } => println!("{}", message),
+        Event::Planned {
+            server,
+            change,
+            from,
+            to,
+        } => {
+            let versions = match (from, to) {
+                (Some(from), Some(to)) => format!(" {} -> {}", from, to),
+                (Some(version), None) | (None, Some(version)) => format!(" {}", version),
+                (None, None) => String::new(),
+            };
+            println!("  {:<6} {}{}", change, server, versions);
+        }
+        Event::Failed { message, .. } => eprintln!("{}", message),
+    }
+}
+
+/// Server name and version of an argument like `rust-analyzer@2024-11-04`
+fn split_version(arg: &str) -> (&str, Option<&str>) {
+    match arg.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (arg, None),
+    }
+}
+
+/// Run `task` on every server in `names`, returning how many failed
+fn run_tasks(
+    out: &mut dyn Write,
+    servers: &[Arc<dyn Server>],
+    names: &[String],
+    task: Task,
+    jobs: usize,
+    json: bool,
+) -> usize {
+    let mut failed = 0;
+    let mut fail = |out: &mut dyn Write, server: &str, message: String| {
+        let server = server.to_string();
+        emit(out, Event::Failed { server, message }, json);
+        failed += 1;
+    };
+
+    let mut queue = Queue::new(jobs);
+    for name in names {
+        let (name, task) = match (split_version(name), &task) {
+            ((name, Some(version)), Task::Install) => (name, Task::Pin(version.to_string())),
+            _ => (name.as_str(), task.clone()),
+        };
+        match servers.iter().position(|server| server.name() == name) {
+            None => fail(out, name, format!("{}: unknown server", name)),
+            Some(i) if matches!(task, Task::Build) && !servers[i].can_build() => fail(
+                out,
+                name,
+                format!("{}: the registry doesn't tell how to build it", name),
+            ),
+            Some(i) if matches!(task, Task::Build) => {
+                queue.push(i, task);
+            }
+            Some(i) if matches!(task, Task::Local { .. }) => {
+                queue.push(i, task);
+            }
+            Some(i) if !matches!(task, Task::Uninstall) && !servers[i].is_available() => {
+                let mut message = format!("{}: unavailable on this platform", name);
+                if servers[i].can_build() {
+                    message.push_str(", --from-source builds it");
+                }
+                fail(out, name, message)
+            }
+            Some(i)
+                if matches!(task, Task::Uninstall)
+                    && servers[i].status() == Status::NotInstalled =>
+            {
+                fail(out, name, format!("{}: not installed", name))
+            }
+            // Naming a server twice runs its task once
+            Some(i) => {
+                queue.push(i, task);
+            }
+        }
+    }
+
+    // Whether a job failed, leaving its transcript in the log file
+    let mut transcript = false;
+    let (sender, reports) = mpsc::channel();
+    let mut workers = worker::Pool::default();
+    let mut start_next = |queue: &mut Queue| {
+        while let Some(job) = queue.start_next() {
+            workers.spawn(
+                job.server,
+                servers[job.server].clone(),
+                job.task.clone(),
+                sender.clone(),
+                job.cancelled.clone(),
+            );
+        }
+    };
+    start_next(&mut queue);
+
+    while queue.unfinished() > 0 {
+        let Ok((i, report)) = reports.recv() else {
+            break;
+        };
+        let server = servers[i].name().to_string();
+        match report {
+            Report::Started => {}
+            Report::Progress { done, total } => emit(
+                out,
+                Event::Progress {
+                    server,
+                    done,
+                    total,
+                },
+                json,
+            ),
+            Report::Log(message) => emit(out, Event::Log { server, message }, json),
+            Report::Warning(message) => emit(out, Event::Warning { server, message }, json),
+            Report::Completed(message) => {
+                emit(out, Event::Completed { server, message }, json);
+                queue.finish(i, JobState::Done);
+            }
+            Report::Failed(failure) => {
+                fail(out, &server, failure.to_string());
+                queue.finish(i, JobState::Failed);
+                transcript = true;
+            }
+            Report::Cancelled(message) => {
+                fail(out, &server, message);
+                queue.finish(i, JobState::Failed);
+                transcript = true;
+            }
+        }
+        start_next(&mut queue);
+    }
+    if transcript && !json {
+        eprintln!("see {} for details", logfile::path().display());
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn list_entries_keep_their_shape() {
+        let entry = Entry {
+            name: String::from("gopls"),
+            languages: vec![String::from("go")],
+            installed: true,
+            installed_version: Some(String::from("v0.16.1")),
+            held: false,
+            latest_version: None,
+            bin: PathBuf::from("/srv/gopls/current/gopls"),
+            global_version: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({
+                "name": "gopls",
+                "languages": ["go"],
+                "installed": true,
+                "installed_version": "v0.16.1",
+                "held": false,
+                "latest_version": null,
+                "bin": "/srv/gopls/current/gopls",
+                "global_version": null,
+            })
+        );
+    }
+
+    #[test]
+    fn events_are_tagged_objects() {
+        let cases = [
+            (
+                Event::Progress {
+                    server: String::from("gopls"),
+                    done: 512,
+                    total: Some(2048),
+                },
+                r#"{"event":"progress","server":"gopls","done":512,"total":2048}"#,
+            ),
+            (
+                Event::Completed {
+                    server: String::from("gopls"),
+                    message: String::from("Installed gopls"),
+                },
+                r#"{"event":"completed","server":"gopls","message":"Installed gopls"}"#,
+            ),
+            (
+                Event::Failed {
+                    server: String::from("gopls"),
+                    message: String::from("go is missing"),
+                },
+                r#"{"event":"failed","server":"gopls","message":"go is missing"}"#,
+            ),
+            (
+                Event::Planned {
+                    server: String::from("pyright"),
+                    change: String::from("update"),
+                    from: Some(String::from("1.1.380")),
+                    to: None,
+                },
+                r#"{"event":"planned","server":"pyright","change":"update","from":"1.1.380","to":null}"#,
+            ),
+        ];
+        for (event, line) in cases {
+            assert_eq!(serde_json::to_string(&event).unwrap(), line);
+        }
+    }
+
+    #[test]
+    fn json_output_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            os => os,
+        };
+        let arch = match std::env::consts::ARCH {
+            "aarch64" => "arm64",
+            _ => "amd64",
+        };
+        let file = dir.path().join(format!("shfmt_v3.8.0_{}_{}", os, arch));
+        fs::write(&file, "#!/bin/sh\necho v3.8.0\n").unwrap();
+        let servers = crate::app::tests::registry(&["shfmt"]);
+        let names = [String::from("shfmt")];
+
+        let mut out = Vec::new();
+        let task = Task::Local {
+            file,
+            verify: false,
+        };
+        assert_eq!(run_tasks(&mut out, &servers, &names, task, 1, true), 0);
+        let events: Vec<Event> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let event: Event = serde_json::from_str(line).unwrap();
+                assert_eq!(serde_json::to_string(&event).unwrap(), line);
+                event
+            })
+            .collect();
+        assert!(matches!(
+            events.last(),
+            Some(Event::Completed { server, .. }) if server == "shfmt"
+        ));
+
+        let mut out = Vec::new();
+        list(&mut out, &servers, true, true);
+        let entries: Vec<Entry> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "shfmt");
+        assert!(entries[0].installed);
+        assert_eq!(entries[0].installed_version.as_deref(), Some("3.8.0"));
+        let written = serde_json::to_string_pretty(&entries).unwrap();
+        assert_eq!(
+            written.trim_end(),
+            String::from_utf8(out).unwrap().trim_end()
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Text copied to the system clipboard. The terminal gets it through OSC 52,
+//! which works over SSH as well, and the clipboard tool of the platform gets
+//! it too for terminals ignoring the sequence.
+
+use std::{
+    env,
+    io::{self, Write},
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+/// Copy `text`, writing the escape sequence to `out`, the terminal. Fails
+/// only when neither the terminal nor a clipboard tool could be handed it.
+pub fn copy(out: &mut impl Write, text: &str) -> io::Result<()> {
+    let osc = write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes())).and_then(|()| out.flush());
+    match (osc, native(text)) {
+        (Err(err), Err(_)) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Hand `text` to the first clipboard tool found, without waiting for it
+fn native(text: &str) -> io::Result<()> {
+    let tools: &[(&str, &[&str])] = if cfg!(windows) {
+        &[("clip", &[])]
+    } else if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[("wl-copy", &[])]
+    } else if env::var_os("DISPLAY").is_some() {
+        &[
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    } else {
+        &[]
+    };
+
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool");
+    for (program, args) in tools {
+        match spawn(program, args) {
+            Ok(mut child) => {
+                let text = text.to_string();
+                thread::spawn(move || {
+                    if let Some(mut stdin) = child.stdin.take() {
+                        let _ = stdin.write_all(text.as_bytes());
+                    }
+                    child.wait()
+                });
+                return Ok(());
+            }
+            Err(err) => last = err,
+        }
+    }
+    Err(last)
+}
+
+fn spawn(program: &str, args: &[&str]) -> io::Result<Child> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+/// Standard base64 of `bytes`, padded, as OSC 52 expects
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,691 @@
+//! Editor configuration starting the installed servers from the install
+//! root, by paths relative to the project when installing into one.
+
+use crate::{
+    paths,
+    servers::{Category, Server},
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{json, ser::PrettyFormatter, Map, Value};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use toml_edit::{Array, ArrayOfTables, DocumentMut, Item, Table};
+
+/// Languages of the registry spelled differently by Helix
+const HELIX_LANGUAGES: &[(&str, &str)] = &[("terraform", "hcl")];
+
+/// Languages of the registry spelled differently as Neovim filetypes
+const NVIM_FILETYPES: &[(&str, &str)] = &[("bash", "sh")];
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Editor {
+    /// Neovim with nvim-lspconfig
+    #[value(alias = "neovim")]
+    Nvim,
+    /// VS Code, through the settings of each server's extension
+    #[value(alias = "code")]
+    Vscode,
+    /// Helix, through its `languages.toml`
+    Helix,
+    /// Neovim formatters and linters, with conform.nvim and nvim-lint
+    #[value(alias = "nvim-lint")]
+    Conform,
+}
+
+/// Configuration generated for an editor
+pub struct Export {
+    pub text: String,
+    /// Number of servers it sets up
+    pub count: usize,
+    /// Installed servers the editor can't be pointed at
+    pub skipped: Vec<String>,
+}
+
+impl Editor {
+    /// Editor set by the `editor` setting, like `neovim`
+    pub fn from_name(name: &str) -> Option<Editor> {
+        Editor::from_str(name, true).ok()
+    }
+
+    /// File the TUI writes the configuration to
+    pub fn path(self) -> PathBuf {
+        let name = match self {
+            Editor::Nvim => "lspconfig.lua",
+            Editor::Vscode => "settings.json",
+            Editor::Helix => "languages.toml",
+            Editor::Conform => "conform.lua",
+        };
+        paths::export_dir().join(name)
+    }
+
+    /// Name of the editor in messages
+    pub fn title(self) -> &'static str {
+        match self {
+            Editor::Nvim => "nvim-lspconfig",
+            Editor::Vscode => "VS Code",
+            Editor::Helix => "Helix",
+            Editor::Conform => "conform.nvim and nvim-lint",
+        }
+    }
+
+    /// Start of a line comment in the configuration language
+    pub fn comment(self) -> &'static str {
+        match self {
+            Editor::Nvim | Editor::Conform => "--",
+            Editor::Vscode => "//",
+            Editor::Helix => "#",
+        }
+    }
+
+    /// Configuration of every installed server in `servers`, or of every
+    /// installed formatter and linter for conform.nvim and nvim-lint
+    pub fn render(self, servers: &[Arc<dyn Server>]) -> Export {
+        match self {
+            Editor::Nvim => nvim(&language_servers(servers)),
+            Editor::Vscode => vscode(&language_servers(servers), Map::new()),
+            Editor::Helix => helix(&language_servers(servers), DocumentMut::new()),
+            Editor::Conform => conform(&installed(servers)),
+        }
+    }
+
+    /// Write the configuration to `path()`
+    pub fn write(self, servers: &[Arc<dyn Server>]) -> io::Result<Export> {
+        let export = self.render(servers);
+        fs::create_dir_all(paths::export_dir())?;
+        fs::write(self.path(), &export.text)?;
+        Ok(export)
+    }
+}
+
+/// Lua snippet calling the nvim-lspconfig `setup` of each server
+fn nvim(servers: &[&Arc<dyn Server>]) -> Export {
+    let mut export = Export {
+        text: String::from(
+            "-- Generated by `lsp_installer export nvim`, load it from init.lua with\n\
+             -- dofile() or paste the setups you need\n\
+             local lspconfig = require('lspconfig')\n",
+        ),
+        count: 0,
+        skipped: Vec::new(),
+    };
+    for server in servers {
+        let Some(name) = server.lspconfig() else {
+            export.skipped.push(server.name().to_string());
+            continue;
+        };
+        let cmd: Vec<String> = std::iter::once(command(server))
+            .chain(server.args().iter().cloned())
+            .map(|arg| lua_string(&arg))
+            .collect();
+        let init_options = server
+            .init_options()
+            .map(|options| format!("  init_options = {},\n", lua_value(&options)))
+            .unwrap_or_default();
+        let _ = write!(
+            export.text,
+            "\n-- {}\nlspconfig.{}.setup({{\n  cmd = {{ {} }},\n{}}})\n",
+            server.name(),
+            name,
+            cmd.join(", "),
+            init_options
+        );
+        export.count += 1;
+    }
+    export
+}
+
+/// Lua snippet pointing conform.nvim at the installed formatters and
+/// nvim-lint at the installed linters, by filetype
+fn conform(servers: &[&Arc<dyn Server>]) -> Export {
+    let mut export = Export {
+        text: String::from(
+            "-- Generated by `lsp_installer export conform`, load it from init.lua with\n\
+             -- dofile() once conform.nvim and nvim-lint are loaded\n",
+        ),
+        count: 0,
+        skipped: Vec::new(),
+    };
+    let mut formatters = Vec::new();
+    let mut linters = Vec::new();
+    for server in servers {
+        let (tools, name) = match server.category() {
+            Category::Formatter => (&mut formatters, server.conform()),
+            Category::Linter => (&mut linters, server.nvim_lint()),
+            Category::LanguageServer | Category::DebugAdapter => continue,
+        };
+        let Some(name) = name else {
+            export.skipped.push(server.name().to_string());
+            continue;
+        };
+        tools.push((name, *server));
+        export.count += 1;
+    }
+
+    if !formatters.is_empty() {
+        export
+            .text
+            .push_str("\nrequire('conform').setup({\n  formatters_by_ft = {\n");
+        export.text.push_str(&by_filetype(&formatters, "    "));
+        export.text.push_str("  },\n  formatters = {\n");
+        for (name, server) in &formatters {
+            let _ = writeln!(
+                export.text,
+                "    {} = {{ command = {} }},",
+                lua_key(name),
+                lua_string(&command(server))
+            );
+        }
+        export.text.push_str("  },\n})\n");
+    }
+    if !linters.is_empty() {
+        export
+            .text
+            .push_str("\nlocal lint = require('lint')\nlint.linters_by_ft = {\n");
+        export.text.push_str(&by_filetype(&linters, "  "));
+        export.text.push_str("}\n");
+        for (name, server) in &linters {
+            let _ = writeln!(
+                export.text,
+                "lint.linters{}.cmd = {}",
+                lua_index(name),
+                lua_string(&command(server))
+            );
+        }
+    }
+    export
+}
+
+/// Lua snippet defining the nvim-dap adapter of each installed debug
+/// adapter, added to the nvim-lspconfig one
+pub fn nvim_dap(servers: &[Arc<dyn Server>]) -> Export {
+    let mut export = Export {
+        text: String::new(),
+        count: 0,
+        skipped: Vec::new(),
+    };
+    let adapters = installed(servers)
+        .into_iter()
+        .filter(|server| server.category() == Category::DebugAdapter);
+    for server in adapters {
+        let Some(adapter) = server.dap() else {
+            export.skipped.push(server.name().to_string());
+            continue;
+        };
+        let command = command(server);
+        let config = if adapter.server {
+            json!({
+                "type": "server",
+                "port": "${port}",
+                "executable": { "command": command, "args": adapter.args },
+            })
+        } else {
+            json!({ "type": "executable", "command": command, "args": adapter.args })
+        };
+        if export.count == 0 {
+            export.text.push_str("\nlocal dap = require('dap')\n");
+        }
+        let _ = write!(
+            export.text,
+            "\n-- {}\ndap.adapters{} = {}\n",
+            server.name(),
+            lua_index(&adapter.name),
+            lua_value(&config)
+        );
+        export.count += 1;
+    }
+    export
+}
+
+/// Fields of a Lua table listing the named tools by the Neovim filetypes
+/// of their languages, each line starting with `indent`
+fn by_filetype(tools: &[(&str, &Arc<dyn Server>)], indent: &str) -> String {
+    let mut filetypes: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (name, server) in tools {
+        for language in server.languages() {
+            let filetype = NVIM_FILETYPES
+                .iter()
+                .find(|(ours, _)| ours == language)
+                .map_or(language.as_str(), |(_, theirs)| theirs);
+            filetypes
+                .entry(filetype)
+                .or_default()
+                .push(lua_string(name));
+        }
+    }
+    filetypes
+        .iter()
+        .map(|(filetype, names)| {
+            format!(
+                "{}{} = {{ {} }},\n",
+                indent,
+                lua_key(filetype),
+                names.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// `value` as a single quoted Lua string
+fn lua_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// `value` as a Lua expression, objects and arrays becoming tables
+fn lua_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::from("nil"),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(value) => lua_string(value),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(lua_value).collect();
+            format!("{{ {} }}", values.join(", "))
+        }
+        Value::Object(map) => {
+            let fields: Vec<String> = map
+                .iter()
+                .map(|(key, value)| format!("{} = {}", lua_key(key), lua_value(value)))
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+    }
+}
+
+/// `.key` indexing a Lua table, or `['key']` when it isn't an identifier
+fn lua_index(key: &str) -> String {
+    match lua_key(key) {
+        key if key.starts_with('[') => key,
+        key => format!(".{}", key),
+    }
+}
+
+/// `key` of a Lua table constructor, bare when it's an identifier
+fn lua_key(key: &str) -> String {
+    let identifier = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        key.to_string()
+    } else {
+        format!("[{}]", lua_string(key))
+    }
+}
+
+/// Path the editor starts `server` from, relative to the project root when
+/// it's installed into a project so the configuration can be checked in
+fn command(server: &Arc<dyn Server>) -> String {
+    let launcher = server.launcher();
+    paths::project()
+        .and_then(|project| launcher.strip_prefix(project).ok())
+        .unwrap_or(&launcher)
+        .display()
+        .to_string()
+}
+
+/// `command()` for VS Code, which resolves paths from the workspace folder
+/// only through its variable
+fn vscode_command(server: &Arc<dyn Server>) -> String {
+    let command = command(server);
+    if Path::new(&command).is_relative() {
+        format!("${{workspaceFolder}}/{}", command)
+    } else {
+        command
+    }
+}
+
+fn installed(servers: &[Arc<dyn Server>]) -> Vec<&Arc<dyn Server>> {
+    servers
+        .iter()
+        .filter(|server| server.is_installed())
+        .collect()
+}
+
+/// Installed language servers, the editors' server settings having no
+/// place for formatters, linters and debug adapters
+fn language_servers(servers: &[Arc<dyn Server>]) -> Vec<&Arc<dyn Server>> {
+    installed(servers)
+        .into_iter()
+        .filter(|server| server.category() == Category::LanguageServer)
+        .collect()
+}
+
+/// `settings` with the path of each server having a VS Code setting filled
+/// in
+fn vscode(servers: &[&Arc<dyn Server>], mut settings: Map<String, Value>) -> Export {
+    let mut count = 0;
+    let mut skipped = Vec::new();
+    for server in servers {
+        match server.vscode() {
+            Some(key) => {
+                set(&mut settings, key, vscode_command(server));
+                count += 1;
+            }
+            None => skipped.push(server.name().to_string()),
+        }
+    }
+    Export {
+        text: to_json(&Value::Object(settings)),
+        count,
+        skipped,
+    }
+}
+
+/// Update the VS Code `settings.json` at `path` with the settings of the
+/// installed servers, keeping every other key where it is. A missing file
+/// gets created.
+pub fn merge_into(path: &Path, servers: &[Arc<dyn Server>]) -> io::Result<Export> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) if !text.trim().is_empty() => {
+            // VS Code allows comments, which serde_json would throw away
+            match serde_json::from_str(&text) {
+                Ok(Value::Object(map)) => map,
+                Ok(_) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{} doesn't hold a JSON object", path.display()),
+                    ))
+                }
+                Err(err) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                        "{} isn't plain JSON, comments and trailing commas aren't supported: {}",
+                        path.display(),
+                        err
+                    ),
+                    ))
+                }
+            }
+        }
+        Ok(_) => Map::new(),
+        Err(err) if err.kind() == ErrorKind::NotFound => Map::new(),
+        Err(err) => return Err(err),
+    };
+
+    let export = vscode(&language_servers(servers), existing);
+    fs::write(path, &export.text)?;
+    Ok(export)
+}
+
+/// Set `key` of `settings` to `path`, creating the nested objects on the way
+fn set(settings: &mut Map<String, Value>, key: &str, path: String) {
+    let mut keys: Vec<&str> = key.split('/').collect();
+    let last = keys.pop().unwrap_or_default();
+    let mut object = settings;
+    for key in keys {
+        let entry = object
+            .entry(key)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        object = entry.as_object_mut().expect("the entry is an object");
+    }
+    object.insert(last.to_string(), Value::String(path));
+}
+
+/// `value` indented by four spaces like VS Code writes its settings
+fn to_json(value: &Value) -> String {
+    let mut out = Vec::new();
+    let mut serializer =
+        serde_json::Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(b"    "));
+    value
+        .serialize(&mut serializer)
+        .expect("a JSON value always serializes");
+    out.push(b'\n');
+    String::from_utf8(out).expect("serde_json writes UTF-8")
+}
+
+/// `document` with a `[language-server]` table for each server, listed by
+/// the `[[language]]` entries of its languages ahead of the servers already
+/// there
+fn helix(servers: &[&Arc<dyn Server>], mut document: DocumentMut) -> Export {
+    let table = document
+        .entry("language-server")
+        .or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_mut();
+    let Some(table) = table else {
+        return Export {
+            text: document.to_string(),
+            count: 0,
+            skipped: servers
+                .iter()
+                .map(|server| server.name().to_string())
+                .collect(),
+        };
+    };
+    for server in servers {
+        let entry = table
+            .entry(server.name())
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut();
+        let Some(entry) = entry else {
+            continue;
+        };
+        entry.insert("command", toml_edit::value(command(server)));
+        if server.args().is_empty() {
+            entry.remove("args");
+        } else {
+            let args: Array = server.args().iter().collect();
+            entry.insert("args", toml_edit::value(args));
+        }
+        // Sent by Helix as the initialization options
+        if let Some(options) = server.init_options().as_ref().and_then(toml_value) {
+            entry.insert("config", toml_edit::value(options));
+        }
+    }
+
+    let languages = document
+        .entry("language")
+        .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut();
+    if let Some(languages) = languages {
+        // Each server gets put first, going backwards keeps their order
+        for server in servers.iter().rev() {
+            for language in server.languages() {
+                let name = HELIX_LANGUAGES
+                    .iter()
+                    .find(|(ours, _)| ours == language)
+                    .map_or(language.as_str(), |(_, theirs)| theirs);
+                list_server(languages, name, server.name());
+            }
+        }
+    }
+
+    Export {
+        text: document.to_string(),
+        count: servers.len(),
+        skipped: Vec::new(),
+    }
+}
+
+/// `value` as a TOML value, `None` for `null` which TOML can't spell
+fn toml_value(value: &Value) -> Option<toml_edit::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(value) => toml_edit::Value::from(*value),
+        Value::Number(value) => match value.as_i64() {
+            Some(value) => toml_edit::Value::from(value),
+            None => toml_edit::Value::from(value.as_f64()?),
+        },
+        Value::String(value) => toml_edit::Value::from(value.as_str()),
+        Value::Array(values) => {
+            toml_edit::Value::Array(values.iter().filter_map(toml_value).collect())
+        }
+        Value::Object(map) => toml_edit::Value::InlineTable(
+            map.iter()
+                .filter_map(|(key, value)| Some((key.as_str(), toml_value(value)?)))
+                .collect(),
+        ),
+    })
+}
+
+/// Add `server` ahead of the servers of the `[[language]]` entry named
+/// `name`, unless it's listed already
+fn list_server(languages: &mut ArrayOfTables, name: &str, server: &str) {
+    let position = languages
+        .iter()
+        .position(|language| language.get("name").and_then(Item::as_str) == Some(name));
+    let language = match position {
+        Some(i) => languages.get_mut(i).expect("the position was just found"),
+        None => {
+            let mut language = Table::new();
+            language.insert("name", toml_edit::value(name));
+            languages.push(language);
+            languages
+                .iter_mut()
+                .last()
+                .expect("an entry was just pushed")
+        }
+    };
+    let Some(servers) = language
+        .entry("language-servers")
+        .or_insert_with(|| toml_edit::value(Array::new()))
+        .as_array_mut()
+    else {
+        return;
+    };
+    // Entries are either a name or an inline table with a `name` key
+    let listed = servers.iter().any(|entry| {
+        entry.as_str() == Some(server)
+            || entry
+                .as_inline_table()
+                .and_then(|table| table.get("name"))
+                .and_then(|name| name.as_str())
+                == Some(server)
+    });
+    if !listed {
+        servers.insert(0, server);
+        // The entry that was first had nothing before it
+        if let Some(next) = servers.get_mut(1) {
+            let prefix = next.decor().prefix().and_then(|prefix| prefix.as_str());
+            if prefix.is_none_or(str::is_empty) {
+                next.decor_mut().set_prefix(" ");
+            }
+        }
+    }
+}
+
+/// Merge the Helix configuration into the `languages.toml` at `path`, a
+/// copy of the previous file being kept next to it. A file that doesn't
+/// parse is left alone.
+pub fn write_helix(path: &Path, servers: &[Arc<dyn Server>]) -> io::Result<Export> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    let document = match &existing {
+        Some(text) => text.parse::<DocumentMut>().map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} is invalid, it was left untouched: {}",
+                    path.display(),
+                    err
+                ),
+            )
+        })?,
+        None => DocumentMut::new(),
+    };
+
+    if existing.is_some() {
+        // Colons can't be part of a file name on Windows
+        let time = humantime::format_rfc3339_seconds(SystemTime::now())
+            .to_string()
+            .replace(':', "-");
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".{}.bak", time));
+        fs::copy(path, backup)?;
+    } else if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let export = helix(&language_servers(servers), document);
+    fs::write(path, &export.text)?;
+    Ok(export)
+}
+
+// Spelling paths the Unix way
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::app::tests::registry;
+
+    /// Helix configuration of `names` merged into `existing`, with the
+    /// install root spelled `<root>`
+    fn helix_of(names: &[&str], existing: &str) -> String {
+        let servers = registry(names);
+        let servers: Vec<_> = servers.iter().collect();
+        let export = helix(&servers, existing.parse().unwrap());
+        assert_eq!(export.count, names.len());
+        export
+            .text
+            .replace(&paths::root().display().to_string(), "<root>")
+    }
+
+    #[test]
+    fn helix_languages() {
+        assert_eq!(
+            helix_of(&["gopls", "pyright", "terraform-ls"], ""),
+            r#"[language-server.gopls]
+command = "<root>/gopls/current/gopls"
+
+[language-server.terraform-ls]
+command = "<root>/terraform-ls/current/terraform-ls"
+args = ["serve"]
+
+[language-server.pyright]
+command = "<root>/pyright/current/node_modules/.bin/pyright-langserver"
+args = ["--stdio"]
+
+[[language]]
+name = "python"
+language-servers = ["pyright"]
+
+[[language]]
+name = "hcl"
+language-servers = ["terraform-ls"]
+
+[[language]]
+name = "go"
+language-servers = ["gopls"]
+"#
+        );
+    }
+
+    #[test]
+    fn helix_keeps_the_servers_listed_already() {
+        let existing = r#"[[language]]
+name = "go"
+language-servers = ["golangci-lint-lsp"]
+
+[language-server.golangci-lint-lsp]
+command = "golangci-lint-langserver"
+"#;
+        assert_eq!(
+            helix_of(&["gopls"], existing),
+            r#"[[language]]
+name = "go"
+language-servers = ["gopls", "golangci-lint-lsp"]
+
+[language-server.golangci-lint-lsp]
+command = "golangci-lint-langserver"
+
+[language-server.gopls]
+command = "<root>/gopls/current/gopls"
+"#
+        );
+    }
+}
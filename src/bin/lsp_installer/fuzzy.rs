@@ -0,0 +1,159 @@
+//! Subsequence matching with fzf-like scoring.
+
+/// Score of each matched character
+const MATCH: i64 = 16;
+/// Extra score of a match right after the previous one
+const CONSECUTIVE: i64 = 8;
+/// Extra score of a match at the start of the candidate
+const FIRST: i64 = 10;
+/// Extra score of a match starting a word, like the `l` of `ruff-lsp`
+const WORD_START: i64 = 8;
+/// Extra score of an uppercase match following a lowercase character
+const CAMEL: i64 = 6;
+/// Cost of each character skipped between two matches
+const GAP: i64 = 1;
+
+/// Match `query` against `candidate` ignoring case.
+///
+/// Returns `None` unless every character of `query` appears in `candidate`
+/// in order, otherwise the score of the best alignment, higher being better,
+/// along with the char indices of `candidate` it matched. An empty query
+/// matches everything with a score of 0.
+pub fn find(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let bonus: Vec<i64> = (0..chars.len()).map(|j| bonus(&chars, j)).collect();
+    let matches = |i: usize, j: usize| chars[j].to_lowercase().eq(query[i..=i].iter().copied());
+
+    // best[i][j] holds the best score with query[..=i] matched and query[i]
+    // landing on chars[j], from[i][j] where query[i - 1] landed
+    let mut best = vec![vec![None; chars.len()]; query.len()];
+    let mut from = vec![vec![0; chars.len()]; query.len()];
+
+    for j in 0..chars.len() {
+        if matches(0, j) {
+            best[0][j] = Some(MATCH + bonus[j]);
+        }
+    }
+    for i in 1..query.len() {
+        for j in i..chars.len() {
+            if !matches(i, j) {
+                continue;
+            }
+            for k in i - 1..j {
+                let Some(previous) = best[i - 1][k] else {
+                    continue;
+                };
+                let score = previous
+                    + MATCH
+                    + if k + 1 == j {
+                        CONSECUTIVE.max(bonus[j])
+                    } else {
+                        bonus[j] - GAP * (j - k - 1) as i64
+                    };
+                if best[i][j].is_none_or(|current| score > current) {
+                    best[i][j] = Some(score);
+                    from[i][j] = k;
+                }
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+
+    let mut positions = vec![j; query.len()];
+    for i in (1..query.len()).rev() {
+        j = from[i][j];
+        positions[i - 1] = j;
+    }
+    Some((score, positions))
+}
+
+/// Extra score for matching `chars[j]`, depending on where it sits
+fn bonus(chars: &[char], j: usize) -> i64 {
+    if j == 0 {
+        return FIRST;
+    }
+    let previous = chars[j - 1];
+    if !previous.is_alphanumeric() {
+        WORD_START
+    } else if previous.is_lowercase() && chars[j].is_uppercase() {
+        CAMEL
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find;
+
+    #[test]
+    fn matches_subsequences() {
+        let cases = [
+            ("", "rust-analyzer", vec![]),
+            ("ra", "rust-analyzer", vec![0, 5]),
+            ("RUST", "rust-analyzer", vec![0, 1, 2, 3]),
+            ("lsp", "ruff-lsp", vec![5, 6, 7]),
+            ("ts", "typescript-language-server", vec![0, 4]),
+            ("gopls", "gopls", vec![0, 1, 2, 3, 4]),
+        ];
+        for (query, candidate, positions) in cases {
+            let found = find(query, candidate).map(|(_, positions)| positions);
+            assert_eq!(found, Some(positions), "{:?} in {:?}", query, candidate);
+        }
+    }
+
+    #[test]
+    fn rejects_non_matches() {
+        let cases = [
+            ("x", "rust-analyzer"),
+            ("ar", "ra"),
+            ("goplss", "gopls"),
+            ("lua", ""),
+        ];
+        for (query, candidate) in cases {
+            assert_eq!(
+                find(query, candidate),
+                None,
+                "{:?} in {:?}",
+                query,
+                candidate
+            );
+        }
+    }
+
+    #[test]
+    fn ranks_tighter_matches_higher() {
+        // Each query scores higher against the first candidate
+        let cases = [
+            ("py", "pyright", "spyglass"),
+            ("lsp", "ruff-lsp", "lemminx-sp"),
+            ("cl", "clangd", "ocaml-lsp"),
+            ("ts", "TsServer", "tailwindcss"),
+            ("ls", "ltex-ls", "tailwindcss"),
+        ];
+        for (query, better, worse) in cases {
+            let better_score = find(query, better).unwrap().0;
+            let worse_score = find(query, worse).unwrap().0;
+            assert!(
+                better_score > worse_score,
+                "{:?}: {} ({}) should beat {} ({})",
+                query,
+                better,
+                better_score,
+                worse,
+                worse_score
+            );
+        }
+    }
+}
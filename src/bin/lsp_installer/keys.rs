@@ -0,0 +1,599 @@
+//! Keys bound to each action, shared by the key handling and the help
+//! overlay so the two can't disagree. The `[keys]` table of the config file
+//! replaces the default keys of an action by its own.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Search,
+    Help,
+    Next,
+    Previous,
+    SwitchPane,
+    FocusLanguages,
+    FocusServers,
+    /// Open the servers of the selected language, or install the selected
+    /// server
+    Open,
+    Install,
+    Update,
+    /// Update every server with a newer version out
+    UpdateAll,
+    /// Install every server suggested for the project in the current
+    /// directory
+    InstallSuggested,
+    /// Pick a version of the selected server to install
+    PickVersion,
+    /// Install the selected server from a file downloaded by other means
+    InstallFile,
+    Rollback,
+    /// Adopt the install of the selected server found on `PATH`, or stop
+    /// tracking it
+    Adopt,
+    /// Look for servers installed outside the installer again
+    Rescan,
+    Uninstall,
+    ToggleMark,
+    Cancel,
+    /// Show the servers of one category after the other, then all of them
+    CycleCategory,
+    ToggleFavorite,
+    /// Sort the panes the next way
+    CycleSort,
+    /// Open the homepage of the selected server in the browser
+    OpenHomepage,
+    /// Open the releases page of the selected server in the browser
+    OpenReleases,
+    /// Copy the path editors start the selected server from
+    CopyPath,
+    /// Show every server
+    TabAll,
+    /// Show the installed servers alone
+    TabInstalled,
+    /// Show the servers with an update out alone
+    TabUpdates,
+    NextTab,
+    Settings,
+    ToggleLog,
+    Doctor,
+    /// Show what an install would do before starting it, or stop doing so
+    ToggleDryRun,
+    /// Install into the project in the current directory, or globally again
+    ToggleProject,
+    /// Write the configuration of the `editor` setting for the installed
+    /// servers
+    Export,
+    /// Write the installed servers to a file, for another machine to
+    /// install them
+    ExportState,
+    /// Move a page up in the log while it's shown, else in the focused pane
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    Top,
+    /// Move to the last item, or back to following the log while it's shown
+    Bottom,
+    DeleteChar,
+    DeleteForward,
+    DeleteWord,
+    CursorLeft,
+    CursorRight,
+    CursorStart,
+    CursorEnd,
+    ClearSearch,
+    Confirm,
+    SaveSettings,
+    ViewLog,
+    Close,
+}
+
+/// Name of each action in the `[keys]` table
+const NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("search", Action::Search),
+    ("help", Action::Help),
+    ("next", Action::Next),
+    ("previous", Action::Previous),
+    ("switch_pane", Action::SwitchPane),
+    ("focus_languages", Action::FocusLanguages),
+    ("focus_servers", Action::FocusServers),
+    ("open", Action::Open),
+    ("install", Action::Install),
+    ("update", Action::Update),
+    ("update_all", Action::UpdateAll),
+    ("install_suggested", Action::InstallSuggested),
+    ("pick_version", Action::PickVersion),
+    ("install_file", Action::InstallFile),
+    ("rollback", Action::Rollback),
+    ("adopt", Action::Adopt),
+    ("rescan", Action::Rescan),
+    ("uninstall", Action::Uninstall),
+    ("toggle_mark", Action::ToggleMark),
+    ("cancel", Action::Cancel),
+    ("cycle_category", Action::CycleCategory),
+    ("toggle_favorite", Action::ToggleFavorite),
+    ("cycle_sort", Action::CycleSort),
+    ("open_homepage", Action::OpenHomepage),
+    ("open_releases", Action::OpenReleases),
+    ("copy_path", Action::CopyPath),
+    ("tab_all", Action::TabAll),
+    ("tab_installed", Action::TabInstalled),
+    ("tab_updates", Action::TabUpdates),
+    ("next_tab", Action::NextTab),
+    ("settings", Action::Settings),
+    ("toggle_log", Action::ToggleLog),
+    ("doctor", Action::Doctor),
+    ("toggle_dry_run", Action::ToggleDryRun),
+    ("toggle_project", Action::ToggleProject),
+    ("export", Action::Export),
+    ("export_state", Action::ExportState),
+    ("page_up", Action::PageUp),
+    ("page_down", Action::PageDown),
+    ("half_page_up", Action::HalfPageUp),
+    ("half_page_down", Action::HalfPageDown),
+    ("top", Action::Top),
+    ("bottom", Action::Bottom),
+    ("delete_char", Action::DeleteChar),
+    ("delete_forward", Action::DeleteForward),
+    ("delete_word", Action::DeleteWord),
+    ("cursor_left", Action::CursorLeft),
+    ("cursor_right", Action::CursorRight),
+    ("cursor_start", Action::CursorStart),
+    ("cursor_end", Action::CursorEnd),
+    ("clear_search", Action::ClearSearch),
+    ("confirm", Action::Confirm),
+    ("save_settings", Action::SaveSettings),
+    ("view_log", Action::ViewLog),
+    ("close", Action::Close),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        NAMES
+            .iter()
+            .find(|(_, action)| *action == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct Binding {
+    pub key: KeyCode,
+    /// Control and Alt held with the key, Shift being part of the character
+    pub modifiers: KeyModifiers,
+    pub action: Action,
+    pub description: &'static str,
+}
+
+const fn bind(key: KeyCode, action: Action, description: &'static str) -> Binding {
+    Binding {
+        key,
+        modifiers: KeyModifiers::NONE,
+        action,
+        description,
+    }
+}
+
+const fn ctrl(c: char, action: Action, description: &'static str) -> Binding {
+    Binding {
+        key: KeyCode::Char(c),
+        modifiers: KeyModifiers::CONTROL,
+        action,
+        description,
+    }
+}
+
+pub const NORMAL: &[Binding] = &[
+    bind(KeyCode::Char('?'), Action::Help, "show this help"),
+    bind(KeyCode::Char('q'), Action::Quit, "quit"),
+    bind(KeyCode::Char('e'), Action::Search, "search"),
+    bind(KeyCode::Down, Action::Next, "move down"),
+    bind(KeyCode::Char('j'), Action::Next, "move down"),
+    bind(KeyCode::Up, Action::Previous, "move up"),
+    bind(KeyCode::Char('k'), Action::Previous, "move up"),
+    bind(
+        KeyCode::PageDown,
+        Action::PageDown,
+        "page down, or scroll the log",
+    ),
+    bind(
+        KeyCode::PageUp,
+        Action::PageUp,
+        "page up, or scroll the log",
+    ),
+    ctrl('d', Action::HalfPageDown, "half a page down"),
+    ctrl('u', Action::HalfPageUp, "half a page up"),
+    bind(KeyCode::Char('g'), Action::Top, "first item"),
+    bind(KeyCode::Home, Action::Top, "first item"),
+    bind(
+        KeyCode::Char('G'),
+        Action::Bottom,
+        "last item, or follow the log",
+    ),
+    bind(KeyCode::End, Action::Bottom, "last item, or follow the log"),
+    bind(KeyCode::Tab, Action::SwitchPane, "switch pane"),
+    bind(KeyCode::Char('1'), Action::TabAll, "show every server"),
+    bind(
+        KeyCode::Char('2'),
+        Action::TabInstalled,
+        "show the installed servers",
+    ),
+    bind(
+        KeyCode::Char('3'),
+        Action::TabUpdates,
+        "show the servers with updates",
+    ),
+    bind(KeyCode::BackTab, Action::NextTab, "next tab"),
+    bind(KeyCode::Left, Action::FocusLanguages, "focus languages"),
+    bind(KeyCode::Right, Action::FocusServers, "focus servers"),
+    bind(
+        KeyCode::Enter,
+        Action::Open,
+        "open language, install server",
+    ),
+    bind(KeyCode::Char('i'), Action::Install, "install"),
+    bind(KeyCode::Char('u'), Action::Update, "update"),
+    bind(
+        KeyCode::Char('U'),
+        Action::UpdateAll,
+        "update everything outdated",
+    ),
+    bind(
+        KeyCode::Char('I'),
+        Action::InstallSuggested,
+        "install everything suggested",
+    ),
+    bind(
+        KeyCode::Char('v'),
+        Action::PickVersion,
+        "install another version",
+    ),
+    bind(
+        KeyCode::Char('F'),
+        Action::InstallFile,
+        "install from a downloaded file",
+    ),
+    bind(
+        KeyCode::Char('b'),
+        Action::Rollback,
+        "roll back the last update",
+    ),
+    bind(
+        KeyCode::Char('a'),
+        Action::Adopt,
+        "adopt the server found on PATH",
+    ),
+    bind(
+        KeyCode::Char('r'),
+        Action::Rescan,
+        "look for servers on PATH",
+    ),
+    bind(KeyCode::Char('d'), Action::Uninstall, "uninstall"),
+    bind(KeyCode::Char('s'), Action::Settings, "show settings"),
+    bind(
+        KeyCode::Char('f'),
+        Action::CycleCategory,
+        "show one category at a time",
+    ),
+    bind(KeyCode::Char('o'), Action::CycleSort, "change the order"),
+    bind(
+        KeyCode::Char('w'),
+        Action::OpenHomepage,
+        "open the homepage",
+    ),
+    bind(
+        KeyCode::Char('W'),
+        Action::OpenReleases,
+        "open the releases page",
+    ),
+    bind(
+        KeyCode::Char('y'),
+        Action::CopyPath,
+        "copy the path of the executable",
+    ),
+    bind(
+        KeyCode::Char('*'),
+        Action::ToggleFavorite,
+        "add to or remove from the favorites",
+    ),
+    bind(
+        KeyCode::Char('l'),
+        Action::ToggleLog,
+        "show or hide the log",
+    ),
+    bind(
+        KeyCode::Char('h'),
+        Action::Doctor,
+        "check the installed servers",
+    ),
+    bind(
+        KeyCode::Char('x'),
+        Action::Export,
+        "export the editor config",
+    ),
+    bind(
+        KeyCode::Char('X'),
+        Action::ExportState,
+        "export the installed servers to a file",
+    ),
+    bind(
+        KeyCode::Char('p'),
+        Action::ToggleDryRun,
+        "preview installs before running them",
+    ),
+    bind(
+        KeyCode::Char('P'),
+        Action::ToggleProject,
+        "install into the project or globally",
+    ),
+];
+
+/// Keys of the normal mode acting on the job queue
+pub const QUEUE: &[Binding] = &[
+    bind(
+        KeyCode::Char(' '),
+        Action::ToggleMark,
+        "select for a batch install",
+    ),
+    bind(KeyCode::Enter, Action::Install, "install the selection"),
+    bind(KeyCode::Char('c'), Action::Cancel, "cancel the job"),
+];
+
+pub const EDITING: &[Binding] = &[
+    bind(
+        KeyCode::Esc,
+        Action::Close,
+        "stop editing and clear the search",
+    ),
+    ctrl('u', Action::ClearSearch, "clear the search"),
+    bind(KeyCode::Enter, Action::Confirm, "pick the best match"),
+    bind(KeyCode::Backspace, Action::DeleteChar, "delete a character"),
+    bind(
+        KeyCode::Delete,
+        Action::DeleteForward,
+        "delete under the cursor",
+    ),
+    ctrl('w', Action::DeleteWord, "delete a word"),
+    bind(KeyCode::Left, Action::CursorLeft, "move the cursor left"),
+    bind(KeyCode::Right, Action::CursorRight, "move the cursor right"),
+    bind(KeyCode::Home, Action::CursorStart, "start of the search"),
+    ctrl('a', Action::CursorStart, "start of the search"),
+    bind(KeyCode::End, Action::CursorEnd, "end of the search"),
+    ctrl('e', Action::CursorEnd, "end of the search"),
+    bind(KeyCode::Down, Action::Next, "move down"),
+    bind(KeyCode::Up, Action::Previous, "move up"),
+    bind(KeyCode::Tab, Action::SwitchPane, "switch pane"),
+];
+
+/// Keys of the confirmation, settings, error, health check and help popups
+pub const POPUP: &[Binding] = &[
+    bind(KeyCode::Char('y'), Action::Confirm, "confirm"),
+    bind(KeyCode::Esc, Action::Close, "close or cancel"),
+    bind(KeyCode::Char('n'), Action::Close, "close or cancel"),
+    bind(
+        KeyCode::Char('w'),
+        Action::SaveSettings,
+        "save the settings",
+    ),
+    bind(KeyCode::Char('l'), Action::ViewLog, "view the log"),
+];
+
+/// Bindings in effect, the defaults with the `[keys]` table of the config
+/// file applied
+pub struct Keymap {
+    pub normal: Vec<Binding>,
+    pub queue: Vec<Binding>,
+    pub editing: Vec<Binding>,
+    pub popup: Vec<Binding>,
+}
+
+impl Keymap {
+    /// Defaults with the key of each action in `keys` replacing its default
+    /// ones. Unknown actions and keys are left out, and so is a key bound to
+    /// another action already, each with a warning. In the editing mode a
+    /// plain character keeps typing, the defaults staying there.
+    pub fn load(keys: &BTreeMap<String, String>) -> (Keymap, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut overrides = Vec::new();
+        for (name, key) in keys {
+            let Some(&(_, action)) = NAMES.iter().find(|(known, _)| known == name) else {
+                warnings.push(format!("keys.{}: unknown action, it was ignored", name));
+                continue;
+            };
+            match parse(key) {
+                Some((code, modifiers)) => overrides.push((action, code, modifiers)),
+                None => warnings.push(format!("keys.{}: unknown key `{}`", name, key)),
+            }
+        }
+
+        // Taking back an override gives the defaults back, which may clash
+        // with another override in turn
+        loop {
+            let keymap = Keymap::with(&overrides);
+            let conflict =
+                overrides
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, &(action, code, modifiers))| {
+                        keymap
+                            .groups()
+                            .into_iter()
+                            .filter(|group| group.iter().any(|binding| binding.action == action))
+                            .flatten()
+                            .find(|binding| {
+                                binding.key == code
+                                    && binding.modifiers == modifiers
+                                    && binding.action != action
+                            })
+                            .map(|binding| (i, binding.action))
+                    });
+            let Some((i, other)) = conflict else {
+                return (keymap, warnings);
+            };
+            let (action, _, _) = overrides.remove(i);
+            warnings.push(format!(
+                "keys.{}: `{}` is bound to both `{}` and `{}`, keeping the default keys of `{}`",
+                action.name(),
+                keys[action.name()],
+                action.name(),
+                other.name(),
+                action.name()
+            ));
+        }
+    }
+
+    fn with(overrides: &[(Action, KeyCode, KeyModifiers)]) -> Keymap {
+        let apply = |defaults: &[Binding], typing: bool| {
+            let mut bindings: Vec<Binding> = Vec::new();
+            for binding in defaults {
+                let replacement = overrides.iter().find(|(action, _, modifiers)| {
+                    *action == binding.action && !(typing && held(*modifiers).is_empty())
+                });
+                match replacement {
+                    // Only the first of the default keys makes way for it
+                    Some(_) if bindings.iter().any(|kept| kept.action == binding.action) => {}
+                    Some(&(action, key, modifiers)) => bindings.push(Binding {
+                        key,
+                        modifiers,
+                        action,
+                        description: binding.description,
+                    }),
+                    None => bindings.push(binding.clone()),
+                }
+            }
+            bindings
+        };
+        Keymap {
+            normal: apply(NORMAL, false),
+            queue: apply(QUEUE, false),
+            editing: apply(EDITING, true),
+            popup: apply(POPUP, false),
+        }
+    }
+
+    /// Bindings looked up together, where one key can't do two things
+    fn groups(&self) -> [Vec<&Binding>; 3] {
+        [
+            self.normal.iter().chain(&self.queue).collect(),
+            self.editing.iter().collect(),
+            self.popup.iter().collect(),
+        ]
+    }
+
+    /// Every table, named after the mode using it, in the order shown by the
+    /// help overlay
+    pub fn modes(&self) -> [(&'static str, &[Binding]); 4] {
+        [
+            ("Normal", &self.normal),
+            ("Queue", &self.queue),
+            ("Editing", &self.editing),
+            ("Popup", &self.popup),
+        ]
+    }
+}
+
+/// Control and Alt, the modifiers bindings tell apart
+fn held(modifiers: KeyModifiers) -> KeyModifiers {
+    modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+/// Action bound to `key` in `table`
+pub fn action(table: &[Binding], key: KeyEvent) -> Option<Action> {
+    table
+        .iter()
+        .find(|binding| binding.key == key.code && binding.modifiers == held(key.modifiers))
+        .map(|binding| binding.action)
+}
+
+/// Whether `key` types into the search box rather than being a shortcut
+pub fn is_typing(key: KeyEvent) -> Option<char> {
+    match key.code {
+        KeyCode::Char(c) if held(key.modifiers).is_empty() => Some(c),
+        _ => None,
+    }
+}
+
+/// Name of the first key bound to `action` in `table`, for the hint line
+pub fn key(table: &[Binding], action: Action) -> String {
+    table
+        .iter()
+        .find(|binding| binding.action == action)
+        .map(name)
+        .unwrap_or_default()
+}
+
+/// Key written like `q`, `Ctrl+u`, `alt-x`, `Space` or `F5`, the names
+/// shown by the help overlay being accepted in any case
+fn parse(text: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text.trim();
+    // A lone `+` or `-` is a key of its own
+    while rest.len() > 1 {
+        let Some((modifier, key)) = rest.split_once(['+', '-']) else {
+            break;
+        };
+        match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+            "alt" => modifiers |= KeyModifiers::ALT,
+            "shift" if key.eq_ignore_ascii_case("tab") => {
+                return Some((KeyCode::BackTab, modifiers))
+            }
+            _ => return None,
+        }
+        rest = key;
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pgup" | "pageup" => KeyCode::PageUp,
+            "pgdn" | "pagedown" => KeyCode::PageDown,
+            name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+    Some((code, modifiers))
+}
+
+/// How the key of `binding` gets written in the help overlay
+pub fn name(binding: &Binding) -> String {
+    let key = match binding.key {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => String::from("Up"),
+        KeyCode::Down => String::from("Down"),
+        KeyCode::Left => String::from("Left"),
+        KeyCode::Right => String::from("Right"),
+        KeyCode::PageUp => String::from("PgUp"),
+        KeyCode::PageDown => String::from("PgDn"),
+        KeyCode::BackTab => String::from("Shift+Tab"),
+        KeyCode::F(n) => format!("F{}", n),
+        key => format!("{:?}", key),
+    };
+    if binding.modifiers.contains(KeyModifiers::CONTROL) {
+        format!("Ctrl+{}", key)
+    } else if binding.modifiers.contains(KeyModifiers::ALT) {
+        format!("Alt+{}", key)
+    } else {
+        key
+    }
+}
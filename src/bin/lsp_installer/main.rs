@@ -0,0 +1,198 @@
+mod app;
+mod browser;
+mod cli;
+mod clipboard;
+mod export;
+mod fuzzy;
+mod keys;
+mod terminal;
+mod theme;
+mod ui;
+
+use lsp_installer::{
+    cache, config, disk, doctor, download, error, lock, logfile, manifest, metadata, paths,
+    platform, project, queue, servers, updates, version, worker,
+};
+
+use app::{App, Effect, Message};
+use config::{Config, Settings};
+use download::ChecksumPolicy;
+
+use clap::Parser;
+use crossterm::event::{self, Event};
+use std::{
+    error::Error,
+    io,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    layout::Rect,
+    Terminal,
+};
+
+/// How long to wait for input before checking on background tasks
+const TICK_RATE: Duration = Duration::from_millis(200);
+
+/// Time between two frames of the spinner
+const SPIN_RATE: Duration = Duration::from_millis(80);
+
+fn main() -> ExitCode {
+    let args = Cli::parse();
+    let (config, mut warnings) = Config::load();
+    if let Some(root) = &config.install_root {
+        paths::configure_root(paths::expand(root));
+    }
+    // Syncing a project installs into it, like everything with --project
+    let sync = matches!(args.command, Some(cli::Command::Sync { .. }));
+    if args.project || (sync && paths::project_file().is_some()) {
+        paths::set_project(true);
+    }
+    warnings.extend(manifest::check());
+    warnings.extend(servers::recover());
+    warnings.extend(servers::shim::path_notice());
+    let suggest = args.no_suggest.then_some(false);
+    let settings = Settings::resolve(&config, args.jobs, args.checksums, suggest);
+    settings.apply();
+    if args.offline {
+        download::set_offline();
+    }
+
+    if let Some(command) = args.command {
+        return cli::run(command, &settings, warnings, args.wait);
+    }
+
+    // setup terminal
+    let guard = match terminal::Guard::new(settings.mouse.0) {
+        Ok(guard) => guard,
+        Err(err) => return report(&err),
+    };
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = match Terminal::new(backend) {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            drop(guard);
+            return report(&err);
+        }
+    };
+
+    // create app and run it
+    let mut app = App::new(config, settings, warnings);
+    let res = run_app(&mut terminal, &mut app);
+
+    // restore terminal
+    drop(guard);
+
+    // Quitting cancels what's still running, which gets to clean up first
+    let running = app.running_workers();
+    if running > 0 {
+        eprintln!("Waiting for {} jobs to stop", running);
+    }
+    app.shutdown();
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => report(&err),
+    }
+}
+
+/// Print `err` with what caused it, once the terminal is back to normal
+fn report(err: &dyn Error) -> ExitCode {
+    eprintln!("error: {}", err);
+    let mut source = err.source();
+    while let Some(cause) = source {
+        eprintln!("caused by: {}", cause);
+        source = cause.source();
+    }
+    ExitCode::FAILURE
+}
+
+/// Install language servers, from a TUI unless a subcommand is given
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Tasks run at the same time
+    #[arg(long, global = true, value_parser = jobs)]
+    jobs: Option<usize>,
+    /// What to do with downloads nothing publishes a checksum for
+    #[arg(long, global = true, value_enum)]
+    checksums: Option<ChecksumPolicy>,
+    /// Don't use the network, only cached metadata and downloads
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Install into `.lsp_installer` of the project in the current
+    /// directory, which has its own manifest
+    #[arg(long, global = true)]
+    project: bool,
+    /// Wait for another lsp_installer changing the install root to be
+    /// done, instead of failing
+    #[arg(long, global = true)]
+    wait: bool,
+    /// Don't suggest servers for the project in the current directory
+    #[arg(long)]
+    no_suggest: bool,
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+}
+
+fn jobs(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&jobs| jobs > 0)
+        .ok_or_else(|| String::from("expects a number greater than 0"))
+}
+
+fn run_app<B: Backend + io::Write>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    let started = Instant::now();
+    let mut redraw = true;
+    loop {
+        // Checked before draining so the last report still gets drawn
+        let busy = app.is_busy();
+        app.drain_reports();
+        if app.expire_message() || busy || redraw {
+            app.now = Instant::now();
+            app.ticks = (app.now - started).as_millis() as usize / SPIN_RATE.as_millis() as usize;
+            terminal.draw(|f| ui::ui(f, app))?;
+        }
+        redraw = false;
+
+        // Wake up regularly so reports from background tasks get drawn, and
+        // often enough for the spinner while jobs run
+        let timeout = if app.queue.running().next().is_some() {
+            SPIN_RATE
+        } else {
+            TICK_RATE
+        };
+        if !event::poll(timeout)? {
+            continue;
+        }
+
+        redraw = true;
+        let event = event::read()?;
+        if let Event::Resize(width, height) = event {
+            // Lists get fitted to their new height on the next draw
+            terminal.resize(Rect::new(0, 0, width, height))?;
+            continue;
+        }
+        if let Event::Mouse(event) = event {
+            let size = terminal.size()?;
+            if !ui::too_small(size) {
+                let areas = ui::layout(size, app);
+                app.handle_mouse(&areas, event);
+            }
+        }
+        if let Event::Key(key) = event {
+            if app.handle_key(key) == Some(Effect::Quit) {
+                return Ok(());
+            }
+        }
+        // Written between two frames, so it can't end up inside one
+        if let Some(text) = app.clipboard.take() {
+            if let Err(err) = clipboard::copy(terminal.backend_mut(), &text) {
+                app.message = Some(Message::Error(format!("Couldn't copy {}: {}", text, err)));
+            }
+        }
+    }
+}
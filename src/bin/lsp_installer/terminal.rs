@@ -0,0 +1,89 @@
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    io, panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+};
+
+/// Whether the terminal is set up for the UI and still needs restoring
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Terminal switched to raw mode on the alternate screen, put back the way
+/// it was when dropped, including while unwinding from a panic
+pub struct Guard(());
+
+impl Guard {
+    /// Set up the terminal, capturing the mouse if `mouse` is set
+    pub fn new(mouse: bool) -> io::Result<Guard> {
+        restore_on_panic();
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        // Made first, so a step failing below leaves raw mode as it drops
+        let guard = Guard(());
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+}
+
+/// Restore the terminal before the panic hook in place prints, so the
+/// panic message lands on the normal screen instead of vanishing with the
+/// UI. Only installed once, however many guards get made.
+fn restore_on_panic() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            hook(info);
+        }));
+    });
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leave raw mode and the alternate screen, only the first time it's called
+fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            Show
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::{backend::TestBackend, Terminal};
+
+    #[test]
+    fn a_panic_while_drawing_restores_the_terminal() {
+        restore_on_panic();
+        restore_on_panic();
+        // As set up by `Guard::new`, which needs a real terminal
+        ACTIVE.store(true, Ordering::SeqCst);
+        let mut terminal = Terminal::new(TestBackend::new(20, 5)).unwrap();
+        let drawn = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            terminal.draw(|_| panic!("drawing failed")).unwrap();
+        }));
+        assert!(drawn.is_err());
+        assert!(!ACTIVE.load(Ordering::SeqCst));
+    }
+}
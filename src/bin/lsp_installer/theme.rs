@@ -0,0 +1,196 @@
+//! Colors of the UI, picked from a preset and adjusted by the `[colors]`
+//! table of the config file.
+
+use std::collections::BTreeMap;
+use tui::style::{Color, Modifier, Style};
+
+/// Preset names accepted by the `theme` setting
+pub const PRESETS: &[&str] = &["default", "light", "high-contrast", "monochrome"];
+
+pub struct Theme {
+    /// Selected item of the focused pane
+    pub selection: Style,
+    /// Selected item of the other pane
+    pub selection_inactive: Style,
+    pub installed: Style,
+    pub error: Style,
+    pub warning: Style,
+    pub border: Style,
+    /// Text of the search box while typing in it
+    pub search: Style,
+    /// Jobs waiting or running
+    pub busy: Style,
+    /// Characters matching the search
+    pub matched: Style,
+    /// Secondary text like counts and origins
+    pub muted: Style,
+    /// Marker of servers from `servers.toml`
+    pub custom: Style,
+    /// Bottom line with the counts and the running job
+    pub status: Style,
+}
+
+impl Theme {
+    /// Preset named `name`, with the colors in `colors` replacing its own.
+    /// Unknown names and colors are left out, each with a warning.
+    pub fn load(name: &str, colors: &BTreeMap<String, String>) -> (Theme, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut theme = Theme::preset(name).unwrap_or_else(|| {
+            warnings.push(format!(
+                "unknown theme `{}`, expected one of {}",
+                name,
+                PRESETS.join(", ")
+            ));
+            Theme::default()
+        });
+
+        for (key, value) in colors {
+            let Some(color) = parse_color(value) else {
+                warnings.push(format!("colors.{}: unknown color `{}`", key, value));
+                continue;
+            };
+            let style = match key.as_str() {
+                "selection_fg" => {
+                    theme.selection = theme.selection.fg(color);
+                    continue;
+                }
+                "selection_bg" => {
+                    theme.selection = theme.selection.bg(color);
+                    continue;
+                }
+                "status_fg" => {
+                    theme.status = theme.status.fg(color);
+                    continue;
+                }
+                "status_bg" => {
+                    theme.status = theme.status.bg(color);
+                    continue;
+                }
+                "installed" => &mut theme.installed,
+                "error" => &mut theme.error,
+                "warning" => &mut theme.warning,
+                "border" => &mut theme.border,
+                "search" => &mut theme.search,
+                "busy" => &mut theme.busy,
+                "matched" => &mut theme.matched,
+                "muted" => &mut theme.muted,
+                "custom" => &mut theme.custom,
+                _ => {
+                    warnings.push(format!("colors.{}: unknown key, it was ignored", key));
+                    continue;
+                }
+            };
+            *style = style.fg(color);
+        }
+        (theme, warnings)
+    }
+
+    fn preset(name: &str) -> Option<Theme> {
+        let fg = |color| Style::default().fg(color);
+        let bold = |color| fg(color).add_modifier(Modifier::BOLD);
+        let modifier = |modifier| Style::default().add_modifier(modifier);
+
+        let theme = match name {
+            "default" => Theme::default(),
+            "light" => Theme {
+                selection: fg(Color::White).bg(Color::Blue),
+                selection_inactive: fg(Color::Black).bg(Color::Gray),
+                installed: fg(Color::Green),
+                error: fg(Color::Red),
+                warning: fg(Color::Magenta),
+                border: Style::default(),
+                search: fg(Color::Blue),
+                busy: fg(Color::Blue),
+                matched: bold(Color::Red),
+                muted: fg(Color::Gray),
+                custom: fg(Color::Magenta),
+                status: fg(Color::Black).bg(Color::Gray),
+            },
+            "high-contrast" => Theme {
+                selection: bold(Color::Black).bg(Color::Yellow),
+                selection_inactive: fg(Color::Black).bg(Color::White),
+                installed: bold(Color::LightGreen),
+                error: bold(Color::LightRed),
+                warning: bold(Color::LightYellow),
+                border: fg(Color::White),
+                search: fg(Color::LightYellow),
+                busy: fg(Color::LightCyan),
+                matched: bold(Color::LightYellow).add_modifier(Modifier::UNDERLINED),
+                muted: fg(Color::Gray),
+                custom: fg(Color::LightMagenta),
+                status: fg(Color::Black).bg(Color::White),
+            },
+            "monochrome" => Theme {
+                selection: modifier(Modifier::REVERSED),
+                selection_inactive: modifier(Modifier::UNDERLINED),
+                installed: modifier(Modifier::BOLD),
+                error: modifier(Modifier::BOLD),
+                warning: modifier(Modifier::BOLD),
+                border: Style::default(),
+                search: modifier(Modifier::BOLD),
+                busy: Style::default(),
+                matched: modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                muted: modifier(Modifier::DIM),
+                custom: modifier(Modifier::ITALIC),
+                status: modifier(Modifier::REVERSED),
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            selection: Style::default().fg(Color::Black).bg(Color::White),
+            selection_inactive: Style::default().fg(Color::White).bg(Color::DarkGray),
+            installed: Style::default().fg(Color::Green),
+            error: Style::default().fg(Color::Red),
+            warning: Style::default().fg(Color::Yellow),
+            border: Style::default(),
+            search: Style::default().fg(Color::Yellow),
+            busy: Style::default().fg(Color::Cyan),
+            matched: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            muted: Style::default().fg(Color::DarkGray),
+            custom: Style::default().fg(Color::Magenta),
+            status: Style::default().fg(Color::White).bg(Color::DarkGray),
+        }
+    }
+}
+
+/// Color named like `red`, `light-blue` or `dark_gray`, written as
+/// `#rrggbb`, or given as a 256 color palette index
+fn parse_color(value: &str) -> Option<Color> {
+    let name = value.trim().to_lowercase().replace(['-', '_', ' '], "");
+    let color = match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => {
+            if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+                let rgb = u32::from_str_radix(hex, 16).ok()?;
+                Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+            } else {
+                Color::Indexed(name.parse().ok()?)
+            }
+        }
+    };
+    Some(color)
+}
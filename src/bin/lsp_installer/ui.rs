@@ -0,0 +1,1601 @@
+use crate::{
+    app::{App, Focus, InputMode, LanguageRow, Match, Message, ServerRow, StatefulList, Tab},
+    config::{Config, SortMode},
+    doctor::Outcome,
+    download, fuzzy,
+    keys::{self, Action, Keymap},
+    logfile, paths,
+    platform::Platform,
+    queue::{Job, JobState},
+    servers::Status,
+    theme::Theme,
+    version,
+    worker::{Failure, Task},
+};
+use std::time::{Duration, Instant};
+use tui::{
+    backend::Backend,
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{
+        Block, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph, Row, Table, Tabs, Widget,
+        Wrap,
+    },
+    Frame,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Where each part of the UI goes
+pub struct Areas {
+    pub help: Rect,
+    pub search: Rect,
+    pub tabs: Rect,
+    /// Space shared by both lists and the log, which popups get centered in
+    pub middle: Rect,
+    pub languages: Rect,
+    pub servers: Rect,
+    pub log: Option<Rect>,
+    pub details: Rect,
+    pub progress: Rect,
+    pub message: Rect,
+    pub status: Rect,
+}
+
+/// Split `area` for the current state of `app`, the same way for drawing
+/// and for finding what the mouse points at
+pub fn layout(area: Rect, app: &App) -> Areas {
+    // Short terminals give the space of the details to the lists
+    let details = if area.height >= ROWS_FOR_DETAILS {
+        DETAILS_HEIGHT
+    } else {
+        0
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(1),
+                Constraint::Length(details),
+                Constraint::Length(app.queue.running().count() as u16),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let (lists, log) = if app.show_log {
+        let halves = Layout::default()
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(chunks[3]);
+        (halves[0], Some(halves[1]))
+    } else {
+        (chunks[3], None)
+    };
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+        .split(lists);
+
+    Areas {
+        help: chunks[0],
+        search: chunks[1],
+        tabs: chunks[2],
+        middle: chunks[3],
+        languages: panes[0],
+        servers: panes[1],
+        log,
+        details: chunks[4],
+        progress: chunks[5],
+        message: chunks[6],
+        status: chunks[7],
+    }
+}
+
+/// Smallest terminal the UI fits in
+pub const MIN_SIZE: (u16, u16) = (40, 12);
+
+/// Whether `area` is too small to draw anything useful in
+pub fn too_small(area: Rect) -> bool {
+    area.width < MIN_SIZE.0 || area.height < MIN_SIZE.1
+}
+
+pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    if too_small(f.size()) {
+        let text = format!(
+            "Terminal too small (need at least {}x{})",
+            MIN_SIZE.0, MIN_SIZE.1
+        );
+        let area = centered_rect(100, 3, f.size());
+        f.render_widget(
+            Paragraph::new(text)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            area,
+        );
+        return;
+    }
+
+    let areas = layout(f.size(), app);
+    // Rows inside the borders
+    app.language_list
+        .fit(areas.languages.height.saturating_sub(2) as usize);
+    app.server_list
+        .fit(areas.servers.height.saturating_sub(2) as usize);
+
+    let keys = &app.keys;
+    let (msg, style) = match app.input_mode {
+        InputMode::Normal => (
+            hint(&[
+                (keys::key(&keys.normal, Action::Help), "for help"),
+                (keys::key(&keys.normal, Action::Quit), "to exit"),
+                (keys::key(&keys.normal, Action::Search), "to start search"),
+                (
+                    keys::key(&keys.normal, Action::SwitchPane),
+                    "to switch pane",
+                ),
+                (keys::key(&keys.normal, Action::Open), "to install"),
+                (keys::key(&keys.queue, Action::Cancel), "to cancel"),
+                (keys::key(&keys.queue, Action::ToggleMark), "to select"),
+                (keys::key(&keys.normal, Action::Update), "to update"),
+                (keys::key(&keys.normal, Action::Uninstall), "to uninstall"),
+                (keys::key(&keys.normal, Action::Settings), "for settings"),
+                (keys::key(&keys.normal, Action::ToggleLog), "for the log"),
+            ]),
+            Style::default().add_modifier(Modifier::RAPID_BLINK),
+        ),
+        InputMode::ConfirmUninstall | InputMode::ConfirmQuit | InputMode::ConfirmBuild => (
+            hint(&[
+                (keys::key(&keys.popup, Action::Confirm), "to confirm"),
+                (keys::key(&keys.popup, Action::Close), "to cancel"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::Help => (
+            vec![
+                Span::raw("Press "),
+                Span::styled(
+                    keys::key(&keys.normal, Action::Help),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" or "),
+                Span::styled(
+                    keys::key(&keys.popup, Action::Close),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" to close."),
+            ],
+            Style::default(),
+        ),
+        InputMode::Error => (
+            hint(&[
+                (keys::key(&keys.popup, Action::ViewLog), "to view the log"),
+                (keys::key(&keys.popup, Action::Close), "to dismiss"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::Doctor => (
+            hint(&[(keys::key(&keys.popup, Action::Close), "to close")]),
+            Style::default(),
+        ),
+        InputMode::Versions => (
+            hint(&[
+                (keys::key(&keys.normal, Action::Next), "to move"),
+                (keys::key(&keys.popup, Action::Confirm), "to install"),
+                (keys::key(&keys.popup, Action::Close), "to close"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::Plan => (
+            hint(&[
+                (
+                    keys::key(&keys.popup, Action::Confirm),
+                    match app.plan_task {
+                        Task::Update => "to update",
+                        _ => "to install",
+                    },
+                ),
+                (keys::key(&keys.popup, Action::Close), "to cancel"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::Settings => (
+            hint(&[
+                (
+                    keys::key(&keys.popup, Action::SaveSettings),
+                    "to write them to the config file",
+                ),
+                (keys::key(&keys.popup, Action::Close), "to close"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::LocalFile => (
+            hint(&[
+                (keys::key(&keys.editing, Action::Confirm), "to install"),
+                (keys::key(&keys.editing, Action::Close), "to cancel"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::StateFile => (
+            hint(&[
+                (keys::key(&keys.editing, Action::Confirm), "to export"),
+                (keys::key(&keys.editing, Action::Close), "to cancel"),
+            ]),
+            Style::default(),
+        ),
+        InputMode::Editing => (
+            hint(&[
+                (keys::key(&keys.editing, Action::Close), "to stop editing"),
+                (
+                    keys::key(&keys.editing, Action::Confirm),
+                    "to pick the best match",
+                ),
+            ]),
+            Style::default(),
+        ),
+    };
+    let mut text = Text::from(Spans::from(msg));
+    text.patch_style(style);
+    let help_message = Paragraph::new(text);
+    f.render_widget(help_message, areas.help);
+
+    let input = Paragraph::new(app.input.as_ref())
+        .style(match app.input_mode {
+            InputMode::Editing => app.theme.search,
+            _ => Style::default(),
+        })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(match app.search_flash {
+                    Some(at) if app.now.saturating_duration_since(at) < FLASH => app.theme.error,
+                    _ => app.theme.border,
+                })
+                .title("Search"),
+        );
+    f.render_widget(input, areas.search);
+    // Otherwise the cursor stays hidden, which `Frame` does by default
+    if let InputMode::Editing = app.input_mode {
+        // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
+        f.set_cursor(
+            // Past the text before the cursor, as wide as it's displayed
+            areas.search.x + app.input[..app.cursor].width() as u16 + 1,
+            // Move one line down, from the border to the input line
+            areas.search.y + 1,
+        )
+    }
+
+    let titles = tab_titles(app).into_iter().map(Spans::from).collect();
+    let tabs = Tabs::new(titles)
+        .select(app.tab as usize)
+        .style(app.theme.muted)
+        .highlight_style(app.theme.selection)
+        .divider("|");
+    f.render_widget(tabs, areas.tabs);
+
+    // Owned, the lists getting rendered with the app borrowed mutably
+    let query = app.query().0.to_string();
+    let languages: Vec<ListItem> = app
+        .language_list
+        .items
+        .iter()
+        .map(|row| {
+            let language = match row {
+                LanguageRow::Suggested => {
+                    return ListItem::new(Spans::from(vec![
+                        Span::styled(
+                            "For this project",
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            format!(" {}", app.suggested_servers().len()),
+                            app.theme.muted,
+                        ),
+                    ]));
+                }
+                LanguageRow::Favorites => {
+                    let servers = app.favorite_servers();
+                    let installed = servers
+                        .iter()
+                        .filter(|&&i| matches!(app.statuses[i], Status::Installed))
+                        .count();
+                    return ListItem::new(Spans::from(vec![
+                        Span::styled(
+                            format!("{} Favorites", star(app)),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(format!(" {}/{}", installed, servers.len()), app.theme.muted),
+                    ]));
+                }
+                LanguageRow::Language(language) => language,
+            };
+            let servers = app.servers_for(language);
+            let installed = servers
+                .iter()
+                .filter(|&&i| matches!(app.statuses[i], Status::Installed))
+                .count();
+            let mut spans = match app.matches.get(language) {
+                Some(Match::Language) => highlight(language, &query, app.theme.matched),
+                _ => vec![Span::raw(language.as_str())],
+            };
+            spans.push(Span::styled(
+                format!(" {}/{}", installed, servers.len()),
+                app.theme.muted,
+            ));
+            match app.matches.get(language) {
+                // Only worth telling when the language itself doesn't match
+                Some(Match::Server(name)) if fuzzy::find(&query, language).is_none() => {
+                    spans.push(Span::styled(format!(" matches: {}", name), app.theme.muted))
+                }
+                Some(Match::Alias(alias)) => spans.push(Span::styled(
+                    format!(" matches: {}", alias),
+                    app.theme.muted,
+                )),
+                Some(Match::Description) => {
+                    spans.push(Span::styled(" matches: description", app.theme.muted))
+                }
+                _ => {}
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+
+    let mut title = String::from("Languages");
+    let list = &app.language_list;
+    if list.items.len() > list.height {
+        let selected = list.state.selected().map_or(0, |i| i + 1);
+        title.push_str(&format!(" {}/{}", selected, list.items.len()));
+    }
+    if app.sort != SortMode::Alphabetical {
+        title.push_str(&format!(" · {}", app.sort.name().replace('-', " ")));
+    }
+    let scrollbar = Scrollbar::of(&app.language_list, app.theme.border);
+    let languages = List::new(languages)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.border)
+                .title(title),
+        )
+        .highlight_style(highlight_style(&app.theme, app.focus == Focus::Languages))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(languages, areas.languages, &mut app.language_list.state);
+    f.render_widget(scrollbar, areas.languages);
+
+    let frame = spinner(app);
+    let favorite = format!(" {}", star(app));
+    let favorites: Vec<bool> = (0..app.servers.len()).map(|i| app.is_favorite(i)).collect();
+    let servers: Vec<ListItem> = app
+        .server_list
+        .items
+        .iter()
+        .map(|&row| {
+            let i = match row {
+                ServerRow::Header(category) => {
+                    return ListItem::new(Span::styled(
+                        format!("— {} —", category.title()),
+                        app.theme.muted.add_modifier(Modifier::BOLD),
+                    ))
+                }
+                ServerRow::Server(i) => i,
+            };
+            let server = &app.servers[i];
+            let mut spans = vec![Span::raw(if app.marked.contains(&i) {
+                "[x] "
+            } else {
+                "[ ] "
+            })];
+            spans.extend(highlight(server.name(), &query, app.theme.matched));
+            if favorites[i] {
+                spans.push(Span::styled(favorite.as_str(), app.theme.warning));
+            }
+            if server.is_custom() {
+                spans.push(Span::styled(" [custom]", app.theme.custom));
+            }
+            spans.push(match (app.queue.job(i), app.statuses[i]) {
+                (Some(job), _) if matches!(job.state, JobState::Queued) => {
+                    Span::styled("  queued", app.theme.busy)
+                }
+                (Some(job), _) if job.is_cancelling() => {
+                    Span::styled("  cancelling...", app.theme.busy)
+                }
+                (Some(job), _) if matches!(job.state, JobState::Running) => Span::styled(
+                    match job.progress {
+                        Some((done, Some(total))) if total > 0 => format!(
+                            "  {} {} {}%",
+                            frame,
+                            job.task.describe().to_lowercase(),
+                            (done * 100 / total).min(100)
+                        ),
+                        _ => format!(
+                            "  {} {}...{}",
+                            frame,
+                            job.task.describe().to_lowercase(),
+                            elapsed(job, app.now)
+                                .map(|time| format!(" {}", time))
+                                .unwrap_or_default()
+                        ),
+                    },
+                    app.theme.busy,
+                ),
+                (Some(job), _) if matches!(job.state, JobState::Failed) => {
+                    Span::styled("  failed", app.theme.error)
+                }
+                (_, Status::Installed) if app.adopted[i] => {
+                    Span::styled("  ✓ installed (external)", app.theme.installed)
+                }
+                (_, Status::Installed) => Span::styled("  ✓ installed", app.theme.installed),
+                (_, Status::NotInstalled) if app.found[i].is_some() => {
+                    Span::styled("  installed (external), not adopted", app.theme.muted)
+                }
+                (_, Status::NotInstalled) if !server.is_available() && server.can_build() => {
+                    Span::styled("  no prebuilt binary, can be built", app.theme.muted)
+                }
+                (_, Status::NotInstalled) if !server.is_available() => {
+                    Span::styled("  unavailable on this platform", app.theme.muted)
+                }
+                (_, Status::NotInstalled)
+                    if app
+                        .offline
+                        .as_ref()
+                        .is_some_and(|offline| !offline.installable[i]) =>
+                {
+                    Span::styled("  unavailable offline", app.theme.muted)
+                }
+                (_, Status::NotInstalled) => Span::styled(
+                    "  not installed",
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+                (_, Status::Broken) => Span::styled("  broken (not executable)", app.theme.error),
+            });
+            if let (Status::Installed, Some(size)) = (app.statuses[i], app.disk_sizes[i]) {
+                spans.push(Span::styled(
+                    format!("  {}", download::format_bytes(size)),
+                    app.theme.muted,
+                ));
+            }
+            if let (true, Some(installed)) = (app.held[i], &app.installed_versions[i]) {
+                spans.push(Span::styled(
+                    format!("  held at {}", installed),
+                    app.theme.muted,
+                ));
+            } else if let (Some(installed), Some(latest)) =
+                (&app.installed_versions[i], &app.latest_versions[i])
+            {
+                if version::is_newer(latest, installed) {
+                    spans.push(Span::styled(
+                        format!("  ↑ {} available", latest),
+                        app.theme.warning,
+                    ));
+                }
+            }
+            // The status shown being the one of the project's install
+            if let Some(global) = &app.global_versions[i] {
+                spans.push(Span::styled(
+                    format!("  {} installed globally", global),
+                    app.theme.muted,
+                ));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+
+    let mut title = match app.category {
+        Some(category) => String::from(category.title()),
+        None => String::from("Servers"),
+    };
+    if !app.marked.is_empty() {
+        title.push_str(&format!(" ({} selected)", app.marked.len()));
+    }
+    match app.offline.as_ref().map(|offline| offline.age) {
+        Some(Some(age)) => title.push_str(&format!(" · offline, metadata from {}", ago(age))),
+        Some(None) => title.push_str(" · offline"),
+        None => {}
+    }
+    match app.outdated().len() {
+        0 => {}
+        1 => title.push_str(" · 1 update available"),
+        n => title.push_str(&format!(" · {} updates available", n)),
+    }
+    let servers = List::new(servers)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.border)
+                .title(title),
+        )
+        .highlight_style(highlight_style(&app.theme, app.focus == Focus::Servers))
+        .highlight_symbol(">> ");
+    let scrollbar = Scrollbar::of(&app.server_list, app.theme.border);
+    f.render_stateful_widget(servers, areas.servers, &mut app.server_list.state);
+    f.render_widget(scrollbar, areas.servers);
+
+    if app.server_list.items.is_empty() {
+        let placeholder = Paragraph::new("No matching servers")
+            .style(Style::default().add_modifier(Modifier::DIM))
+            .alignment(Alignment::Center);
+        let inside = Layout::default()
+            .margin(1)
+            .constraints([Constraint::Min(0)].as_ref())
+            .split(areas.servers);
+        f.render_widget(placeholder, inside[0]);
+    }
+
+    if let Some(area) = areas.log {
+        f.render_widget(log(app, area.height.saturating_sub(2)), area);
+    }
+
+    let details = match app.server_list.server() {
+        Some(i) => details(app, i),
+        None => Text::styled(
+            "Select a server to see its details",
+            Style::default().add_modifier(Modifier::DIM),
+        ),
+    };
+    let title = match app.server_list.server() {
+        Some(i) if app.servers[i].homepage().is_some() => format!(
+            "Details · {} homepage · {} releases",
+            keys::key(&app.keys.normal, Action::OpenHomepage),
+            keys::key(&app.keys.normal, Action::OpenReleases)
+        ),
+        _ => String::from("Details"),
+    };
+    let details = Paragraph::new(details)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.border)
+                .title(title),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(details, areas.details);
+
+    let message = match &app.message {
+        Some(Message::Info(text)) => Paragraph::new(text.as_ref()),
+        Some(Message::Warning(text)) => Paragraph::new(text.as_ref()).style(app.theme.warning),
+        Some(Message::Error(text)) => Paragraph::new(text.as_ref()).style(app.theme.error),
+        None => Paragraph::new(""),
+    };
+    f.render_widget(message, areas.message);
+
+    // One row per running job, in the order they started
+    let rows = Layout::default()
+        .constraints(vec![Constraint::Length(1); app.queue.running().count()])
+        .split(areas.progress);
+    for (job, row) in app.queue.running().zip(rows) {
+        let name = app.servers[job.server].name();
+        match job.progress {
+            Some((done, Some(total))) if total > 0 => {
+                let gauge = Gauge::default()
+                    .gauge_style(app.theme.busy)
+                    .ratio((done as f64 / total as f64).min(1.0))
+                    .label(format!(
+                        "{}  {} / {}",
+                        name,
+                        download::format_bytes(done),
+                        download::format_bytes(total)
+                    ));
+                f.render_widget(gauge, row);
+            }
+            progress => {
+                // Without a Content-Length there's no ratio to show
+                let status = match (progress, &job.log) {
+                    (Some((done, _)), _) => download::format_bytes(done),
+                    (None, Some(line)) => line.clone(),
+                    (None, None) => format!("{}...", job.task.describe()),
+                };
+                let elapsed = elapsed(job, app.now)
+                    .map(|time| format!("  {}", time))
+                    .unwrap_or_default();
+                f.render_widget(
+                    Paragraph::new(format!("{} {}: {}{}", spinner(app), name, status, elapsed))
+                        .style(Style::default().add_modifier(Modifier::BOLD)),
+                    row,
+                );
+            }
+        }
+    }
+
+    f.render_widget(
+        Paragraph::new(status(app, areas.status.width as usize)).style(app.theme.status),
+        areas.status,
+    );
+
+    if let (InputMode::ConfirmUninstall, Some(server)) = (&app.input_mode, app.selected_server()) {
+        let mut lines = vec![Spans::from(format!("Remove {}? y/n", server.name()))];
+        if let Some(command) = server.uninstall_command() {
+            lines.insert(0, Spans::from(format!("This runs `{}`.", command)));
+        }
+        let area = centered_rect(50, lines.len() as u16 + 2, areas.middle);
+        let popup = Paragraph::new(lines).alignment(Alignment::Center).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.border)
+                .title("Uninstall"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    if let (InputMode::ConfirmBuild, Some(server)) = (&app.input_mode, app.selected_server()) {
+        let lines = vec![
+            Spans::from(format!(
+                "No prebuilt {} for {}.",
+                server.name(),
+                Platform::current()
+            )),
+            Spans::from(format!(
+                "Building it from source needs {}",
+                server.build_tools().join(", ")
+            )),
+            Spans::from("and may take a while. Build it? y/n"),
+        ];
+        let area = centered_rect(70, lines.len() as u16 + 2, areas.middle);
+        let popup = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(app.theme.border)
+                    .title("Build from source"),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    let prompt = match (&app.input_mode, app.selected_server()) {
+        (InputMode::LocalFile, Some(server)) => {
+            Some(format!("Install {} from the file", server.name()))
+        }
+        (InputMode::StateFile, _) => Some(String::from("Export the installed servers to")),
+        _ => None,
+    };
+    if let Some(title) = prompt {
+        let area = centered_rect(70, 3, areas.middle);
+        let popup = Paragraph::new(app.local_file.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.border)
+                .title(title),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+        f.set_cursor(
+            (area.x + app.local_file.width() as u16 + 1).min(area.right().saturating_sub(2)),
+            area.y + 1,
+        );
+    }
+
+    if let InputMode::Settings = app.input_mode {
+        let mut rows: Vec<Row> = app
+            .settings
+            .rows()
+            .into_iter()
+            .map(|(name, value, origin)| {
+                Row::new(vec![
+                    Cell::from(name),
+                    Cell::from(value),
+                    Cell::from(origin.describe())
+                        .style(Style::default().add_modifier(Modifier::DIM)),
+                ])
+            })
+            .collect();
+        rows.push(Row::new(vec![
+            Cell::from("download cache"),
+            Cell::from(download::format_bytes(app.cache.0)),
+            Cell::from(app.cache.1.display().to_string())
+                .style(Style::default().add_modifier(Modifier::DIM)),
+        ]));
+        let area = centered_rect(80, rows.len() as u16 + 3, areas.middle);
+        let table = Table::new(rows)
+            .header(
+                Row::new(vec!["Setting", "Value", "From"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .widths(
+                [
+                    Constraint::Length(14),
+                    Constraint::Percentage(60),
+                    Constraint::Percentage(40),
+                ]
+                .as_ref(),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(app.theme.border)
+                    .title(format!("Settings ({})", Config::path().display())),
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    if let InputMode::Doctor = app.input_mode {
+        doctor(f, app, areas.middle);
+    }
+
+    if let InputMode::Plan = app.input_mode {
+        plan(f, app, areas.middle);
+    }
+
+    if let InputMode::Versions = app.input_mode {
+        versions(f, app, areas.middle);
+    }
+
+    if let InputMode::ConfirmQuit = app.input_mode {
+        let area = centered_rect(50, 3, areas.middle);
+        let popup = Paragraph::new(format!(
+            "{} jobs unfinished, quit anyway? y/n",
+            app.queue.unfinished()
+        ))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.border)
+                .title("Quit"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    if let (InputMode::Error, Some(failure)) = (&app.input_mode, &app.error) {
+        error(f, &app.theme, failure);
+    }
+
+    if let InputMode::Help = app.input_mode {
+        help(f, &app.theme, &app.keys);
+    }
+}
+
+/// Counts, the first running job, the marks and the last log line, the
+/// log line cut to what's left of `width`
+fn status(app: &App, width: usize) -> Spans<'static> {
+    let installed = app
+        .statuses
+        .iter()
+        .filter(|status| matches!(status, Status::Installed))
+        .count();
+    let mut parts = vec![match app.installed_disk() {
+        0 => format!("{} servers, {} installed", app.servers.len(), installed),
+        bytes => format!(
+            "{} servers, {} installed ({})",
+            app.servers.len(),
+            installed,
+            download::format_bytes(bytes)
+        ),
+    }];
+    if let Some(project) = paths::project() {
+        let name = project.file_name().unwrap_or(project.as_os_str());
+        parts.insert(0, format!("project {}", name.to_string_lossy()));
+    }
+    match app.outdated().len() {
+        0 => {}
+        1 => parts.push(String::from("1 update")),
+        n => parts.push(format!("{} updates", n)),
+    }
+    if let Some(job) = app.queue.running().next() {
+        let name = app.servers[job.server].name();
+        let spinner = spinner(app);
+        parts.push(match job.progress {
+            Some((done, Some(total))) if total > 0 => {
+                format!("{} {} {}%", spinner, name, (done * 100 / total).min(100))
+            }
+            _ => format!(
+                "{} {} {}",
+                spinner,
+                job.task.describe().to_lowercase(),
+                name
+            ),
+        });
+        let more = app.queue.running().count() - 1;
+        if more > 0 {
+            parts.push(format!("{} more running", more));
+        }
+    }
+    if !app.marked.is_empty() {
+        parts.push(format!("{} selected", app.marked.len()));
+    }
+    let mut text = format!(" {}", parts.join(" · "));
+    if let Some(line) = app.log.back() {
+        let (Message::Info(message) | Message::Warning(message) | Message::Error(message)) =
+            &line.message;
+        let room = width.saturating_sub(text.width() + 3);
+        let last = format!("{}: {}", line.server, message);
+        if room > 1 {
+            text.push_str(" · ");
+            push_truncated(&mut text, &last, room);
+        }
+    }
+    Spans::from(text)
+}
+
+/// Push `text` onto `line`, cut with an ellipsis so it takes at most `width`
+/// columns
+fn push_truncated(line: &mut String, text: &str, width: usize) {
+    if text.width() <= width {
+        line.push_str(text);
+        return;
+    }
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        line.push(c);
+        used += w;
+    }
+    line.push('…');
+}
+
+/// Current frame of the spinner, drawn with braille dots except on the
+/// Linux console whose font lacks them
+fn spinner(app: &App) -> char {
+    if app.ascii_spinner {
+        ASCII_SPINNER[app.ticks % ASCII_SPINNER.len()]
+    } else {
+        SPINNER[app.ticks % SPINNER.len()]
+    }
+}
+
+/// Time a job has been running for, like `elapsed 1m32s`, once it's been
+/// long enough to be worth showing at `now`
+fn elapsed(job: &Job, now: Instant) -> Option<String> {
+    let secs = now.saturating_duration_since(job.started?).as_secs();
+    if secs < LONG_JOB.as_secs() {
+        return None;
+    }
+    Some(match secs {
+        0..=59 => format!("elapsed {}s", secs),
+        60..=3599 => format!("elapsed {}m{:02}s", secs / 60, secs % 60),
+        _ => format!("elapsed {}h{:02}m", secs / 3600, secs % 3600 / 60),
+    })
+}
+
+/// `age` in words, like `3 days ago`
+fn ago(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    let (count, unit) = match minutes {
+        0 => return String::from("just now"),
+        1..=59 => (minutes, "minute"),
+        60..=1439 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Hint line reading "Press <key> <what it does>, ..."
+fn hint(parts: &[(String, &'static str)]) -> Vec<Span<'static>> {
+    let mut spans = vec![Span::raw("Press ")];
+    for (i, (key, text)) in parts.iter().enumerate() {
+        spans.push(Span::styled(
+            key.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        let end = if i + 1 == parts.len() { "." } else { ", " };
+        spans.push(Span::raw(format!(" {}{}", text, end)));
+    }
+    spans
+}
+
+/// Popup with the results of the health checks, or a notice while they run
+fn doctor<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.border)
+        .title("Doctor");
+    let Some(checks) = &app.checks else {
+        let area = centered_rect(50, 3, area);
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new("Checking the installed servers...")
+                .alignment(Alignment::Center)
+                .block(block),
+            area,
+        );
+        return;
+    };
+
+    let mut rows = Vec::new();
+    for check in checks {
+        let style = match check.outcome {
+            Outcome::Pass => app.theme.installed,
+            Outcome::Warn => app.theme.warning,
+            Outcome::Fail => app.theme.error,
+        };
+        rows.push(Row::new(vec![
+            Cell::from(check.outcome.name()).style(style),
+            Cell::from(check.server.as_deref().unwrap_or("-")),
+            Cell::from(check.what),
+            Cell::from(check.detail.as_str()),
+        ]));
+        if let Some(hint) = &check.hint {
+            rows.push(Row::new(vec![
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(format!("hint: {}", hint)).style(app.theme.muted),
+            ]));
+        }
+    }
+    let width = checks
+        .iter()
+        .filter_map(|check| check.server.as_ref().map(|server| server.width()))
+        .max()
+        .unwrap_or(1);
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(width as u16),
+        Constraint::Length(7),
+        Constraint::Percentage(100),
+    ];
+    let area = centered_rect(90, rows.len() as u16 + 2, area);
+    let table = Table::new(rows).widths(&widths).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+/// Picker of the versions of a server, or a notice while they're fetched
+fn versions<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let i = app.versions_of;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.border)
+        .title(format!("Versions of {}", app.servers[i].name()));
+    let versions = match &mut app.versions {
+        Some(Ok(versions)) if !versions.items.is_empty() => versions,
+        other => {
+            let (text, style) = match other {
+                None => (String::from("Fetching the versions..."), Style::default()),
+                Some(Err(err)) => (err.clone(), app.theme.error),
+                Some(Ok(_)) => (String::from("No version found"), app.theme.muted),
+            };
+            let area = centered_rect(50, 3, area);
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(text)
+                    .style(style)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .block(block),
+                area,
+            );
+            return;
+        }
+    };
+
+    let installed = app.installed_versions[i].as_deref();
+    let items: Vec<ListItem> = versions
+        .items
+        .iter()
+        .map(|version| {
+            let mut spans = vec![Span::raw(version.as_str())];
+            if Some(version.as_str()) == installed {
+                spans.push(Span::styled("  installed", app.theme.installed));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    let area = centered_rect(40, items.len() as u16 + 2, area);
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(highlight_style(&app.theme, true))
+        .highlight_symbol(">> ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut versions.state);
+}
+
+/// Popup with what installing each chosen server would do
+fn plan<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    // A batch gets a line per server, a single server its whole plan
+    let batch = app.plan_marked.is_some();
+    let width = app
+        .plans
+        .iter()
+        .map(|(i, _)| app.servers[*i].name().width() + 2)
+        .max()
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+    for (i, plan) in &app.plans {
+        let name = Span::styled(
+            format!("{:<width$}", app.servers[*i].name()),
+            Style::default().add_modifier(Modifier::BOLD),
+        );
+        match plan {
+            None => lines.push(Spans::from(vec![
+                name,
+                Span::styled("resolving...", app.theme.muted),
+            ])),
+            Some(Ok(planned)) if batch => {
+                let mut spans = vec![
+                    name,
+                    Span::raw(format!("{:<16}", planned.plan.version)),
+                    Span::styled(
+                        format!(
+                            "{:<10}",
+                            planned
+                                .plan
+                                .size
+                                .map(download::format_bytes)
+                                .unwrap_or_default()
+                        ),
+                        app.theme.muted,
+                    ),
+                ];
+                if !planned.missing.is_empty() {
+                    spans.push(Span::styled(
+                        format!("needs {}", planned.missing.join(", ")),
+                        app.theme.error,
+                    ));
+                }
+                lines.push(Spans::from(spans));
+            }
+            Some(Ok(planned)) => {
+                lines.push(Spans::from(name));
+                for (label, value) in planned.plan.lines() {
+                    lines.push(Spans::from(vec![
+                        Span::styled(format!("  {:<8} ", label), app.theme.muted),
+                        Span::raw(value),
+                    ]));
+                }
+                if !planned.missing.is_empty() {
+                    lines.push(Spans::from(Span::styled(
+                        format!("  {} not found on PATH", planned.missing.join(", ")),
+                        app.theme.error,
+                    )));
+                }
+            }
+            Some(Err(err)) if batch => lines.push(Spans::from(vec![
+                name,
+                Span::styled(err.clone(), app.theme.error),
+            ])),
+            Some(Err(err)) => {
+                lines.push(Spans::from(name));
+                lines.push(Spans::from(Span::styled(
+                    format!("  {}", err),
+                    app.theme.error,
+                )));
+            }
+        }
+    }
+
+    let verb = app.plan_task.describe();
+    let runnable = app.runnable_plans().count();
+    let title = match app.plan_marked {
+        Some(marked) if app.plans.iter().any(|(_, plan)| plan.is_none()) => {
+            format!("{} {} of {} selected, resolving...", verb, runnable, marked)
+        }
+        Some(marked) => format!("{} {} of {} selected", verb, runnable, marked),
+        None => String::from("Dry run"),
+    };
+    let area = centered_rect(90, lines.len() as u16 + 2, area);
+    let popup = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(app.theme.border)
+            .title(title),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Lines of command output shown below the first line of an error
+const ERROR_OUTPUT_LINES: usize = 8;
+
+/// Popup showing `failure` with what caused it
+fn error<B: Backend>(f: &mut Frame<B>, theme: &Theme, failure: &Failure) {
+    // The rest of the command output is in the log
+    let mut message = failure.message.lines();
+    let mut lines = vec![Spans::from(message.next().unwrap_or_default().to_string())];
+    for line in message.take(ERROR_OUTPUT_LINES) {
+        lines.push(Spans::from(Span::styled(line.to_string(), theme.muted)));
+    }
+    for cause in &failure.causes {
+        lines.push(Spans::from(vec![
+            Span::styled("caused by: ", Style::default().add_modifier(Modifier::DIM)),
+            Span::raw(cause.clone()),
+        ]));
+    }
+    if let Some(hint) = &failure.hint {
+        lines.push(Spans::default());
+        lines.push(Spans::from(vec![
+            Span::styled("hint: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(hint.clone()),
+        ]));
+    }
+    lines.push(Spans::default());
+    lines.push(Spans::from(Span::styled(
+        format!("Details in {}", logfile::path().display()),
+        Style::default().add_modifier(Modifier::DIM),
+    )));
+
+    let width = f.size().width * 7 / 10;
+    let inner = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(inner))
+        .sum();
+    let area = centered_rect(70, rows as u16 + 2, f.size());
+    let popup = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.error)
+            .title("Error"),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Overlay listing the bindings of every mode, Normal on the left and the
+/// others on the right
+fn help<B: Backend>(f: &mut Frame<B>, theme: &Theme, keymap: &Keymap) {
+    let section = |(mode, bindings): &(&str, &[keys::Binding])| {
+        let mut lines = vec![Spans::from(Span::styled(
+            mode.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+        // Keys doing the same thing share a line
+        let mut entries: Vec<(String, &keys::Binding)> = Vec::new();
+        for binding in bindings.iter() {
+            match entries.last_mut() {
+                Some((names, last))
+                    if last.action == binding.action && last.description == binding.description =>
+                {
+                    names.push_str(&format!(", {}", keys::name(binding)))
+                }
+                _ => entries.push((keys::name(binding), binding)),
+            }
+        }
+        for (names, binding) in entries {
+            lines.push(Spans::from(vec![
+                Span::styled(format!("  {:<12} ", names), theme.matched),
+                Span::raw(binding.description),
+            ]));
+        }
+        lines.push(Spans::default());
+        lines
+    };
+    let modes = keymap.modes();
+    let (left, right) = modes.split_at(1);
+    let left: Vec<Spans> = left.iter().flat_map(section).collect();
+    let right: Vec<Spans> = right.iter().flat_map(section).collect();
+
+    let height = left.len().max(right.len()) as u16 + 2;
+    let area = centered_rect(90, height, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border)
+        .title("Help");
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(block.inner(area));
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    f.render_widget(Paragraph::new(left), columns[0]);
+    f.render_widget(Paragraph::new(right), columns[1]);
+}
+
+/// The lines of the log pane fitting in `height` rows
+fn log(app: &App, height: u16) -> Paragraph<'_> {
+    let end = app.log.len() - app.log_scroll;
+    let start = end.saturating_sub(height as usize);
+    let lines: Vec<Spans> = app
+        .log
+        .range(start..end)
+        .map(|line| {
+            let (text, style) = match &line.message {
+                Message::Info(text) => (text, Style::default()),
+                Message::Warning(text) => (text, app.theme.warning),
+                Message::Error(text) => (text, app.theme.error),
+            };
+            Spans::from(vec![
+                Span::styled(
+                    format!("[{}] ", line.server),
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+                Span::styled(text.as_str(), style),
+            ])
+        })
+        .collect();
+
+    let title = match app.log_scroll {
+        0 => String::from("Log"),
+        n => format!("Log ({} lines below, End to follow)", n),
+    };
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(app.theme.border)
+            .title(title),
+    )
+}
+
+/// Title of each tab, with the key showing it and how many servers it has
+fn tab_titles(app: &App) -> Vec<String> {
+    Tab::ALL
+        .iter()
+        .map(|&tab| {
+            let action = match tab {
+                Tab::All => Action::TabAll,
+                Tab::Installed => Action::TabInstalled,
+                Tab::Updates => Action::TabUpdates,
+            };
+            format!(
+                "[{}] {} ({})",
+                keys::key(&app.keys.normal, action),
+                tab.title(),
+                app.tab_count(tab)
+            )
+        })
+        .collect()
+}
+
+/// Tab whose title is at column `x` of the tab bar in `area`, laid out the
+/// way `Tabs` draws it: padded by a space on each side, then a divider
+pub fn tab_at(area: Rect, app: &App, x: u16) -> Option<Tab> {
+    let mut start = area.x;
+    for (tab, title) in Tab::ALL.into_iter().zip(tab_titles(app)) {
+        let end = start + title.width() as u16 + 2;
+        if (start..end).contains(&x) {
+            return Some(tab);
+        }
+        start = end + 1;
+    }
+    None
+}
+
+/// How long the search box stays red after Enter found nothing
+const FLASH: Duration = Duration::from_millis(300);
+
+/// Rows taken by the details pane, borders included
+const DETAILS_HEIGHT: u16 = 7;
+
+/// Terminal rows below which the details pane is left out
+const ROWS_FOR_DETAILS: u16 = 24;
+
+/// What the details pane shows about `app.servers[i]`
+fn details(app: &App, i: usize) -> Text<'static> {
+    let server = &app.servers[i];
+    let field = |key: &str, value: String| {
+        vec![
+            Span::styled(
+                format!("{}: ", key),
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+            Span::raw(format!("{}  ", value)),
+        ]
+    };
+
+    let mut about = field("languages", server.languages().join(", "));
+    about.extend(field("backend", server.backend().to_string()));
+    if let Some(homepage) = server.homepage() {
+        about.extend(field("homepage", homepage));
+    }
+
+    let installed = match &app.installed_versions[i] {
+        Some(version) if app.held[i] => format!("{} (held)", version),
+        Some(version) => version.clone(),
+        None => String::from("no"),
+    };
+    let mut versions = match paths::project() {
+        Some(_) => field("in the project", installed),
+        None => field("installed", installed),
+    };
+    if let Some(global) = &app.global_versions[i] {
+        versions.extend(field("globally", global.clone()));
+    }
+    if let Some(reported) = &app.reported_versions[i] {
+        versions.extend(field("reports", reported.clone()));
+    }
+    if let Some(previous) = &app.previous_versions[i] {
+        versions.extend(field("previous", previous.clone()));
+    }
+    if let Some(latest) = &app.latest_versions[i] {
+        versions.extend(field("latest", latest.clone()));
+    }
+    if let Some(size) = app.download_sizes[i] {
+        versions.extend(field("download", download::format_bytes(size)));
+    }
+    if let Some(size) = app.disk_sizes[i] {
+        versions.extend(field("disk", download::format_bytes(size)));
+    }
+
+    let bin = match &app.found[i] {
+        Some(found) => found.clone(),
+        None => app.launchers[i].clone(),
+    };
+    let mut paths = field("bin", bin.display().to_string());
+    for (key, value) in &app.details[i] {
+        paths.extend(field(key, value.clone()));
+    }
+
+    Text::from(vec![
+        Spans::from(server.description().to_string()),
+        Spans::from(about),
+        Spans::from(versions),
+        Spans::from(paths),
+    ])
+}
+
+/// Spans of `text` with the characters matched by `query` emphasized
+fn highlight<'a>(text: &'a str, query: &str, matched: Style) -> Vec<Span<'a>> {
+    let positions = match fuzzy::find(query, text) {
+        Some((_, positions)) if !positions.is_empty() => positions,
+        _ => return vec![Span::raw(text)],
+    };
+
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for (j, (offset, c)) in text.char_indices().enumerate() {
+        if positions.contains(&j) {
+            if start < offset {
+                spans.push(Span::raw(&text[start..offset]));
+            }
+            let end = offset + c.len_utf8();
+            spans.push(Span::styled(&text[offset..end], matched));
+            start = end;
+        }
+    }
+    if start < text.len() {
+        spans.push(Span::raw(&text[start..]));
+    }
+    spans
+}
+
+/// Selection style of a pane, dimmed while the other pane has focus
+fn highlight_style(theme: &Theme, focused: bool) -> Style {
+    if focused {
+        theme.selection
+    } else {
+        theme.selection_inactive
+    }
+}
+
+/// Part of a list in view, drawn over the right border of its block once
+/// the list doesn't fit
+struct Scrollbar {
+    offset: usize,
+    visible: usize,
+    total: usize,
+    style: Style,
+}
+
+impl Scrollbar {
+    /// Scrollbar of `list` as last fitted to its block
+    fn of<T>(list: &StatefulList<T>, style: Style) -> Scrollbar {
+        Scrollbar {
+            offset: list.offset,
+            visible: list.height,
+            total: list.items.len(),
+            style,
+        }
+    }
+}
+
+impl Widget for Scrollbar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Rows between the top and bottom borders
+        let track = area.height.saturating_sub(2) as usize;
+        if self.total <= self.visible || track == 0 || area.width == 0 {
+            return;
+        }
+        let thumb = (track * self.visible / self.total).clamp(1, track);
+        let scrolled = self.offset.min(self.total - self.visible);
+        let start = (track - thumb) * scrolled / (self.total - self.visible);
+        let x = area.right() - 1;
+        for row in start..start + thumb {
+            buf.get_mut(x, area.y + 1 + row as u16)
+                .set_symbol("█")
+                .set_style(self.style);
+        }
+    }
+}
+
+/// Marker of the favorite servers, plain on the Linux console
+fn star(app: &App) -> char {
+    if app.ascii_spinner {
+        '*'
+    } else {
+        '★'
+    }
+}
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const ASCII_SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Running time past which jobs show how long they've been running
+const LONG_JOB: Duration = Duration::from_secs(10);
+
+/// Rect of `percent_x` of the width and `height` rows centered inside `r`
+fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(r.height.saturating_sub(height) / 2),
+                Constraint::Length(height),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::{app, key};
+    use crossterm::event::KeyCode;
+    use tui::{backend::TestBackend, Terminal};
+
+    /// Rows `rows` of the screen `app` draws on a terminal of 100x30,
+    /// without their trailing spaces
+    fn render(app: &mut App, rows: std::ops::Range<u16>) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        rows.map(|y| {
+            let row: String = (0..buffer.area.width)
+                .map(|x| buffer.get(x, y).symbol.as_str())
+                .collect();
+            row.trim_end().to_string()
+        })
+        .collect()
+    }
+
+    fn servers() -> App {
+        app(&["clangd", "gopls", "pyright", "rust-analyzer"])
+    }
+
+    fn search(app: &mut App, query: &str) {
+        app.handle_key(key(KeyCode::Char('e')));
+        for c in query.chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn fresh_start() {
+        let mut app = servers();
+        assert_eq!(
+            render(&mut app, 6..14),
+            [
+                "   [1] All (4) | [2] Installed (0) | [3] Updates (0)",
+                "  ┌Languages─────────────────┐┌Servers───────────────────────────────────────────────────────────┐",
+                "  │>> c 0/1                  ││>> [ ] clangd  not installed                                      │",
+                "  │   cpp 0/1                ││                                                                  │",
+                "  │   go 0/1                 ││                                                                  │",
+                "  │   python 0/1             ││                                                                  │",
+                "  │   rust 0/1               ││                                                                  │",
+                "  │                          ││                                                                  │",
+            ]
+        );
+        assert_eq!(
+            render(&mut app, 20..23),
+            [
+                "  │C and C++ language server from the LLVM project.                                              │",
+                "  │languages: c, cpp  backend: github-release  homepage: https://clangd.llvm.org                 │",
+                "  │installed: no                                                                                 │",
+            ]
+        );
+        assert_eq!(render(&mut app, 27..28), ["   4 servers, 0 installed"]);
+    }
+
+    #[test]
+    fn search_with_matches() {
+        let mut app = servers();
+        search(&mut app, "py");
+        assert_eq!(
+            render(&mut app, 2..10),
+            [
+                "  Press Esc to stop editing, Enter to pick the best match.",
+                "  ┌Search────────────────────────────────────────────────────────────────────────────────────────┐",
+                "  │py                                                                                            │",
+                "  └──────────────────────────────────────────────────────────────────────────────────────────────┘",
+                "   [1] All (4) | [2] Installed (0) | [3] Updates (0)",
+                "  ┌Languages─────────────────┐┌Servers───────────────────────────────────────────────────────────┐",
+                "  │>> python 0/1 matches: py ││>> [ ] pyright  not installed                                     │",
+                "  │                          ││                                                                  │",
+            ]
+        );
+    }
+
+    #[test]
+    fn search_without_matches() {
+        let mut app = servers();
+        search(&mut app, "pyz");
+        assert_eq!(
+            render(&mut app, 7..10),
+            [
+                "  ┌Languages─────────────────┐┌Servers───────────────────────────────────────────────────────────┐",
+                "  │                          ││                        No matching servers                       │",
+                "  │                          ││                                                                  │",
+            ]
+        );
+        assert_eq!(
+            render(&mut app, 19..21),
+            [
+                "  ┌Details───────────────────────────────────────────────────────────────────────────────────────┐",
+                "  │Select a server to see its details                                                            │",
+            ]
+        );
+    }
+
+    #[test]
+    fn install_in_progress() {
+        let mut app = servers();
+        // Down to go, whose only server is gopls
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        app.queue.push(1, Task::Install);
+        app.queue.start_next();
+        assert_eq!(
+            render(&mut app, 8..11),
+            [
+                "  │   c 0/1                  ││>> [ ] gopls  ⠋ installing...                                     │",
+                "  │   cpp 0/1                ││                                                                  │",
+                "  │>> go 0/1                 ││                                                                  │",
+            ]
+        );
+        assert_eq!(
+            render(&mut app, 25..28),
+            [
+                "  ⠋ gopls: Installing...",
+                "",
+                "   4 servers, 0 installed · ⠋ installing gopls",
+            ]
+        );
+    }
+
+    #[test]
+    fn error_popup() {
+        let mut app = app(&["gopls"]);
+        app.error = Some(Failure {
+            message: String::from("couldn't install gopls: go exited with status 1"),
+            causes: vec![String::from("no Go toolchain found")],
+            hint: Some(String::from("Install Go 1.21 or newer")),
+        });
+        app.input_mode = InputMode::Error;
+        assert_eq!(
+            render(&mut app, 2..3),
+            ["  Press l to view the log, Esc to dismiss."]
+        );
+        assert_eq!(
+            render(&mut app, 11..17),
+            [
+                "  │            ┌Error───────────────────────────────────────────────────────────────┐            │",
+                "  │            │couldn't install gopls: go exited with status 1                     │            │",
+                "  │            │caused by: no Go toolchain found                                    │            │",
+                "  │            │                                                                    │            │",
+                "  │            │hint: Install Go 1.21 or newer                                      │            │",
+                "  │            │                                                                    │            │",
+            ]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Downloaded archives kept in `downloads/` of the cache directory, so
+//! reinstalling a version doesn't fetch it again. Entries are keyed by the
+//! URL and the checksum they were expected to have, and checked against
+//! their SHA-256 again before being used.
+
+use crate::{download, paths};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Directory of the cached downloads
+pub fn dir() -> PathBuf {
+    paths::cache_dir().join("downloads")
+}
+
+/// Entry of the download of `url`, expected to have the SHA-256 `expected`
+fn entry(url: &str, expected: Option<&str>) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(expected.unwrap_or_default().to_ascii_lowercase().as_bytes());
+    let key: String = hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    dir().join(key)
+}
+
+/// SHA-256 recorded next to `entry` when it got stored
+fn digest_file(entry: &Path) -> PathBuf {
+    entry.with_extension("sha256")
+}
+
+/// Put the cached download of `url` at `dest`, returning its SHA-256, when
+/// there's one matching `expected` or the digest it was stored with.
+/// Entries that don't match anymore get dropped.
+pub fn restore(url: &str, expected: Option<&str>, dest: &Path) -> Option<String> {
+    let entry = entry(url, expected);
+    let stored = fs::read_to_string(digest_file(&entry)).ok()?;
+    let want = expected.unwrap_or(stored.trim()).to_ascii_lowercase();
+    match download::sha256_file(&entry) {
+        Ok(digest) if digest == want => {}
+        _ => {
+            remove(&entry);
+            return None;
+        }
+    }
+    link_or_copy(&entry, dest).ok()?;
+    // Cleaning goes by the last use
+    if let Ok(file) = fs::File::options().append(true).open(&entry) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(want)
+}
+
+/// Whether there's a cached download of `url` expected to have `expected`
+pub fn contains(url: &str, expected: Option<&str>) -> bool {
+    let entry = entry(url, expected);
+    entry.is_file() && digest_file(&entry).is_file()
+}
+
+/// Keep the verified download of `url` at `path`, whose SHA-256 is
+/// `digest`. The entry only appears once complete, a failure only means
+/// the next install downloads it again.
+pub fn store(url: &str, expected: Option<&str>, path: &Path, digest: &str) {
+    let entry = entry(url, expected);
+    let temp = entry.with_extension("tmp");
+    let res = fs::create_dir_all(dir())
+        .and_then(|()| {
+            let _ = fs::remove_file(&temp);
+            link_or_copy(path, &temp)
+        })
+        .and_then(|()| fs::write(digest_file(&entry), digest))
+        .and_then(|()| fs::rename(&temp, &entry));
+    if res.is_err() {
+        let _ = fs::remove_file(temp);
+        remove(&entry);
+    }
+}
+
+/// Hard link `from` to `to`, copying it where linking isn't possible
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    fs::hard_link(from, to).or_else(|_| fs::copy(from, to).map(drop))
+}
+
+fn remove(entry: &Path) {
+    let _ = fs::remove_file(entry);
+    let _ = fs::remove_file(digest_file(entry));
+}
+
+/// Bytes taken by the cached downloads
+pub fn size() -> u64 {
+    fs::read_dir(dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Remove the downloads not used for `older_than`, or all of them, returning
+/// how many got removed and the bytes freed
+pub fn clean(older_than: Option<Duration>) -> io::Result<(usize, u64)> {
+    let entries = match fs::read_dir(dir()) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok((0, 0)),
+        Err(err) => return Err(err),
+    };
+    let now = SystemTime::now();
+    let (mut count, mut freed) = (0, 0);
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some() {
+            // Digests go with their entry, the rest are leftovers of stores
+            // that didn't finish
+            let digest = path
+                .extension()
+                .is_some_and(|extension| extension == "sha256");
+            if !digest || !path.with_extension("").exists() {
+                // Gone already when its entry was removed
+                if let Ok(metadata) = fs::metadata(&path) {
+                    freed += metadata.len();
+                    let _ = fs::remove_file(&path);
+                }
+            }
+            continue;
+        }
+        let metadata = fs::metadata(&path)?;
+        let unused = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if older_than.is_some_and(|older_than| unused < older_than) {
+            continue;
+        }
+        freed += metadata.len() + fs::metadata(digest_file(&path)).map_or(0, |m| m.len());
+        remove(&path);
+        count += 1;
+    }
+    Ok((count, freed))
+}
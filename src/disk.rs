@@ -0,0 +1,48 @@
+//! Space taken by installs, and left for them where the install root is.
+
+use std::{fs, path::Path};
+
+/// Bytes taken by the files under `path`, or under what it links to,
+/// symbolic links below it counted as themselves rather than what they
+/// point to
+pub fn size(path: &Path) -> u64 {
+    tree(&fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+fn tree(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| tree(&entry.path()))
+        .sum()
+}
+
+/// Bytes the user can still write to the filesystem holding `path`, or
+/// the closest of its parents that exists. `None` where it can't be told.
+#[cfg(unix)]
+pub fn available(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, mem, os::unix::ffi::OsStrExt};
+
+    let existing = path.ancestors().find(|dir| dir.exists())?;
+    let path = CString::new(existing.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs only writes to the struct it's given
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available(_path: &Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,278 @@
+//! Health checks of the installed servers, for when an install succeeded
+//! but the editor can't start the server.
+
+use crate::{
+    disk, download, paths,
+    servers::{self, shim, Server, Status},
+};
+use std::{path::Path, sync::Arc, time::Duration};
+
+/// How long a server gets to answer `--version` or `--help`
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "FAIL",
+        }
+    }
+}
+
+pub struct Check {
+    /// Server checked, `None` for checks of the installer itself
+    pub server: Option<String>,
+    /// What got checked, like `runtime`
+    pub what: &'static str,
+    pub outcome: Outcome,
+    pub detail: String,
+    /// How to fix a failure or warning
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(server: &dyn Server, what: &'static str, outcome: Outcome, detail: String) -> Check {
+        Check {
+            server: Some(server.name().to_string()),
+            what,
+            outcome,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Check {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+/// Check every installed server in `servers`, then whether the directory
+/// linking their executables is on `PATH` and the install root has room
+/// for the `pending` bytes of downloads still to be made
+pub fn run(servers: &[Arc<dyn Server>], pending: u64) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for server in servers {
+        match server.status() {
+            Status::NotInstalled => {}
+            Status::Broken => checks.push(
+                Check::new(
+                    server.as_ref(),
+                    "binary",
+                    Outcome::Fail,
+                    format!("{} is not an executable file", server.bin().display()),
+                )
+                .hint(reinstall(server.as_ref())),
+            ),
+            Status::Installed => {
+                let external = server.external();
+                let bin = external.clone().unwrap_or_else(|| server.bin());
+                checks.push(Check::new(
+                    server.as_ref(),
+                    "binary",
+                    Outcome::Pass,
+                    match external {
+                        Some(_) => format!("{} (external)", bin.display()),
+                        None => bin.display().to_string(),
+                    },
+                ));
+                if let Some(runtime) = server.runtime() {
+                    checks.push(if servers::has_command(&runtime.program, &["--version"]) {
+                        Check::new(
+                            server.as_ref(),
+                            "runtime",
+                            Outcome::Pass,
+                            format!("{} runs", runtime.program),
+                        )
+                    } else {
+                        Check::new(
+                            server.as_ref(),
+                            "runtime",
+                            Outcome::Fail,
+                            format!("{} can't be run", runtime.program),
+                        )
+                        .hint(runtime.hint)
+                    });
+                }
+                for (package, file) in server.companions() {
+                    checks.push(if file.is_file() {
+                        Check::new(
+                            server.as_ref(),
+                            "package",
+                            Outcome::Pass,
+                            format!("{} resolves from {}", package, server.dir().display()),
+                        )
+                    } else {
+                        Check::new(
+                            server.as_ref(),
+                            "package",
+                            Outcome::Fail,
+                            format!("{} has no {}", server.dir().display(), package),
+                        )
+                        .hint(reinstall(server.as_ref()))
+                    });
+                }
+                checks.push(starts(server.as_ref(), &bin));
+                if external.is_none() {
+                    checks.push(shim(server.as_ref()));
+                }
+            }
+        }
+    }
+    checks.push(path());
+    checks.extend(space(pending));
+    checks
+}
+
+/// Whether the server starts at all, `--help` being tried when it doesn't
+/// know `--version`
+fn starts(server: &dyn Server, bin: &Path) -> Check {
+    if server.meta().verify.as_deref() == Some("alive") {
+        return alive(server, bin);
+    }
+    let mut statuses = Vec::new();
+    for flag in ["--version", "--help"] {
+        match servers::probe(bin, &[flag], TIMEOUT).map(|probe| probe.status) {
+            Ok(Some(status)) if status.success() => {
+                return Check::new(
+                    server,
+                    "starts",
+                    Outcome::Pass,
+                    format!("`{}` succeeded", flag),
+                )
+            }
+            Ok(Some(status)) => statuses.push(format!("`{}` {}", flag, status)),
+            // Waiting for a client, the way some servers answer any flag
+            Ok(None) => {
+                return Check::new(
+                    server,
+                    "starts",
+                    Outcome::Warn,
+                    format!("`{}` still running after {}s", flag, TIMEOUT.as_secs()),
+                )
+                .hint("the server may ignore flags, check it from the editor")
+            }
+            Err(err) => {
+                return Check::new(server, "starts", Outcome::Fail, err.to_string())
+                    .hint(reinstall(server))
+            }
+        }
+    }
+    Check::new(server, "starts", Outcome::Warn, statuses.join(", ")).hint(format!(
+        "run {} by hand to see what it prints",
+        bin.display()
+    ))
+}
+
+/// Whether a server answering no flag keeps running while it waits for a
+/// client on stdio
+fn alive(server: &dyn Server, bin: &Path) -> Check {
+    let wait = servers::ALIVE_WAIT.as_secs();
+    match servers::alive(bin, &[], servers::ALIVE_WAIT) {
+        Ok(None) => Check::new(
+            server,
+            "starts",
+            Outcome::Pass,
+            format!("still waiting for a client after {}s", wait),
+        ),
+        Ok(Some(status)) => Check::new(
+            server,
+            "starts",
+            Outcome::Fail,
+            format!("exited within {}s without a client, {}", wait, status),
+        )
+        .hint(format!(
+            "run {} by hand to see what it prints",
+            bin.display()
+        )),
+        Err(err) => {
+            Check::new(server, "starts", Outcome::Fail, err.to_string()).hint(reinstall(server))
+        }
+    }
+}
+
+fn reinstall(server: &dyn Server) -> String {
+    format!(
+        "reinstall it with `lsp_installer install {}`",
+        server.name()
+    )
+}
+
+/// Whether the bin directory entry of the server starts its executable
+fn shim(server: &dyn Server) -> Check {
+    let path = shim::path(server);
+    if shim::is_current(server) {
+        Check::new(server, "shim", Outcome::Pass, path.display().to_string())
+    } else {
+        Check::new(
+            server,
+            "shim",
+            Outcome::Warn,
+            format!(
+                "{} doesn't start {}",
+                path.display(),
+                server.bin().display()
+            ),
+        )
+        .hint(reinstall(server))
+    }
+}
+
+/// Whether the disk of the install root has room for `pending` bytes,
+/// `None` when its free space can't be told
+fn space(pending: u64) -> Option<Check> {
+    let available = disk::available(paths::root())?;
+    let mut check = Check {
+        server: None,
+        what: "disk",
+        outcome: Outcome::Pass,
+        detail: format!(
+            "{} free in {}",
+            download::format_bytes(available),
+            paths::root().display()
+        ),
+        hint: None,
+    };
+    if available < pending {
+        check.outcome = Outcome::Warn;
+        check.detail = format!(
+            "{} free in {}, the pending downloads take {}",
+            download::format_bytes(available),
+            paths::root().display(),
+            download::format_bytes(pending)
+        );
+        check.hint = Some(String::from(
+            "free some space, or set install_root to a larger disk",
+        ));
+    }
+    Some(check)
+}
+
+/// Whether editors can find the executables linked into the bin directory
+fn path() -> Check {
+    let dir = paths::bin_dir();
+    let mut check = Check {
+        server: None,
+        what: "PATH",
+        outcome: Outcome::Pass,
+        detail: format!("{} is on PATH", dir.display()),
+        hint: None,
+    };
+    if !shim::on_path() {
+        check.outcome = Outcome::Warn;
+        check.detail = format!("{} is not on PATH", dir.display());
+        check.hint = Some(format!(
+            "add `{}` to your shell profile, or point the editor at each server's full path",
+            shim::path_line()
+        ));
+    }
+    check
+}
@@ -0,0 +1,584 @@
+use crate::error::{Context, Error, Result};
+use rustls::pki_types::{pem::PemObject, CertificateDer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Minimum number of bytes between two progress reports
+const PROGRESS_STEP: u64 = 64 * 1024;
+
+/// What to do with downloads nothing publishes a checksum for
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumPolicy {
+    /// Refuse to install them
+    Require,
+    /// Install them with a warning
+    #[default]
+    Warn,
+    /// Install them silently
+    Skip,
+}
+
+impl ChecksumPolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChecksumPolicy::Require => "require",
+            ChecksumPolicy::Warn => "warn",
+            ChecksumPolicy::Skip => "skip",
+        }
+    }
+}
+
+static CHECKSUM_POLICY: OnceLock<ChecksumPolicy> = OnceLock::new();
+
+/// Set the policy for unchecked downloads, only effective before it's read
+pub fn set_checksum_policy(policy: ChecksumPolicy) {
+    let _ = CHECKSUM_POLICY.set(policy);
+}
+
+pub fn checksum_policy() -> ChecksumPolicy {
+    *CHECKSUM_POLICY.get_or_init(ChecksumPolicy::default)
+}
+
+/// Proxy set in the config file, used instead of the environment
+static PROXY: OnceLock<Option<String>> = OnceLock::new();
+/// PEM bundle of extra certificate authorities to trust
+static CA_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+/// Agent of each proxy in use, the one at `None` connecting directly
+static AGENTS: Mutex<BTreeMap<Option<String>, ureq::Agent>> = Mutex::new(BTreeMap::new());
+
+/// Set once the network is found unreachable, or asked for with `--offline`
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Stop making requests, they fail right away from now on
+pub fn set_offline() {
+    OFFLINE.store(true, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Whether `transport` failed because there's no network at all, rather
+/// than a problem with one host
+fn unreachable(transport: &ureq::Transport) -> bool {
+    let kind = std::error::Error::source(transport)
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .map(io::Error::kind);
+    transport.kind() == ureq::ErrorKind::Dns
+        || matches!(
+            kind,
+            Some(io::ErrorKind::NetworkUnreachable | io::ErrorKind::HostUnreachable)
+        )
+}
+
+/// Set the proxy and the certificate authorities downloads go through, only
+/// effective before the first request
+pub fn set_network(proxy: Option<String>, ca_file: Option<PathBuf>) {
+    let _ = PROXY.set(proxy);
+    let _ = CA_FILE.set(ca_file);
+}
+
+/// Proxy requests to `url` go through: the one of the config file, else
+/// the one of `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` for its scheme,
+/// none of them when `NO_PROXY` lists its host
+pub fn proxy_for(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let host = rest
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('@')
+        .next()
+        .unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    if bypassed(host, &env_var("NO_PROXY").unwrap_or_default()) {
+        return None;
+    }
+    if let Some(proxy) = PROXY.get().cloned().flatten() {
+        return Some(proxy);
+    }
+    let var = if scheme == "https" {
+        "HTTPS_PROXY"
+    } else {
+        "HTTP_PROXY"
+    };
+    env_var(var).or_else(|| env_var("ALL_PROXY"))
+}
+
+/// Value of the environment variable `name`, in upper or lower case as
+/// tools set either, when it's not empty
+fn env_var(name: &str) -> Option<String> {
+    [name.to_string(), name.to_lowercase()]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Whether `no_proxy`, a comma separated list of hosts and domains, covers
+/// `host`. `*` covers every host, `example.com` and `.example.com` cover
+/// the domain and its subdomains alike.
+fn bypassed(host: &str, no_proxy: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            entry == "*"
+                || host == entry
+                || host
+                    .strip_suffix(&entry)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+}
+
+/// `url` with the password of its userinfo left out, for messages
+pub fn redact(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let prefix = if scheme.is_empty() {
+        String::new()
+    } else {
+        format!("{}://", scheme)
+    };
+    match rest.split_once('@') {
+        Some((userinfo, host)) => {
+            let user = userinfo.split(':').next().unwrap_or_default();
+            format!("{}{}:***@{}", prefix, user, host)
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Agent for requests through `proxy`, made on first use
+fn agent(proxy: Option<&str>) -> Result<ureq::Agent> {
+    let mut agents = AGENTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let key = proxy.map(str::to_string);
+    if let Some(agent) = agents.get(&key) {
+        return Ok(agent.clone());
+    }
+    let mut builder = ureq::AgentBuilder::new().try_proxy_from_env(false);
+    if let Some(proxy) = proxy {
+        let parsed = ureq::Proxy::new(proxy)
+            .map_err(|err| Error::Config(format!("invalid proxy {}: {}", redact(proxy), err)))?;
+        builder = builder.proxy(parsed);
+    }
+    if let Some(ca_file) = CA_FILE.get().cloned().flatten() {
+        builder = builder.tls_config(tls_config(&ca_file)?);
+    }
+    let agent = builder.build();
+    agents.insert(key, agent.clone());
+    Ok(agent)
+}
+
+/// TLS settings trusting the certificates of `ca_file` on top of the
+/// usual authorities, for proxies that intercept TLS
+fn tls_config(ca_file: &Path) -> Result<Arc<rustls::ClientConfig>> {
+    let invalid = |err: &dyn std::fmt::Display| {
+        Error::Config(format!(
+            "can't load the CA bundle {}: {}",
+            ca_file.display(),
+            err
+        ))
+    };
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let certificates = CertificateDer::pem_file_iter(ca_file).map_err(|err| invalid(&err))?;
+    for certificate in certificates {
+        roots
+            .add(certificate.map_err(|err| invalid(&err))?)
+            .map_err(|err| invalid(&err))?;
+    }
+    let config = rustls::ClientConfig::builder_with_provider(
+        rustls::crypto::ring::default_provider().into(),
+    )
+    .with_safe_default_protocol_versions()
+    .map_err(|err| invalid(&err))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// GET request for `url`, going through the proxy that applies to it
+pub fn request(url: &str) -> Result<ureq::Request> {
+    if is_offline() {
+        return Err(Error::Offline {
+            url: url.to_string(),
+        });
+    }
+    Ok(agent(proxy_for(url).as_deref())?.get(url))
+}
+
+/// Send a GET request, turning transport failures and non-2xx responses
+/// into readable errors
+pub fn get(url: &str) -> Result<ureq::Response> {
+    send(request(url)?)
+}
+
+/// Send `request` with the same error handling as `get()`. Errors tell the
+/// proxy used and the step that failed, the proxy connection, TLS or HTTP.
+pub fn send(request: ureq::Request) -> Result<ureq::Response> {
+    call(request).map_err(|failure| failure.error)
+}
+
+/// Why an attempt at a download failed, and whether trying again may help
+struct Failure {
+    error: Error,
+    transient: bool,
+}
+
+impl Failure {
+    fn fatal(error: impl Into<Error>) -> Failure {
+        Failure {
+            error: error.into(),
+            transient: false,
+        }
+    }
+
+    fn transient(error: Error) -> Failure {
+        Failure {
+            error,
+            transient: true,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self.error {
+            Error::Network { status, .. } => status,
+            _ => None,
+        }
+    }
+}
+
+/// Failure of the transfer from `url` itself, after it started
+fn broken(url: &str, reason: String, source: Option<io::Error>) -> Failure {
+    Failure::transient(Error::Network {
+        url: url.to_string(),
+        reason,
+        status: None,
+        attempts: 1,
+        source: source.map(|source| source.into()),
+    })
+}
+
+fn call(request: ureq::Request) -> std::result::Result<ureq::Response, Failure> {
+    let url = request.url().to_string();
+    let via = proxy_for(&url)
+        .map(|proxy| format!(" through the proxy {}", redact(&proxy)))
+        .unwrap_or_default();
+    request.call().map_err(|err| match err {
+        ureq::Error::Status(code, response) => Failure {
+            error: Error::Network {
+                reason: format!(
+                    "{} responded with HTTP {} {}{}",
+                    url,
+                    code,
+                    response.status_text(),
+                    via
+                ),
+                url,
+                status: Some(code),
+                attempts: 1,
+                source: None,
+            },
+            transient: code >= 500 || code == 429,
+        },
+        ureq::Error::Transport(transport) => {
+            if unreachable(&transport) {
+                set_offline();
+            }
+            let (step, transient) = match transport.kind() {
+                ureq::ErrorKind::Dns => ("name lookup", true),
+                ureq::ErrorKind::InvalidProxyUrl | ureq::ErrorKind::ProxyUnauthorized => {
+                    ("proxy CONNECT", false)
+                }
+                ureq::ErrorKind::ProxyConnect => ("proxy CONNECT", true),
+                ureq::ErrorKind::ConnectionFailed
+                    if transport
+                        .message()
+                        .is_some_and(|message| message.contains("tls")) =>
+                {
+                    ("TLS handshake", false)
+                }
+                ureq::ErrorKind::ConnectionFailed if !via.is_empty() => {
+                    ("connection to the proxy", true)
+                }
+                ureq::ErrorKind::ConnectionFailed => ("connection", true),
+                ureq::ErrorKind::Io => ("HTTP exchange", true),
+                _ => ("HTTP exchange", false),
+            };
+            Failure {
+                error: Error::Network {
+                    reason: format!("could not reach {}{}, the {} failed", url, via, step),
+                    url,
+                    status: None,
+                    attempts: 1,
+                    source: Some(Box::new(transport)),
+                },
+                transient,
+            }
+        }
+    })
+}
+
+/// Downloads retried after a transient failure, unless set otherwise
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after it
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+static RETRIES: OnceLock<u32> = OnceLock::new();
+
+/// Set how many times a download is retried, only effective before it's
+/// read
+pub fn set_retries(retries: u32) {
+    let _ = RETRIES.set(retries);
+}
+
+fn retries() -> u32 {
+    *RETRIES.get_or_init(|| DEFAULT_RETRIES)
+}
+
+/// Stream `url` into `dest`, calling `on_progress` with the bytes written so
+/// far and the total size when the server sent a Content-Length. An error
+/// returned by `on_progress` aborts the download.
+///
+/// The body is written to a `.part` file renamed over `dest` once complete,
+/// so an interrupted download never shows up at `dest`. Returns the SHA-256
+/// of the body in lowercase hex, hashed as it streams in.
+///
+/// Connection failures, 5xx responses and bodies cut short are retried
+/// with a growing wait in between. Retries resume from the end of the
+/// `.part` file when the server honors the Range header.
+pub fn download(
+    url: &str,
+    dest: &Path,
+    on_progress: &mut dyn FnMut(u64, Option<u64>) -> Result<()>,
+) -> Result<String> {
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    let part = Path::new(&part);
+    let _ = fs::remove_file(part);
+
+    let mut attempts = 0;
+    let res = loop {
+        attempts += 1;
+        match attempt(url, part, on_progress) {
+            Ok(digest) => break Ok(digest),
+            Err(failure) if failure.transient && attempts <= retries() => {
+                thread::sleep(RETRY_DELAY * 2u32.pow(attempts - 1));
+            }
+            Err(Failure {
+                error:
+                    Error::Network {
+                        url,
+                        reason,
+                        status,
+                        source,
+                        ..
+                    },
+                ..
+            }) => {
+                break Err(Error::Network {
+                    url,
+                    reason,
+                    status,
+                    attempts,
+                    source,
+                })
+            }
+            Err(failure) => break Err(failure.error),
+        }
+    };
+    match res {
+        Ok(digest) => {
+            fs::rename(part, dest).at(dest)?;
+            Ok(digest)
+        }
+        Err(err) => {
+            let _ = fs::remove_file(part);
+            Err(err)
+        }
+    }
+}
+
+/// Download `url` into `part`, carrying on after the bytes already in it
+/// when the server allows
+fn attempt(
+    url: &str,
+    part: &Path,
+    on_progress: &mut dyn FnMut(u64, Option<u64>) -> Result<()>,
+) -> std::result::Result<String, Failure> {
+    let written = fs::metadata(part).map_or(0, |metadata| metadata.len());
+    let mut request = request(url).map_err(Failure::fatal)?;
+    if written > 0 {
+        request = request.set("Range", &format!("bytes={}-", written));
+    }
+    let response = match call(request) {
+        // What's there already is all of it, or the file changed since
+        Err(failure) if written > 0 && failure.status() == Some(416) => {
+            let _ = fs::remove_file(part);
+            return Err(Failure::transient(failure.error));
+        }
+        response => response?,
+    };
+
+    let length: Option<u64> = response
+        .header("Content-Length")
+        .and_then(|len| len.parse().ok());
+    let resumed = written > 0 && response.status() == 206;
+    let (start, total) = if resumed {
+        // Like `bytes 1000-4999/5000`
+        let total = response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit('/').next()?.parse().ok())
+            .or(length.map(|len| len + written));
+        (written, total)
+    } else {
+        (0, length)
+    };
+
+    let mut hasher = Sha256::new();
+    let mut file = if resumed {
+        let mut existing = fs::File::open(part).map_err(Failure::fatal)?;
+        let mut buf = [0; 8 * 1024];
+        loop {
+            match existing.read(&mut buf).map_err(Failure::fatal)? {
+                0 => break,
+                n => hasher.update(&buf[..n]),
+            }
+        }
+        fs::OpenOptions::new().append(true).open(part)
+    } else {
+        fs::File::create(part)
+    }
+    .map_err(Failure::fatal)?;
+
+    copy(
+        url,
+        &mut response.into_reader(),
+        &mut file,
+        &mut hasher,
+        start,
+        total,
+        on_progress,
+    )?;
+    file.sync_all().map_err(Failure::fatal)?;
+    Ok(hex(hasher))
+}
+
+/// SHA-256 of `bytes`, in lowercase hex
+pub fn sha256(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex(hasher)
+}
+
+/// SHA-256 of the file at `path`, in lowercase hex
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 8 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => break,
+            n => hasher.update(&buf[..n]),
+        }
+    }
+    Ok(hex(hasher))
+}
+
+fn hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Copy `reader`, the body of `url`, to `writer` from byte `start` of it
+/// on. Failing reads are transient, as the connection is what broke.
+fn copy(
+    url: &str,
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    hasher: &mut Sha256,
+    start: u64,
+    total: Option<u64>,
+    on_progress: &mut dyn FnMut(u64, Option<u64>) -> Result<()>,
+) -> std::result::Result<(), Failure> {
+    let mut buf = [0; 8 * 1024];
+    let mut done = start;
+    let mut reported = start;
+
+    on_progress(done, total).map_err(Failure::fatal)?;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                return Err(broken(
+                    url,
+                    format!("the download of {} broke off", url),
+                    Some(err),
+                ))
+            }
+        };
+        writer.write_all(&buf[..n]).map_err(Failure::fatal)?;
+        hasher.update(&buf[..n]);
+
+        done += n as u64;
+        if done - reported >= PROGRESS_STEP {
+            on_progress(done, total).map_err(Failure::fatal)?;
+            reported = done;
+        }
+    }
+    on_progress(done, total).map_err(Failure::fatal)?;
+
+    if let Some(total) = total {
+        if done < total {
+            return Err(broken(
+                url,
+                format!("download ended after {} of {} bytes", done, total),
+                None,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Human readable size, with one decimal past the kilobyte
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,194 @@
+//! What can go wrong installing a server, each case keeping what the user
+//! needs to know to do something about it.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// `url` couldn't be fetched, `reason` telling which step failed, like
+    /// `could not reach … the TLS handshake failed`
+    Network {
+        url: String,
+        reason: String,
+        /// Status of the response, when there was one
+        status: Option<u16>,
+        /// Attempts made before giving up
+        attempts: u32,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    /// Nothing gets fetched, the network being unreachable or turned off
+    Offline { url: String },
+    /// The download of `file` isn't what got published
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    /// The checksum policy refused `file`, nothing publishing a checksum
+    /// for it
+    NoChecksum { file: String },
+    /// `name`, which the server needs to install or run, isn't there
+    MissingRuntime { name: String, hint: String },
+    /// `archive` couldn't be unpacked
+    Extraction { archive: PathBuf, reason: String },
+    /// `command` failed, `output` being the end of what it printed
+    Command {
+        command: String,
+        status: String,
+        output: String,
+    },
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// The registry or custom servers file at `path` can't be read
+    RegistryParse { path: String, reason: String },
+    /// A setting can't be used, like a proxy URL that doesn't parse
+    Config(String),
+    /// A registry or tool answered something that can't be used, like a
+    /// package without a version
+    InvalidResponse(String),
+    /// What was asked for doesn't exist, like a release for this platform
+    NotFound(String),
+    /// Updates leave the server named `name` alone, picked at `version`
+    Held { name: String, version: String },
+    /// The server can't do what was asked, like listing its versions
+    Unsupported(String),
+    /// The task got cancelled before it was done
+    Cancelled,
+    /// Another process is changing the install root, `holder` telling
+    /// which when it noted it
+    Locked {
+        root: PathBuf,
+        holder: Option<String>,
+    },
+}
+
+impl Error {
+    /// What the user can do about it, when there's something to suggest
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Error::MissingRuntime { hint, .. } => Some(hint.clone()),
+            Error::NoChecksum { .. } => Some(String::from(
+                "set checksums = \"warn\" to install it anyway",
+            )),
+            Error::ChecksumMismatch { .. } => Some(String::from(
+                "the file may have been corrupted, download it again",
+            )),
+            Error::Held { .. } => Some(String::from("install it again to get the latest version")),
+            Error::Offline { .. } => Some(String::from(
+                "only servers whose download is cached can be installed offline",
+            )),
+            Error::Locked { .. } => Some(String::from(
+                "try again once it's done, or pass --wait to wait for it",
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Network {
+                reason, attempts, ..
+            } if *attempts > 1 => {
+                write!(f, "download failed after {} attempts: {}", attempts, reason)
+            }
+            Error::Network { reason, .. } => f.write_str(reason),
+            Error::Offline { url } => write!(f, "offline, {} wasn't fetched", url),
+            Error::ChecksumMismatch {
+                file,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch for {}: expected {}, got {}",
+                file, expected, actual
+            ),
+            Error::NoChecksum { file } => {
+                write!(f, "{} has no published checksum to verify it against", file)
+            }
+            Error::MissingRuntime { name, .. } => {
+                write!(f, "{} is required for this server, but wasn't found", name)
+            }
+            Error::Extraction { archive, reason } => {
+                write!(f, "can't unpack {}: {}", archive.display(), reason)
+            }
+            Error::Command {
+                command,
+                status,
+                output,
+            } if output.is_empty() => write!(f, "{} failed ({})", command, status),
+            Error::Command {
+                command,
+                status,
+                output,
+            } => write!(f, "{} failed ({}):\n{}", command, status, output),
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Io { path: None, source } => source.fmt(f),
+            Error::Held { name, version } => write!(f, "{} is held at {}", name, version),
+            Error::RegistryParse { path, reason } => write!(f, "{}: {}", path, reason),
+            Error::Config(message)
+            | Error::InvalidResponse(message)
+            | Error::NotFound(message)
+            | Error::Unsupported(message) => f.write_str(message),
+            Error::Cancelled => f.write_str("cancelled"),
+            Error::Locked {
+                root,
+                holder: Some(holder),
+            } => write!(f, "{} is in use by {}", root.display(), holder),
+            Error::Locked { root, holder: None } => {
+                write!(f, "{} is in use by another lsp_installer", root.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            // Its message is part of this one's already
+            Error::Io { source, .. } => source.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Error {
+        Error::Io { path: None, source }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::InvalidResponse(err.to_string())
+    }
+}
+
+/// Attach the path an I/O error is about
+pub trait Context<T> {
+    fn at(self, path: &Path) -> Result<T>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn at(self, path: &Path) -> Result<T> {
+        self.map_err(|source| Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        })
+    }
+}
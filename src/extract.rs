@@ -0,0 +1,380 @@
+use crate::error::{self, Context};
+use flate2::read::GzDecoder;
+use std::{
+    fs,
+    io::{self, BufReader, Error, ErrorKind, Read},
+    path::{Component, Path, PathBuf},
+};
+
+/// Manifest at the root of a VS Code extension package
+const VSIX_MANIFEST: &str = "extension.vsixmanifest";
+
+/// Directory of the package holding the extension itself
+const VSIX_EXTENSION: &str = "extension/";
+
+/// Shape of a downloaded release asset
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    TarGz,
+    TarXz,
+    Zip,
+    /// A single gzip compressed file
+    Gz,
+    /// Anything else, taken to be the file itself
+    Plain,
+}
+
+impl Format {
+    /// Format told by the extension of the file `name`, if it has a known one
+    pub fn from_name(name: &str) -> Option<Format> {
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(Format::TarXz)
+        } else if name.ends_with(".zip") {
+            Some(Format::Zip)
+        } else if name.ends_with(".gz") {
+            Some(Format::Gz)
+        } else {
+            None
+        }
+    }
+
+    /// What a file of this format is, for messages
+    pub fn describe(self) -> &'static str {
+        match self {
+            Format::TarGz => "a .tar.gz archive",
+            Format::TarXz => "a .tar.xz archive",
+            Format::Zip => "a zip archive",
+            Format::Gz => "a gzip compressed file",
+            Format::Plain => "neither an archive nor compressed",
+        }
+    }
+}
+
+/// Tell the format of `path` from its file name, falling back to its first
+/// bytes for names without a known extension
+pub fn detect(path: &Path) -> io::Result<Format> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match Format::from_name(name) {
+        Some(format) => Ok(format),
+        None => sniff(path),
+    }
+}
+
+/// Format given away by the magic bytes at the start of `path`, whatever
+/// its name says
+pub fn sniff(path: &Path) -> io::Result<Format> {
+    let mut magic = [0; 6];
+    let mut file = fs::File::open(path)?;
+    let n = file.read(&mut magic)?;
+
+    let format = match &magic[..n] {
+        [0x1f, 0x8b, ..] => {
+            // tar puts "ustar" at offset 257 of its first header
+            let mut header = [0; 262];
+            let tar = GzDecoder::new(fs::File::open(path)?)
+                .read_exact(&mut header)
+                .is_ok_and(|()| &header[257..] == b"ustar");
+            if tar {
+                Format::TarGz
+            } else {
+                Format::Gz
+            }
+        }
+        [0xfd, b'7', b'z', b'X', b'Z', 0x00] => Format::TarXz,
+        [b'P', b'K', 0x03, 0x04, ..] => Format::Zip,
+        _ => Format::Plain,
+    };
+    Ok(format)
+}
+
+/// Unpack the archive at `archive` into `dest`. When every entry sits in
+/// one top-level directory, its content goes straight into `dest` so paths
+/// inside don't depend on how the archive was named. Of a VS Code extension,
+/// only the `extension/` directory gets unpacked, the same way. Entries
+/// trying to escape `dest` make the whole extraction fail.
+///
+/// Returns whether `archive` actually was an archive, anything else is left
+/// untouched.
+pub fn extract(archive: &Path, dest: &Path) -> error::Result<bool> {
+    let format = detect(archive).at(archive)?;
+    if matches!(format, Format::Gz | Format::Plain) {
+        return Ok(false);
+    }
+
+    let unpacked = dest.join(".unpacked");
+    if unpacked.exists() {
+        fs::remove_dir_all(&unpacked).at(&unpacked)?;
+    }
+    fs::create_dir_all(&unpacked).at(&unpacked)?;
+
+    let res = unpack(format, archive, &unpacked).and_then(|()| move_up(&unpacked, dest));
+    let _ = fs::remove_dir_all(&unpacked);
+    res.map(|()| true).map_err(|err| failed(archive, err))
+}
+
+/// Error of unpacking `archive`, telling a broken archive from a problem
+/// writing what's inside
+fn failed(archive: &Path, err: io::Error) -> error::Error {
+    match err.kind() {
+        ErrorKind::InvalidData | ErrorKind::UnexpectedEof => error::Error::Extraction {
+            archive: archive.to_path_buf(),
+            reason: err.to_string(),
+        },
+        _ => err.into(),
+    }
+}
+
+fn unpack(format: Format, archive: &Path, dest: &Path) -> io::Result<()> {
+    let file = fs::File::open(archive)?;
+    match format {
+        Format::TarGz => untar(GzDecoder::new(file), dest),
+        Format::TarXz => {
+            // lzma-rs only decompresses into a writer, so go through a file
+            // rather than holding the whole tarball in memory
+            let tar = archive.with_extension("tar");
+            let mut out = io::BufWriter::new(fs::File::create(&tar)?);
+            lzma_rs::xz_decompress(&mut BufReader::new(file), &mut out)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, format!("{:?}", err)))?;
+            drop(out);
+            untar(fs::File::open(&tar)?, dest)?;
+            fs::remove_file(tar)
+        }
+        Format::Zip => unzip(file, dest),
+        Format::Gz | Format::Plain => Ok(()),
+    }
+}
+
+fn untar(reader: impl Read, dest: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        enclosed(&entry.path()?)?;
+        // Keeps the mode of each entry, executable bits included
+        entry.unpack_in(dest)?;
+    }
+    Ok(())
+}
+
+fn unzip(file: fs::File, dest: &Path) -> io::Result<()> {
+    let invalid = |err| Error::new(ErrorKind::InvalidData, err);
+    let mut zip = zip::ZipArchive::new(file).map_err(invalid)?;
+    // A VSIX, whose manifest and metadata sit next to the extension
+    let vsix = zip.index_for_name(VSIX_MANIFEST).is_some();
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(invalid)?;
+        let name = entry.name().map_err(invalid)?.into_owned();
+        let name = match name.strip_prefix(VSIX_EXTENSION) {
+            Some(name) if vsix => name,
+            _ if vsix => continue,
+            _ => &name,
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let path = dest.join(enclosed(Path::new(name))?);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut fs::File::create(&path)?)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+    }
+    Ok(())
+}
+
+/// `path` if it stays inside the directory it gets extracted to
+fn enclosed(path: &Path) -> io::Result<&Path> {
+    let escapes = path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "archive entry {} escapes the install directory",
+                path.display()
+            ),
+        ));
+    }
+    Ok(path)
+}
+
+/// Move what got extracted into `unpacked` to `dest`, stripping a single
+/// top-level directory
+fn move_up(unpacked: &Path, dest: &Path) -> io::Result<()> {
+    let entries: Vec<PathBuf> = fs::read_dir(unpacked)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+
+    let source = match entries.as_slice() {
+        [single] if single.is_dir() => single.clone(),
+        _ => unpacked.to_path_buf(),
+    };
+    for entry in fs::read_dir(&source)? {
+        let entry = entry?;
+        fs::rename(entry.path(), dest.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Decompress a `.gz` file holding a single file into `dest`
+pub fn gunzip(archive: &Path, dest: &Path) -> error::Result<()> {
+    let mut gz = GzDecoder::new(fs::File::open(archive).at(archive)?);
+    let mut file = fs::File::create(dest).at(dest)?;
+    io::copy(&mut gz, &mut file).map_err(|err| failed(archive, err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    /// Tarball of `files`, paths written as they are, even escaping ones
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zip_of(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, content) in files {
+            writer
+                .start_file(*path, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Extract `data` saved as `name`, into a directory of its own
+    fn extracted(name: &str, data: &[u8]) -> (tempfile::TempDir, error::Result<bool>) {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join(name);
+        fs::write(&archive, data).unwrap();
+        let dest = dir.path().join("dest");
+        fs::create_dir(&dest).unwrap();
+        let result = extract(&archive, &dest);
+        (dir, result)
+    }
+
+    fn read(dir: &tempfile::TempDir, path: &str) -> String {
+        fs::read_to_string(dir.path().join("dest").join(path)).unwrap()
+    }
+
+    #[test]
+    fn tar_gz_loses_its_top_directory() {
+        let data = gzip(&tarball(&[
+            ("pkg/bin/tool", "#!/bin/sh\n"),
+            ("pkg/README", "hi"),
+        ]));
+        let (dir, result) = extracted("pkg.tar.gz", &data);
+        assert!(result.unwrap());
+        assert_eq!(read(&dir, "bin/tool"), "#!/bin/sh\n");
+        assert_eq!(read(&dir, "README"), "hi");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let tool = dir.path().join("dest/bin/tool");
+            assert_eq!(
+                fs::metadata(tool).unwrap().permissions().mode() & 0o111,
+                0o111
+            );
+        }
+    }
+
+    #[test]
+    fn tar_xz_keeps_several_top_entries() {
+        let mut data = Vec::new();
+        let tar = tarball(&[("a.txt", "a"), ("b/c.txt", "c")]);
+        lzma_rs::xz_compress(&mut Cursor::new(tar), &mut data).unwrap();
+        let (dir, result) = extracted("release.tar.xz", &data);
+        assert!(result.unwrap());
+        assert_eq!(read(&dir, "a.txt"), "a");
+        assert_eq!(read(&dir, "b/c.txt"), "c");
+        assert!(!dir.path().join("release.tar").exists());
+    }
+
+    #[test]
+    fn zip_and_vsix() {
+        let (dir, result) = extracted("pkg.zip", &zip_of(&[("pkg/tool", "zipped")]));
+        assert!(result.unwrap());
+        assert_eq!(read(&dir, "tool"), "zipped");
+
+        let vsix = zip_of(&[
+            (VSIX_MANIFEST, "<PackageManifest/>"),
+            ("[Content_Types].xml", "<Types/>"),
+            ("extension/server.js", "main()"),
+        ]);
+        // Told apart by its first bytes, the name saying nothing
+        let (dir, result) = extracted("download", &vsix);
+        assert!(result.unwrap());
+        assert_eq!(read(&dir, "server.js"), "main()");
+        assert!(!dir.path().join("dest").join(VSIX_MANIFEST).exists());
+    }
+
+    #[test]
+    fn single_gzip_file() {
+        let (dir, result) = extracted("tool.gz", &gzip(b"binary"));
+        assert!(!result.unwrap());
+
+        let archive = dir.path().join("tool.gz");
+        let tool = dir.path().join("tool");
+        gunzip(&archive, &tool).unwrap();
+        assert_eq!(fs::read_to_string(tool).unwrap(), "binary");
+        // A tarball is more than gzip compressed
+        fs::write(&archive, gzip(&tarball(&[("a", "a")]))).unwrap();
+        assert!(sniff(&archive).unwrap() == Format::TarGz);
+    }
+
+    #[test]
+    fn entries_escaping_dest_are_refused() {
+        let tar = gzip(&tarball(&[("ok", "fine"), ("../evil", "escaped")]));
+        let zip = zip_of(&[("ok", "fine"), ("../evil", "escaped")]);
+        for (name, data) in [("pkg.tar.gz", tar), ("pkg.zip", zip)] {
+            let (dir, result) = extracted(name, &data);
+            assert!(
+                matches!(result, Err(error::Error::Extraction { .. })),
+                "{} got extracted",
+                name
+            );
+            assert!(!dir.path().join("evil").exists(), "{}", name);
+            assert!(!dir.path().join("dest").join("ok").exists(), "{}", name);
+        }
+
+        assert!(enclosed(Path::new("./bin/tool")).is_ok());
+        for path in ["../evil", "bin/../../evil", "/etc/passwd"] {
+            assert!(enclosed(Path::new(path)).is_err(), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Install, update and remove language servers, as the `lsp_installer`
+//! binary does, from another program. Installs go to the same root and
+//! manifest as the binary's, so both see the same servers.
+//!
+//! ```no_run
+//! use lsp_installer::{Event, Options};
+//!
+//! lsp_installer::configure();
+//! let version = lsp_installer::install("rust-analyzer", Options::default(), |event| {
+//!     if let Event::Log(line) = event {
+//!         println!("{}", line);
+//!     }
+//! })?;
+//! println!("installed rust-analyzer {}", version);
+//! # Ok::<(), lsp_installer::Failure>(())
+//! ```
+//!
+//! Building without the default `cli` feature leaves out the TUI and its
+//! dependencies.
+
+pub mod error;
+pub mod manifest;
+pub mod servers;
+pub mod worker;
+
+// Used by the binary, not part of the API
+#[doc(hidden)]
+pub mod cache;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod disk;
+#[doc(hidden)]
+pub mod doctor;
+#[doc(hidden)]
+pub mod download;
+#[doc(hidden)]
+pub mod extract;
+#[doc(hidden)]
+pub mod lock;
+#[doc(hidden)]
+pub mod logfile;
+#[doc(hidden)]
+pub mod metadata;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+pub mod platform;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod queue;
+#[doc(hidden)]
+pub mod updates;
+#[doc(hidden)]
+pub mod version;
+
+pub use download::ChecksumPolicy;
+pub use error::Error;
+pub use servers::{registry, Plan, Server, Status};
+pub use worker::Failure;
+
+use config::{Config, Settings};
+use std::sync::{atomic::AtomicBool, mpsc, Arc};
+use worker::{Report, Task};
+
+/// How to go about an install
+#[derive(Clone, Default)]
+pub struct Options {
+    /// Version to install and hold the server at, instead of the latest one
+    pub version: Option<String>,
+    /// What to do with downloads nothing publishes a checksum for. Like
+    /// the network settings, it holds for the whole process.
+    pub checksums: ChecksumPolicy,
+    /// Only use cached metadata and downloads, for the rest of the process
+    pub offline: bool,
+}
+
+/// Something happening while a task runs
+pub enum Event {
+    /// Bytes downloaded so far, out of `total` when it's known
+    Progress {
+        done: u64,
+        total: Option<u64>,
+    },
+    Log(String),
+    /// Something to let the user know about, the task going on
+    Warning(String),
+}
+
+/// Read the user's `config.toml` like the binary does, for the install
+/// root, proxy, certificates and retries it sets. Returns the problems
+/// found in it, defaults being used in their place.
+pub fn configure() -> Vec<String> {
+    let (config, mut warnings) = Config::load();
+    if let Some(root) = &config.install_root {
+        paths::configure_root(paths::expand(root));
+    }
+    warnings.extend(manifest::check());
+    warnings.extend(servers::recover());
+    Settings::resolve(&config, None, None, None).apply();
+    warnings
+}
+
+/// Install the server named `name`, passing what happens to `on_event`,
+/// and return the version installed. Blocks until done, the work running
+/// on a thread of its own.
+pub fn install(
+    name: &str,
+    options: Options,
+    on_event: impl FnMut(Event),
+) -> Result<String, Failure> {
+    download::set_checksum_policy(options.checksums);
+    if options.offline {
+        download::set_offline();
+    }
+    let server = find(name)?;
+    if !server.is_available() {
+        return Err(failure(format!("{} is unavailable on this platform", name)));
+    }
+    let task = match options.version {
+        Some(version) => Task::Pin(version),
+        None => Task::Install,
+    };
+    run(&server, task, on_event)?;
+    Ok(server.installed_version().unwrap_or_default())
+}
+
+/// Update the server named `name` to its latest version, returning it, or
+/// `None` when it was up to date
+pub fn update(name: &str, on_event: impl FnMut(Event)) -> Result<Option<String>, Failure> {
+    let server = find(name)?;
+    if !server.is_installed() {
+        return Err(failure(format!("{} is not installed", name)));
+    }
+    let before = server.installed_version();
+    run(&server, Task::Update, on_event)?;
+    let after = server.installed_version();
+    Ok(after.filter(|after| before.as_ref() != Some(after)))
+}
+
+/// Remove the server named `name` and its record in the manifest
+pub fn uninstall(name: &str) -> Result<(), Failure> {
+    let server = find(name)?;
+    if server.status() == Status::NotInstalled {
+        return Err(failure(format!("{} is not installed", name)));
+    }
+    run(&server, Task::Uninstall, |_| {})
+}
+
+fn failure(message: String) -> Failure {
+    Failure {
+        message,
+        causes: Vec::new(),
+        hint: None,
+    }
+}
+
+fn find(name: &str) -> Result<Arc<dyn Server>, Failure> {
+    let (servers, _) = registry();
+    servers
+        .into_iter()
+        .find(|server| server.name() == name)
+        .ok_or_else(|| failure(format!("{}: unknown server", name)))
+}
+
+/// Run `task` on `server` in a worker, handing its reports to `on_event`
+/// on this thread until it's done
+fn run(
+    server: &Arc<dyn Server>,
+    task: Task,
+    mut on_event: impl FnMut(Event),
+) -> Result<(), Failure> {
+    let operation = format!("{} {}", task.describe().to_lowercase(), server.name());
+    let _lock = lock::acquire(&operation).map_err(|err| Failure::new(err.to_string(), &err))?;
+    let (tx, rx) = mpsc::channel();
+    worker::spawn(
+        0,
+        server.clone(),
+        task,
+        tx,
+        Arc::new(AtomicBool::new(false)),
+    );
+    for (_, report) in rx {
+        match report {
+            Report::Started => {}
+            Report::Progress { done, total } => on_event(Event::Progress { done, total }),
+            Report::Log(text) => on_event(Event::Log(text)),
+            Report::Warning(text) => on_event(Event::Warning(text)),
+            Report::Completed(_) => return Ok(()),
+            Report::Failed(failure) => return Err(failure),
+            Report::Cancelled(text) => return Err(failure(text)),
+        }
+    }
+    Err(failure(format!(
+        "{} stopped without reporting",
+        server.name()
+    )))
+}
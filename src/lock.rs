@@ -0,0 +1,151 @@
+//! Advisory lock on the install root, held by whatever changes it so two
+//! processes don't race on the manifest and the install directories. The
+//! system lets go of it when the holder exits, however it exits, so a
+//! crashed process leaves nothing to wait on: the note it left in the lock
+//! file only tells that its lock went stale.
+
+use crate::{
+    error::{Context, Error, Result},
+    paths,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::PathBuf,
+    process,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+/// The process holding the lock, as noted in the lock file
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Holder {
+    pub pid: u32,
+    /// When it took the lock
+    pub started: String,
+    /// What it's doing, like `install`
+    pub operation: String,
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "process {} ({}, since {})",
+            self.pid, self.operation, self.started
+        )
+    }
+}
+
+/// Lock file of this process, shared by the threads taking the lock
+struct Held {
+    file: File,
+    guards: usize,
+}
+
+static HELD: Mutex<Option<Held>> = Mutex::new(None);
+
+/// Share of the lock, which is released once the last one of the process
+/// is dropped
+pub struct Lock {
+    /// What the process that last held the lock was doing, when it exited
+    /// without releasing it
+    pub broken: Option<Holder>,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let mut held = HELD.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(state) = held.as_mut() {
+            state.guards -= 1;
+            if state.guards == 0 {
+                // Emptied rather than removed: a process waiting on it would
+                // lock a file nobody else opens anymore
+                let _ = state.file.set_len(0);
+                *held = None;
+            }
+        }
+    }
+}
+
+fn path() -> PathBuf {
+    paths::root().join("lock")
+}
+
+/// Who holds the lock, unless it didn't note it yet
+pub fn holder() -> Option<Holder> {
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+}
+
+fn open() -> Result<File> {
+    fs::create_dir_all(paths::root()).at(paths::root())?;
+    // Not truncated, which would wipe the note of the holder
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path())
+        .at(&path())
+}
+
+/// Take the lock for `operation`, failing with who holds it when another
+/// process does
+pub fn acquire(operation: &str) -> Result<Lock> {
+    let mut held = HELD.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(state) = held.as_mut() {
+        state.guards += 1;
+        return Ok(Lock { broken: None });
+    }
+    let file = open()?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            return Err(Error::Locked {
+                root: paths::root().to_path_buf(),
+                holder: holder().map(|holder| holder.to_string()),
+            })
+        }
+        Err(fs::TryLockError::Error(err)) => return Err(err).at(&path()),
+    }
+    take(&mut held, file, operation)
+}
+
+/// Take the lock for `operation`, waiting for the process holding it to
+/// be done
+pub fn wait(operation: &str) -> Result<Lock> {
+    if let Some(state) = HELD.lock().unwrap_or_else(|err| err.into_inner()).as_mut() {
+        state.guards += 1;
+        return Ok(Lock { broken: None });
+    }
+    // Waited on with `HELD` unlocked, for a thread of this process holding
+    // the lock to be able to let go of it
+    let file = open()?;
+    file.lock().at(&path())?;
+    let mut held = HELD.lock().unwrap_or_else(|err| err.into_inner());
+    take(&mut held, file, operation)
+}
+
+/// Note this process in the lock file it just locked, reading what a
+/// process that crashed holding it left there
+fn take(held: &mut Option<Held>, mut file: File, operation: &str) -> Result<Lock> {
+    let mut previous = String::new();
+    let _ = file.read_to_string(&mut previous);
+    let broken = serde_json::from_str(&previous).ok();
+
+    let holder = Holder {
+        pid: process::id(),
+        started: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        operation: operation.to_string(),
+    };
+    file.set_len(0).at(&path())?;
+    file.rewind().at(&path())?;
+    file.write_all(serde_json::to_string(&holder)?.as_bytes())
+        .at(&path())?;
+    *held = Some(Held { file, guards: 1 });
+    Ok(Lock { broken })
+}
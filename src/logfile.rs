@@ -0,0 +1,75 @@
+//! Transcript of every job, kept on disk for bug reports.
+//!
+//! Writing is best effort: when the file can't be written the lines are
+//! dropped and jobs go on as usual.
+
+use crate::paths;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+/// Size past which the file gets moved aside to `lsp_installer.log.1`
+const LIMIT: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Info => "INFO",
+            Level::Warning => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+pub fn path() -> PathBuf {
+    paths::state_dir().join("lsp_installer.log")
+}
+
+/// Append `text` logged by `server`, one timestamped line per line of text
+pub fn write(level: Level, server: &str, text: &str) {
+    static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+    let Ok(mut file) = FILE.lock() else {
+        return;
+    };
+    let full = file
+        .as_ref()
+        .and_then(|file| file.metadata().ok())
+        .is_some_and(|metadata| metadata.len() > LIMIT);
+    if full {
+        *file = None;
+        let _ = fs::rename(path(), path().with_extension("log.1"));
+    }
+    if file.is_none() {
+        *file = open();
+    }
+    let Some(file) = file.as_mut() else {
+        return;
+    };
+
+    let time = humantime::format_rfc3339_seconds(SystemTime::now());
+    for line in text.lines() {
+        let _ = writeln!(file, "{} {:<5} {}: {}", time, level.name(), server, line);
+    }
+}
+
+fn open() -> Option<File> {
+    let path = path();
+    fs::create_dir_all(path.parent()?).ok()?;
+    // A file left too big by a previous run starts over
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > LIMIT) {
+        let _ = fs::rename(&path, path.with_extension("log.1"));
+    }
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
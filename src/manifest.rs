@@ -0,0 +1,267 @@
+//! `manifest.json` in the install root, recording what got installed for
+//! each server. Installs made before it existed, or adopted from elsewhere,
+//! have no record and get probed on disk instead. A project installed into
+//! has a manifest of its own.
+
+use crate::{
+    error::{Context, Result},
+    paths,
+    servers::external,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+/// One install, as recorded once it passed verification
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Record {
+    pub version: String,
+    pub backend: String,
+    /// RFC 3339 time of the install
+    pub installed_at: String,
+    /// Archive or binary the install got downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// SHA-256 of the download
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub bin: PathBuf,
+    /// First line printed by the executable when verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_version: Option<String>,
+    /// Whether the version got picked by the user, keeping updates away
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub held: bool,
+    /// RFC 3339 time of the last task run on the server since the install
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub used_at: Option<String>,
+    /// Bytes taken by the install directory, measured once installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_size: Option<u64>,
+    /// Whether the server got built from its sources rather than downloaded
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub built_from_source: bool,
+    /// Whether the server got installed from a file the user had, `url`
+    /// being its path
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_file: bool,
+}
+
+impl Record {
+    /// When the server was last installed or had a task run on it
+    pub fn last_used(&self) -> &str {
+        self.used_at.as_deref().unwrap_or(&self.installed_at)
+    }
+}
+
+/// Servers installed on a machine, as `export-state` writes them for
+/// another one to install: the part of the manifest that carries over,
+/// which makes a manifest readable as one too
+#[derive(Default, Deserialize, Serialize)]
+pub struct State {
+    pub servers: BTreeMap<String, Installed>,
+}
+
+/// A server in a `State`, with the fields of its `Record`
+#[derive(Deserialize, Serialize)]
+pub struct Installed {
+    pub version: String,
+    pub backend: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub held: bool,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Manifest {
+    servers: BTreeMap<String, Record>,
+    /// Install each server can roll back to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    previous: BTreeMap<String, Record>,
+}
+
+/// Held while the manifest gets rewritten, as installs run in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub fn path() -> PathBuf {
+    paths::root().join("manifest.json")
+}
+
+/// Move a manifest that can't be read aside, so the next install starts a
+/// new one, returning a warning saying where it went
+pub fn check() -> Option<String> {
+    let _lock = LOCK.lock();
+    let text = fs::read_to_string(path()).ok()?;
+    let err = serde_json::from_str::<Manifest>(&text).err()?;
+    let time = humantime::format_rfc3339_seconds(SystemTime::now())
+        .to_string()
+        .replace(':', "-");
+    let aside = path().with_extension(format!("json.corrupt-{}", time));
+    match fs::rename(path(), &aside) {
+        Ok(()) => Some(format!(
+            "{} is corrupted ({}), it was moved to {}",
+            path().display(),
+            err,
+            aside.display()
+        )),
+        Err(rename) => Some(format!(
+            "{} is corrupted ({}) and couldn't be moved aside: {}",
+            path().display(),
+            err,
+            rename
+        )),
+    }
+}
+
+/// Record of the server named `name`, if it has one
+pub fn get(name: &str) -> Option<Record> {
+    read().servers.remove(name)
+}
+
+/// Records of every installed server, by name
+pub fn all() -> BTreeMap<String, Record> {
+    read().servers
+}
+
+/// The installed servers, leaving out the adopted ones as they weren't
+/// installed by lsp_installer
+pub fn state() -> State {
+    let servers = all()
+        .into_iter()
+        .filter(|(_, record)| record.backend != external::BACKEND)
+        .map(|(name, record)| {
+            let installed = Installed {
+                version: record.version,
+                backend: record.backend,
+                held: record.held,
+            };
+            (name, installed)
+        })
+        .collect();
+    State { servers }
+}
+
+/// Records of the servers installed globally, while installing into a
+/// project, else none as they're the ones `all()` gives
+pub fn global() -> BTreeMap<String, Record> {
+    if paths::project().is_none() {
+        return BTreeMap::new();
+    }
+    read_at(&paths::global_root().join("manifest.json")).servers
+}
+
+/// Record `record` for the server named `name`, replacing its previous one
+pub fn insert(name: &str, record: Record) -> Result<()> {
+    update(|manifest| {
+        manifest.servers.insert(name.to_string(), record);
+    })
+}
+
+/// Record `record` for the server named `name` after an update, `previous`
+/// being the install it replaced
+pub fn replace(name: &str, record: Record, previous: Option<Record>) -> Result<()> {
+    update(|manifest| {
+        manifest.servers.insert(name.to_string(), record);
+        match previous {
+            Some(previous) => manifest.previous.insert(name.to_string(), previous),
+            None => manifest.previous.remove(name),
+        };
+    })
+}
+
+/// Record of the install the server named `name` can roll back to
+pub fn previous(name: &str) -> Option<Record> {
+    read().previous.remove(name)
+}
+
+/// Swap the records of the current and previous install of the server
+/// named `name`
+pub fn roll_back(name: &str) -> Result<()> {
+    update(|manifest| {
+        let current = manifest.servers.remove(name);
+        if let Some(previous) = manifest.previous.remove(name) {
+            manifest.servers.insert(name.to_string(), previous);
+        }
+        if let Some(current) = current {
+            manifest.previous.insert(name.to_string(), current);
+        }
+    })
+}
+
+/// Hold the server named `name` at its recorded version
+pub fn hold(name: &str) -> Result<()> {
+    update(|manifest| {
+        if let Some(record) = manifest.servers.get_mut(name) {
+            record.held = true;
+        }
+    })
+}
+
+/// Note that a task just ran on the server named `name`
+pub fn touch(name: &str) -> Result<()> {
+    update(|manifest| {
+        if let Some(record) = manifest.servers.get_mut(name) {
+            record.used_at = Some(now());
+        }
+    })
+}
+
+/// Keep the bytes the install of the server named `name` takes
+pub fn set_disk_size(name: &str, bytes: u64) -> Result<()> {
+    update(|manifest| {
+        if let Some(record) = manifest.servers.get_mut(name) {
+            record.disk_size = Some(bytes);
+        }
+    })
+}
+
+/// Drop the record of the server named `name`, returning it. The record of
+/// its previous install is kept.
+pub fn remove(name: &str) -> Result<Option<Record>> {
+    let mut removed = None;
+    update(|manifest| removed = manifest.servers.remove(name))?;
+    Ok(removed)
+}
+
+/// Drop every record of the server named `name`, once it got uninstalled
+pub fn forget(name: &str) -> Result<()> {
+    update(|manifest| {
+        manifest.servers.remove(name);
+        manifest.previous.remove(name);
+    })
+}
+
+/// Current time, the way `Record::installed_at` holds it
+pub fn now() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+}
+
+/// The manifest on disk, empty when there's none or it can't be read
+fn read() -> Manifest {
+    read_at(&path())
+}
+
+fn read_at(path: &Path) -> Manifest {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Apply `change` to the manifest, replacing the file in a single rename so
+/// it's never seen half-written
+fn update(change: impl FnOnce(&mut Manifest)) -> Result<()> {
+    let _lock = LOCK.lock();
+    let mut manifest = read();
+    change(&mut manifest);
+
+    let text = serde_json::to_string_pretty(&manifest)?;
+    let temp = path().with_extension("json.tmp");
+    fs::create_dir_all(paths::root()).at(paths::root())?;
+    fs::write(&temp, text + "\n").at(&temp)?;
+    fs::rename(&temp, path()).at(&path())
+}
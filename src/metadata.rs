@@ -0,0 +1,138 @@
+//! What the registries answered last, kept in `metadata.json` of the cache
+//! directory: the latest version of each server and the plans of its
+//! installs. Update checks use it to skip asking again within a day, and
+//! with the network unreachable it stands in for the registries.
+
+use crate::{paths, servers::Plan};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Deserialize, Serialize)]
+struct Entry<T> {
+    value: T,
+    /// Seconds since the Unix epoch
+    fetched: u64,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Store {
+    #[serde(default)]
+    latest: BTreeMap<String, Entry<String>>,
+    /// By server name, followed by `@version` for the plans of a given
+    /// version
+    #[serde(default)]
+    plans: BTreeMap<String, Entry<Plan>>,
+}
+
+/// Held while the file gets rewritten, as installs and the update check
+/// write it at the same time
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn path() -> PathBuf {
+    paths::cache_dir().join("metadata.json")
+}
+
+fn read() -> Store {
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Apply `change` to the store, dropping it when it can't be written as
+/// the next fetch fills it again
+fn update(change: impl FnOnce(&mut Store)) {
+    let _lock = LOCK.lock();
+    let mut store = read();
+    change(&mut store);
+    if let Ok(text) = serde_json::to_string_pretty(&store) {
+        let temp = path().with_extension("json.tmp");
+        let _ = fs::create_dir_all(paths::cache_dir())
+            .and_then(|()| fs::write(&temp, text + "\n"))
+            .and_then(|()| fs::rename(temp, path()));
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn key(name: &str, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("{}@{}", name, version),
+        None => name.to_string(),
+    }
+}
+
+/// Last known latest version of the server named `name`, with how long ago
+/// it got fetched
+pub fn latest(name: &str) -> Option<(String, Duration)> {
+    read().latest.remove(name).map(|entry| {
+        let age = Duration::from_secs(now().saturating_sub(entry.fetched));
+        (entry.value, age)
+    })
+}
+
+pub fn save_latest(name: &str, version: &str) {
+    update(|store| {
+        store.latest.insert(
+            name.to_string(),
+            Entry {
+                value: version.to_string(),
+                fetched: now(),
+            },
+        );
+    })
+}
+
+/// Plan last resolved for installing `version` of the server named `name`,
+/// or its latest version
+pub fn plan(name: &str, version: Option<&str>) -> Option<Plan> {
+    read()
+        .plans
+        .remove(&key(name, version))
+        .map(|entry| entry.value)
+}
+
+pub fn save_plan(name: &str, version: Option<&str>, plan: &Plan) {
+    update(|store| {
+        store.plans.insert(
+            key(name, version),
+            Entry {
+                value: plan.clone(),
+                fetched: now(),
+            },
+        );
+    })
+}
+
+/// Size of the download of the latest version of each server whose plan
+/// told it, by name
+pub fn download_sizes() -> BTreeMap<String, u64> {
+    read()
+        .plans
+        .into_iter()
+        .filter_map(|(key, entry)| Some((key, entry.value.size?)))
+        .filter(|(key, _)| !key.contains('@'))
+        .collect()
+}
+
+/// Time since anything got last fetched, `None` when nothing was
+pub fn age() -> Option<Duration> {
+    let store = read();
+    let fetched = store
+        .latest
+        .values()
+        .map(|entry| entry.fetched)
+        .chain(store.plans.values().map(|entry| entry.fetched))
+        .max()?;
+    Some(Duration::from_secs(now().saturating_sub(fetched)))
+}
@@ -0,0 +1,203 @@
+//! Where lsp_installer keeps its files, resolved once per run.
+
+use crate::config::Origin;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+/// Variable overriding the install root
+pub const HOME_VAR: &str = "LSP_INSTALLER_HOME";
+
+/// File at the root of a project declaring the servers it uses
+pub const PROJECT_FILE: &str = "lsp_installer.toml";
+
+/// Directory of a project its own servers get installed into
+pub const PROJECT_DIR: &str = ".lsp_installer";
+
+const APP: &str = "lsp_installer";
+
+/// Directory holding the user's configuration
+pub fn config_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let base = if cfg!(windows) {
+            env_dir("APPDATA").unwrap_or_else(|| home().join("AppData").join("Roaming"))
+        } else if cfg!(target_os = "macos") {
+            env_dir("XDG_CONFIG_HOME")
+                .unwrap_or_else(|| home().join("Library").join("Application Support"))
+        } else {
+            env_dir("XDG_CONFIG_HOME").unwrap_or_else(|| home().join(".config"))
+        };
+        base.join(APP)
+    })
+}
+
+/// Install root set in the config file
+static CONFIGURED_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Install into `root` unless `LSP_INSTALLER_HOME` is set, only effective
+/// before `root()` is first called
+pub fn configure_root(root: PathBuf) {
+    let _ = CONFIGURED_ROOT.set(root);
+}
+
+/// Empty install root shared by the tests, which keep the log file there
+/// too
+#[cfg(test)]
+pub(crate) fn test_root() -> &'static Path {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        let dir = tempfile::tempdir().unwrap().keep();
+        env::set_var("XDG_STATE_HOME", dir.join("state"));
+        configure_root(dir.join("root"));
+        dir
+    });
+    root()
+}
+
+/// Whether servers go into the project rather than the global root
+static IN_PROJECT: AtomicBool = AtomicBool::new(false);
+
+/// Install into the project from now on, or back into the global root.
+/// Nothing should be running while it changes.
+pub fn set_project(on: bool) {
+    IN_PROJECT.store(on, Ordering::Relaxed);
+}
+
+/// Root of the project when installing into it
+pub fn project() -> Option<&'static Path> {
+    IN_PROJECT.load(Ordering::Relaxed).then(project_root)
+}
+
+/// Root of the project in the current directory: the closest directory
+/// up from it with a `lsp_installer.toml`, else the current directory
+pub fn project_root() -> &'static Path {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        let current = env::current_dir().unwrap_or_default();
+        current
+            .ancestors()
+            .find(|dir| dir.join(PROJECT_FILE).is_file())
+            .unwrap_or(&current)
+            .to_path_buf()
+    })
+}
+
+/// `lsp_installer.toml` of the project in the current directory, if it
+/// has one
+pub fn project_file() -> Option<PathBuf> {
+    Some(project_root().join(PROJECT_FILE)).filter(|file| file.is_file())
+}
+
+/// Directory every server gets installed into: `.lsp_installer` of the
+/// project when installing into one, else `global_root()`
+pub fn root() -> &'static Path {
+    static PROJECT: OnceLock<PathBuf> = OnceLock::new();
+    match project() {
+        Some(project) => PROJECT.get_or_init(|| project.join(PROJECT_DIR)),
+        None => global_root(),
+    }
+}
+
+/// Directory servers get installed into outside projects:
+/// `LSP_INSTALLER_HOME` when set, else `install_root` from the config file,
+/// else the platform's data directory
+pub fn global_root() -> &'static Path {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        env_dir(HOME_VAR)
+            .or_else(|| CONFIGURED_ROOT.get().cloned())
+            .unwrap_or_else(|| data_dir().join(APP))
+    })
+}
+
+/// Which of the sources listed for `root()` and `global_root()` it comes
+/// from
+pub fn root_origin() -> Origin {
+    if project().is_some() {
+        Origin::Project
+    } else if env_dir(HOME_VAR).is_some() {
+        Origin::Env(HOME_VAR)
+    } else if CONFIGURED_ROOT.get().is_some() {
+        Origin::File
+    } else {
+        Origin::Default
+    }
+}
+
+/// Directory collecting links to the executables of installed servers
+pub fn bin_dir() -> PathBuf {
+    root().join("bin")
+}
+
+/// Directory for files worth keeping between installs, like package caches,
+/// shared by the projects
+pub fn cache_dir() -> PathBuf {
+    global_root().join("cache")
+}
+
+/// Directory for editor configuration generated by the export action
+pub fn export_dir() -> PathBuf {
+    root().join("export")
+}
+
+/// Directory for files describing past runs, like the log file
+pub fn state_dir() -> PathBuf {
+    if cfg!(any(windows, target_os = "macos")) {
+        data_dir().join(APP)
+    } else {
+        env_dir("XDG_STATE_HOME")
+            .unwrap_or_else(|| home().join(".local").join("state"))
+            .join(APP)
+    }
+}
+
+/// Helix's `languages.toml`
+pub fn helix_languages() -> PathBuf {
+    let base = if cfg!(windows) {
+        env_dir("APPDATA").unwrap_or_else(|| home().join("AppData").join("Roaming"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").unwrap_or_else(|| home().join(".config"))
+    };
+    base.join("helix").join("languages.toml")
+}
+
+/// Base directory for application data on this platform
+fn data_dir() -> PathBuf {
+    if cfg!(windows) {
+        env_dir("LOCALAPPDATA").unwrap_or_else(|| home().join("AppData").join("Local"))
+    } else if cfg!(target_os = "macos") {
+        env_dir("XDG_DATA_HOME")
+            .unwrap_or_else(|| home().join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME").unwrap_or_else(|| home().join(".local").join("share"))
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory
+pub fn expand(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home().join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+fn home() -> PathBuf {
+    env_dir("HOME")
+        .or_else(|| env_dir("USERPROFILE"))
+        .unwrap_or_default()
+}
+
+/// Value of the variable `name`, when it's set to something
+fn env_dir(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
@@ -0,0 +1,81 @@
+//! Platform the servers get installed for.
+
+use std::{env, fs, process::Command, sync::OnceLock};
+
+/// Variable overriding the detected platform, as `os-arch` or
+/// `os-arch-libc`, for platforms where another one's assets work
+pub const OVERRIDE_VAR: &str = "LSP_INSTALLER_PLATFORM";
+
+pub struct Platform {
+    /// Like `std::env::consts::OS`
+    pub os: String,
+    /// Like `std::env::consts::ARCH`
+    pub arch: String,
+    /// C library on Linux, `gnu` or `musl`
+    pub libc: Option<String>,
+}
+
+impl Platform {
+    /// Platform named by `LSP_INSTALLER_PLATFORM`, or the one running
+    pub fn current() -> &'static Platform {
+        static CURRENT: OnceLock<Platform> = OnceLock::new();
+        CURRENT.get_or_init(|| {
+            env::var(OVERRIDE_VAR)
+                .ok()
+                .and_then(|value| Platform::parse(&value))
+                .unwrap_or_else(Platform::detect)
+        })
+    }
+
+    /// OS and architecture, like `linux-aarch64`
+    pub fn short(&self) -> String {
+        format!("{}-{}", self.os, self.arch)
+    }
+
+    fn parse(value: &str) -> Option<Platform> {
+        let mut parts = value.trim().splitn(3, '-');
+        let os = parts.next().filter(|os| !os.is_empty())?;
+        let arch = parts.next().filter(|arch| !arch.is_empty())?;
+        Some(Platform {
+            os: os.to_string(),
+            arch: arch.to_string(),
+            libc: parts.next().map(str::to_string),
+        })
+    }
+
+    fn detect() -> Platform {
+        Platform {
+            os: env::consts::OS.to_string(),
+            arch: env::consts::ARCH.to_string(),
+            libc: (env::consts::OS == "linux").then(|| String::from(linux_libc())),
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}", self.arch, self.os)?;
+        if let Some(libc) = &self.libc {
+            write!(f, "-{}", libc)?;
+        }
+        Ok(())
+    }
+}
+
+/// C library of the system, which isn't necessarily the one this binary
+/// was linked against
+fn linux_libc() -> &'static str {
+    let musl_loader = fs::read_dir("/lib").into_iter().flatten().any(|entry| {
+        entry.is_ok_and(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+    });
+    if musl_loader {
+        return "musl";
+    }
+
+    // musl's ldd prints its banner to stderr and exits with 1
+    let ldd = Command::new("ldd").arg("--version").output();
+    match ldd {
+        Ok(output) if String::from_utf8_lossy(&output.stderr).contains("musl") => "musl",
+        _ => "gnu",
+    }
+}
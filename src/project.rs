@@ -0,0 +1,109 @@
+//! Files of the project in the current directory, telling which languages
+//! it's written in so the servers for them can be suggested. The walk is
+//! shallow and bounded, to stay instant at the root of a monorepo. Also
+//! reads the servers a project declares in its `lsp_installer.toml`.
+
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Version declaring the server without holding it at a version
+pub const LATEST: &str = "latest";
+
+/// Content of `lsp_installer.toml`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Declared {
+    /// Version of each server by name, or `LATEST`
+    #[serde(default)]
+    servers: BTreeMap<String, String>,
+}
+
+/// Servers the `lsp_installer.toml` at `path` declares, with the version
+/// each one is held at, `None` for the latest
+pub fn declared(path: &Path) -> Result<BTreeMap<String, Option<String>>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let declared: Declared =
+        toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err.message()))?;
+    Ok(wanted(declared.servers))
+}
+
+/// `servers` as declared in a file, with `None` for the ones at `LATEST`
+pub fn wanted(servers: BTreeMap<String, String>) -> BTreeMap<String, Option<String>> {
+    servers
+        .into_iter()
+        .map(|(name, version)| {
+            let version = Some(version).filter(|version| version != LATEST);
+            (name, version)
+        })
+        .collect()
+}
+
+/// Levels of directories looked into below the root
+const DEPTH: usize = 3;
+
+/// Directory entries read before the walk stops
+const MAX_ENTRIES: usize = 5000;
+
+/// Directories holding dependencies or build output rather than the
+/// project's own files, never looked into, like hidden ones
+const SKIPPED: &[&str] = &["node_modules", "target", "vendor", "venv", "__pycache__"];
+
+/// Names of the files in `root` and in its directories down to `DEPTH`
+/// levels
+pub fn file_names(root: &Path) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut dirs: VecDeque<(PathBuf, usize)> = VecDeque::from([(root.to_path_buf(), 0)]);
+    let mut read = 0;
+    while let Some((dir, depth)) = dirs.pop_front() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            read += 1;
+            if read > MAX_ENTRIES {
+                return names;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Not following links, which can lead out of the project
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => {
+                    let skipped = name.starts_with('.') || SKIPPED.contains(&name.as_str());
+                    if !skipped && depth < DEPTH {
+                        dirs.push_back((entry.path(), depth + 1));
+                    }
+                }
+                Ok(_) => {
+                    names.insert(name);
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    names
+}
+
+/// Whether the file name `name` matches `pattern`, where `*` stands for
+/// any part of a name, like `*.tf`
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
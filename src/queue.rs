@@ -0,0 +1,155 @@
+use crate::worker::Task;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// Jobs running together unless `--jobs` says otherwise
+pub const DEFAULT_JOBS: usize = 3;
+
+/// Where a job stands in the queue
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// A task requested on a server
+pub struct Job {
+    /// Index into `App::servers`
+    pub server: usize,
+    pub task: Task,
+    pub state: JobState,
+    /// Last line logged while running
+    pub log: Option<String>,
+    /// Bytes downloaded so far, out of the total when known
+    pub progress: Option<(u64, Option<u64>)>,
+    /// Warnings reported while running
+    pub warnings: Vec<String>,
+    /// Set to ask the running task to stop
+    pub cancelled: Arc<AtomicBool>,
+    /// When the job started running
+    pub started: Option<Instant>,
+}
+
+impl Job {
+    pub fn is_cancelling(&self) -> bool {
+        matches!(self.state, JobState::Running) && self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Tasks run in the order they were requested, at most `limit` at a time
+pub struct Queue {
+    jobs: Vec<Job>,
+    limit: usize,
+}
+
+impl Queue {
+    pub fn new(limit: usize) -> Queue {
+        Queue {
+            jobs: Vec::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    /// Queue `task` on `server`, unless that server already has a job
+    /// waiting or running
+    pub fn push(&mut self, server: usize, task: Task) -> bool {
+        if self.active(server).is_some() {
+            return false;
+        }
+        // Only the last outcome of a server is worth showing
+        self.jobs.retain(|job| job.server != server);
+        self.jobs.push(Job {
+            server,
+            task,
+            state: JobState::Queued,
+            log: None,
+            progress: None,
+            warnings: Vec::new(),
+            cancelled: Arc::default(),
+            started: None,
+        });
+        true
+    }
+
+    /// Jobs currently running, oldest first
+    pub fn running(&self) -> impl Iterator<Item = &Job> {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Running))
+    }
+
+    /// Mark the next queued job as running and return it, unless the limit
+    /// of jobs running together is reached
+    pub fn start_next(&mut self) -> Option<&Job> {
+        if self.running().count() >= self.limit {
+            return None;
+        }
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| matches!(job.state, JobState::Queued))?;
+        job.state = JobState::Running;
+        job.started = Some(Instant::now());
+        Some(job)
+    }
+
+    /// Running job of `server`
+    pub fn running_mut(&mut self, server: usize) -> Option<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|job| job.server == server && matches!(job.state, JobState::Running))
+    }
+
+    /// Record the outcome of the running job of `server`
+    pub fn finish(&mut self, server: usize, state: JobState) {
+        if let Some(job) = self.running_mut(server) {
+            job.state = state;
+            job.log = None;
+            job.progress = None;
+        }
+    }
+
+    /// Cancel the unfinished job of `server`. A queued job is dropped right
+    /// away, a running one is asked to stop and finishes once it did.
+    pub fn cancel(&mut self, server: usize) -> bool {
+        let Some(job) = self.jobs.iter_mut().rev().find(|job| job.server == server) else {
+            return false;
+        };
+        match job.state {
+            JobState::Queued => job.state = JobState::Cancelled,
+            JobState::Running => job.cancelled.store(true, Ordering::SeqCst),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Latest job requested on `server`
+    pub fn job(&self, server: usize) -> Option<&Job> {
+        self.jobs.iter().rev().find(|job| job.server == server)
+    }
+
+    /// Job of `server` which hasn't finished yet
+    fn active(&self, server: usize) -> Option<&Job> {
+        self.job(server)
+            .filter(|job| matches!(job.state, JobState::Queued | JobState::Running))
+    }
+
+    /// Jobs waiting or running, in the order they were requested
+    pub fn unfinished_jobs(&self) -> impl Iterator<Item = &Job> {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Queued | JobState::Running))
+    }
+
+    /// Number of jobs waiting or running
+    pub fn unfinished(&self) -> usize {
+        self.unfinished_jobs().count()
+    }
+}
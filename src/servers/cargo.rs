@@ -0,0 +1,120 @@
+use super::{
+    process::{self, run, run_logged},
+    spec::{Cargo, Meta},
+    Plan, Server, VERSION_COUNT,
+};
+use crate::{
+    download,
+    error::{Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{path::PathBuf, process::Command};
+
+impl Cargo {
+    /// crates.io metadata of the crate, every version included
+    fn krate(&self) -> Result<serde_json::Value> {
+        let response = download::get(&format!("https://crates.io/api/v1/crates/{}", self.krate))?;
+        serde_json::from_reader(response.into_reader()).map_err(Error::from)
+    }
+}
+
+impl Server for Cargo {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "cargo"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://crates.io/crates/{}", self.krate)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join("bin").join(&self.bin)
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let (version, size) = match (version, &self.version) {
+            (None, Some(version)) => (version.to_string(), None),
+            (version, _) => {
+                let krate = self.krate()?;
+                let version = version
+                    .or_else(|| krate["crate"]["max_stable_version"].as_str())
+                    .ok_or_else(|| Error::InvalidResponse(String::from("crate has no version")))?;
+                // Of the source, the build downloads the dependencies too
+                let size = krate["versions"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|entry| entry["num"] == version)
+                    .and_then(|entry| entry["crate_size"].as_u64());
+                (version.to_string(), size)
+            }
+        };
+        Ok(Plan {
+            source: format!("{}@{}", self.krate, version),
+            version,
+            dir: self.dir(),
+            size,
+            requires: vec![String::from("cargo")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        if !process::has_command("cargo", &["--version"]) {
+            return Err(Error::MissingRuntime {
+                name: String::from("cargo"),
+                hint: String::from("install Rust from https://rustup.rs"),
+            });
+        }
+
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!(
+                "Running cargo to install {}",
+                self.krate
+            )));
+
+            run_logged(
+                Command::new("cargo")
+                    .arg("install")
+                    .arg("--root")
+                    .arg(staging)
+                    .arg(&plan.source),
+                report,
+            )?;
+
+            // Lines look like `taplo-cli v0.9.3:`
+            let list = run(Command::new("cargo")
+                .arg("install")
+                .arg("--list")
+                .arg("--root")
+                .arg(staging))?;
+            list.lines()
+                .find_map(|line| line.strip_prefix(&self.krate)?.strip_prefix(" v"))
+                .map(|version| version.trim_end_matches(':').to_string())
+                .ok_or_else(|| Error::InvalidResponse(String::from("cargo reported no version")))
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.plan(None).map(|plan| plan.version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        // Listed newest first
+        Ok(self.krate()?["versions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry["yanked"] != true)
+            .filter_map(|entry| entry["num"].as_str())
+            .filter(|version| !version.contains('-'))
+            .take(VERSION_COUNT)
+            .map(str::to_string)
+            .collect())
+    }
+}
@@ -0,0 +1,126 @@
+use super::{
+    process,
+    spec::{Composer, Meta},
+    Plan, Runtime, Server, VERSION_COUNT,
+};
+use crate::{
+    download,
+    error::{Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{fs, path::PathBuf, process::Command};
+
+impl Composer {
+    /// Every version published on Packagist, newest first
+    fn releases(&self) -> Result<Vec<String>> {
+        let response = download::get(&format!(
+            "https://repo.packagist.org/p2/{}.json",
+            self.package
+        ))?;
+        let metadata: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+        Ok(metadata["packages"][self.package.as_str()]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|package| package["version"].as_str().map(str::to_string))
+            .collect())
+    }
+}
+
+impl Server for Composer {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "composer"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://packagist.org/packages/{}", self.package)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join("vendor").join("bin").join(&self.bin)
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: String::from("php"),
+            hint: "install PHP from https://www.php.net/downloads",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version =
+            match version {
+                Some(version) => version.to_string(),
+                None => self.releases()?.into_iter().next().ok_or_else(|| {
+                    Error::InvalidResponse(String::from("package has no version"))
+                })?,
+            };
+        Ok(Plan {
+            source: format!("{}:{}", self.package, version),
+            version,
+            dir: self.dir(),
+            size: None,
+            requires: vec![String::from("php"), String::from("composer")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        for (program, hint) in [
+            ("php", "install PHP from https://www.php.net/downloads"),
+            ("composer", "install Composer from https://getcomposer.org"),
+        ] {
+            if !process::has_command(program, &["--version"]) {
+                return Err(Error::MissingRuntime {
+                    name: program.to_string(),
+                    hint: hint.to_string(),
+                });
+            }
+        }
+
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!(
+                "Running composer to install {}",
+                self.package
+            )));
+            process::run_logged(
+                Command::new("composer")
+                    .arg("require")
+                    .arg("--no-interaction")
+                    .arg("--working-dir")
+                    .arg(staging)
+                    .arg(&plan.source),
+                report,
+            )?;
+
+            let installed = staging
+                .join("vendor")
+                .join("composer")
+                .join("installed.json");
+            let installed: serde_json::Value = serde_json::from_slice(&fs::read(installed)?)?;
+            installed["packages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|package| package["name"] == self.package)
+                .and_then(|package| package["version"].as_str())
+                .map(str::to_string)
+                .ok_or_else(|| Error::InvalidResponse(String::from("composer reported no version")))
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.plan(None).map(|plan| plan.version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let mut versions = self.releases()?;
+        versions.truncate(VERSION_COUNT);
+        Ok(versions)
+    }
+}
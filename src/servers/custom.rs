@@ -0,0 +1,351 @@
+use super::{
+    fetch, make_executable, process,
+    spec::{Category, Meta},
+    unpack, Plan, Server,
+};
+use crate::{
+    error::{self, Error},
+    worker::{Report, Reporter},
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml::{Spanned, Value};
+
+/// Where a custom server comes from
+enum Source {
+    /// Archive or binary to download, with the SHA-256 it should have
+    Url(String, Option<String>),
+    /// Shell command run from the install directory
+    Command(String),
+}
+
+/// A server defined by the user in `servers.toml`
+pub struct Custom {
+    meta: Meta,
+    source: Source,
+    /// Path of the executable relative to the install directory
+    bin: String,
+}
+
+/// Layout of `servers.toml`, every field kept with its position so problems
+/// can point at the right line
+#[derive(Deserialize)]
+struct File {
+    #[serde(default)]
+    server: Vec<Spanned<BTreeMap<String, Spanned<Value>>>>,
+}
+
+const FIELDS: &[&str] = &[
+    "name",
+    "language",
+    "languages",
+    "description",
+    "category",
+    "aliases",
+    "language_aliases",
+    "homepage",
+    "repository",
+    "lspconfig",
+    "vscode",
+    "conform",
+    "nvim_lint",
+    "verify",
+    "args",
+    "url",
+    "sha256",
+    "command",
+    "bin",
+];
+
+/// Read the custom servers defined in `path`.
+///
+/// Invalid entries are skipped, each problem is returned as a warning naming
+/// the file, line and field at fault. A missing file defines no server.
+pub fn load(path: &Path) -> (Vec<Custom>, Vec<String>) {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return (Vec::new(), Vec::new()),
+        Err(err) => return (Vec::new(), vec![format!("{}: {}", path.display(), err)]),
+    };
+
+    let file: File = match toml::from_str(&text) {
+        Ok(file) => file,
+        Err(err) => {
+            let line = err.span().map_or(1, |span| line_of(&text, span.start));
+            return (
+                Vec::new(),
+                vec![format!("{}:{}: {}", path.display(), line, err.message())],
+            );
+        }
+    };
+
+    let mut servers = Vec::new();
+    let mut warnings = Vec::new();
+    for entry in file.server {
+        let entry = Entry {
+            path,
+            text: &text,
+            line: line_of(&text, entry.span().start),
+            fields: entry.into_inner(),
+        };
+        for key in entry.fields.keys() {
+            if !FIELDS.contains(&key.as_str()) {
+                warnings.push(entry.warning(key, "is not a known field, it was ignored"));
+            }
+        }
+        match entry.parse() {
+            Ok(server) => servers.push(server),
+            Err(warning) => warnings.push(warning),
+        }
+    }
+
+    (servers, warnings)
+}
+
+/// 1-based line of the byte at `offset`
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// A `[[server]]` table being validated
+struct Entry<'a> {
+    path: &'a Path,
+    text: &'a str,
+    /// Line of the table header
+    line: usize,
+    fields: BTreeMap<String, Spanned<Value>>,
+}
+
+impl Entry<'_> {
+    fn parse(&self) -> Result<Custom, String> {
+        let name = self
+            .string("name")?
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| self.warning("name", "is missing"))?;
+
+        let languages = match (self.strings("languages")?, self.string("language")?) {
+            (Some(languages), _) if !languages.is_empty() => languages,
+            (_, Some(language)) => vec![language],
+            _ => return Err(self.warning("languages", "is missing")),
+        };
+        let category = match self.string("category")? {
+            None => Category::default(),
+            Some(name) => Category::ALL
+                .into_iter()
+                .find(|category| category.name() == name)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = Category::ALL.iter().map(|c| c.name()).collect();
+                    self.warning("category", &format!("must be one of {}", names.join(", ")))
+                })?,
+        };
+        let language_aliases = self.aliases_by_language()?;
+        if let Some(language) = language_aliases
+            .keys()
+            .find(|language| !languages.contains(language))
+        {
+            return Err(self.warning(
+                "language_aliases",
+                &format!("names {}, which isn't one of `languages`", language),
+            ));
+        }
+
+        let source = match (self.string("url")?, self.string("command")?) {
+            (Some(url), None) => Source::Url(url, self.string("sha256")?),
+            (None, Some(command)) => Source::Command(command),
+            (Some(_), Some(_)) => {
+                return Err(self.warning("command", "can't be combined with `url`"))
+            }
+            (None, None) => return Err(self.warning("url", "or `command` is missing")),
+        };
+
+        Ok(Custom {
+            meta: Meta {
+                name,
+                languages,
+                description: self.string("description")?.unwrap_or_default(),
+                category,
+                aliases: self.strings("aliases")?.unwrap_or_default(),
+                language_aliases,
+                project_files: BTreeMap::new(),
+                homepage: self.string("homepage")?,
+                repository: self.string("repository")?,
+                lspconfig: self.string("lspconfig")?,
+                vscode: self.string("vscode")?,
+                conform: self.string("conform")?,
+                nvim_lint: self.string("nvim_lint")?,
+                dap: None,
+                verify: self.string("verify")?,
+                args: self.strings("args")?.unwrap_or_default(),
+                shim: None,
+                init_options: None,
+            },
+            source,
+            bin: self
+                .string("bin")?
+                .ok_or_else(|| self.warning("bin", "is missing"))?,
+        })
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>, String> {
+        match self.fields.get(key).map(|value| value.get_ref()) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(self.warning(key, "must be a string")),
+        }
+    }
+
+    fn strings(&self, key: &str) -> Result<Option<Vec<String>>, String> {
+        let values = match self.fields.get(key).map(|value| value.get_ref()) {
+            None => return Ok(None),
+            Some(Value::Array(values)) => values,
+            Some(_) => return Err(self.warning(key, "must be an array of strings")),
+        };
+
+        values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => Ok(value.clone()),
+                _ => Err(self.warning(key, "must be an array of strings")),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// Table of `language_aliases`, each language getting an array of
+    /// other names
+    fn aliases_by_language(&self) -> Result<BTreeMap<String, Vec<String>>, String> {
+        const KEY: &str = "language_aliases";
+        let table = match self.fields.get(KEY).map(|value| value.get_ref()) {
+            None => return Ok(BTreeMap::new()),
+            Some(Value::Table(table)) => table,
+            Some(_) => return Err(self.warning(KEY, "must be a table")),
+        };
+
+        table
+            .iter()
+            .map(|(language, aliases)| {
+                let aliases = aliases
+                    .as_array()
+                    .and_then(|aliases| {
+                        aliases
+                            .iter()
+                            .map(|alias| alias.as_str().map(str::to_string))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        self.warning(KEY, &format!("must give {} an array of strings", language))
+                    })?;
+                Ok((language.clone(), aliases))
+            })
+            .collect()
+    }
+
+    /// Problem with `key`, located at the field when present and at the
+    /// table otherwise
+    fn warning(&self, key: &str, problem: &str) -> String {
+        let line = self
+            .fields
+            .get(key)
+            .map_or(self.line, |value| line_of(self.text, value.span().start));
+        format!(
+            "{}:{}: field `{}` {}",
+            self.path.display(),
+            line,
+            key,
+            problem
+        )
+    }
+}
+
+impl Server for Custom {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        match self.source {
+            Source::Url(..) => "url",
+            Source::Command(_) => "command",
+        }
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join(&self.bin)
+    }
+
+    fn plan(&self, version: Option<&str>) -> error::Result<Plan> {
+        if version.is_some() {
+            return Err(Error::Unsupported(String::from(
+                "custom servers have a single version",
+            )));
+        }
+        let (source, requires, sha256) = match &self.source {
+            Source::Url(url, sha256) => (url.clone(), Vec::new(), sha256.clone()),
+            Source::Command(command) => (command.clone(), vec![String::from("sh")], None),
+        };
+        Ok(Plan {
+            version: String::from("custom"),
+            source,
+            dir: self.dir(),
+            size: None,
+            requires,
+            sha256,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> error::Result<()> {
+        super::install_staged(self, report, |staging| {
+            match &self.source {
+                Source::Url(url, _) => {
+                    let name = url
+                        .rsplit('/')
+                        .next()
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or(&self.bin);
+                    let archive = staging.join(name);
+                    // Without a checksum the URL may serve a newer build each time
+                    let cached = plan.sha256.is_some();
+                    fetch(url, &archive, plan.sha256.as_deref(), cached, report)?;
+                    unpack(&archive, staging, &self.bin)?;
+                }
+                Source::Command(command) => {
+                    report.send(Report::Log(format!("Running {}", command)));
+                    process::run_logged(
+                        Command::new("sh")
+                            .arg("-c")
+                            .arg(command)
+                            .current_dir(staging)
+                            .env("LSP_INSTALLER_DIR", staging),
+                        report,
+                    )?;
+                }
+            }
+
+            let bin = staging.join(&self.bin);
+            if !bin.is_file() {
+                return Err(Error::NotFound(format!(
+                    "installing {} produced no {}",
+                    self.meta.name, self.bin
+                )));
+            }
+            make_executable(&bin)?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> error::Result<String> {
+        Err(Error::Unsupported(String::from(
+            "custom servers don't publish versions, reinstall them instead",
+        )))
+    }
+
+    fn is_custom(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,166 @@
+//! .NET tools installed with `dotnet tool install` into a tool path inside
+//! the install directory, rather than the user's global tools. Also tells
+//! which .NET the machine has, for the servers needing a recent one.
+
+use super::{
+    newest, probe,
+    process::run_logged,
+    spec::{DotnetTool, Meta},
+    Plan, Runtime, Server,
+};
+use crate::{
+    download,
+    error::{Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{path::PathBuf, process::Command, time::Duration};
+
+/// How long `dotnet` gets to list what's installed
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where .NET gets installed from
+const DOWNLOAD: &str = "https://dotnet.microsoft.com/download";
+
+/// Newest major version among the lines `dotnet` prints with `list`, like
+/// `8.0.100 [/usr/share/dotnet/sdk]` for `--list-sdks` or
+/// `Microsoft.NETCore.App 8.0.1 [...]` for `--list-runtimes`
+fn newest_major(list: &str, runtime: Option<&str>) -> Option<u32> {
+    list.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            if let Some(runtime) = runtime {
+                fields.find(|field| *field == runtime)?;
+            }
+            fields.next()?.split('.').next()?.parse().ok()
+        })
+        .max()
+}
+
+/// Fail unless a .NET SDK of major version `minimum` or newer is installed
+pub(super) fn check_sdk(minimum: u32) -> Result<()> {
+    check("SDK", &["--list-sdks"], None, minimum)
+}
+
+/// Fail unless a .NET runtime of major version `minimum` or newer is
+/// installed, an SDK always bringing one
+pub(super) fn check_runtime(minimum: u32) -> Result<()> {
+    check(
+        "runtime",
+        &["--list-runtimes"],
+        Some("Microsoft.NETCore.App"),
+        minimum,
+    )
+}
+
+fn check(what: &str, args: &[&str], runtime: Option<&str>, minimum: u32) -> Result<()> {
+    let found = probe("dotnet", args, LIST_TIMEOUT)
+        .ok()
+        .filter(|probe| probe.status.is_some_and(|status| status.success()))
+        .and_then(|probe| newest_major(&probe.stdout, runtime));
+    let name = format!(".NET {} {} or newer", what, minimum);
+    match found {
+        Some(major) if major >= minimum => Ok(()),
+        Some(major) => Err(Error::MissingRuntime {
+            name,
+            hint: format!(
+                "the newest one installed is .NET {}, get another from {}",
+                major, DOWNLOAD
+            ),
+        }),
+        None => Err(Error::MissingRuntime {
+            name,
+            hint: format!("install it from {}", DOWNLOAD),
+        }),
+    }
+}
+
+impl DotnetTool {
+    fn tool_path(&self) -> PathBuf {
+        self.dir().join("tools")
+    }
+}
+
+impl Server for DotnetTool {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "dotnet-tool"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://www.nuget.org/packages/{}", self.package)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        let exe = if cfg!(windows) { ".exe" } else { "" };
+        self.tool_path().join(format!("{}{}", self.bin, exe))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: String::from("dotnet"),
+            hint: "install the .NET SDK from https://dotnet.microsoft.com/download",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => self.latest_version()?,
+        };
+        Ok(Plan {
+            source: format!("{} {}", self.package, version),
+            version,
+            dir: self.dir(),
+            size: None,
+            requires: vec![String::from("dotnet")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        check_sdk(self.sdk)?;
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!(
+                "Running dotnet tool install {}",
+                plan.source
+            )));
+            run_logged(
+                Command::new("dotnet")
+                    .args(["tool", "install", &self.package])
+                    .args(["--version", &plan.version])
+                    .arg("--tool-path")
+                    .arg(staging.join("tools"))
+                    .env("DOTNET_CLI_TELEMETRY_OPTOUT", "1")
+                    .env("DOTNET_NOLOGO", "1"),
+                report,
+            )?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.versions()?.into_iter().next().ok_or_else(|| {
+            Error::InvalidResponse(format!("NuGet lists no version of {}", self.package))
+        })
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        // Package IDs are lowercased in the flat container
+        let response = download::get(&format!(
+            "https://api.nuget.org/v3-flatcontainer/{}/index.json",
+            self.package.to_lowercase()
+        ))?;
+        let index: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+        let versions = index["versions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|version| version.as_str().map(str::to_string))
+            .collect();
+        Ok(newest(versions))
+    }
+}
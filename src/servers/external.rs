@@ -0,0 +1,82 @@
+//! Servers installed by other means, like `go install` or rustup, found by
+//! looking up their executable in each directory of `PATH`. Adopting one
+//! records its path in the manifest without copying anything.
+
+use super::{verify, Server};
+use crate::{
+    error::Result,
+    manifest::{self, Record},
+    paths, version,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Backend recorded in the manifest for adopted servers
+pub const BACKEND: &str = "external";
+
+/// Extensions tried on Windows, where executables need one
+pub(super) const WINDOWS_EXTENSIONS: &[&str] = &["exe", "cmd", "bat"];
+
+/// First executable on `PATH` named like the one of `server`, the bin
+/// directories of the installer left out, a project's and the global one.
+///
+/// Only looks at each directory itself, so it takes one lookup per
+/// directory and extension.
+pub fn find(server: &dyn Server) -> Option<PathBuf> {
+    let bin = server.bin();
+    let name = bin.file_name()?;
+    let path = env::var_os("PATH")?;
+    let own = paths::bin_dir();
+    env::split_paths(&path)
+        .filter(|dir| {
+            *dir != own && !dir.starts_with(paths::root()) && !dir.starts_with(paths::global_root())
+        })
+        .flat_map(|dir| {
+            let plain = dir.join(name);
+            let mut candidates = vec![plain.clone()];
+            if cfg!(windows) {
+                candidates.extend(
+                    WINDOWS_EXTENSIONS
+                        .iter()
+                        .map(|extension| plain.with_extension(extension)),
+                );
+            }
+            candidates
+        })
+        .find(|candidate| is_executable_file(candidate))
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && super::is_executable(&metadata))
+}
+
+/// Record the executable at `bin` as the install of `server`, once it
+/// passed `Server::verify()`. Returns the version it reported.
+pub fn adopt(server: &dyn Server, bin: &Path) -> Result<String> {
+    let reported = verify(server.meta(), bin)?;
+    let version = reported
+        .as_deref()
+        .and_then(version::find)
+        .unwrap_or(BACKEND)
+        .to_string();
+    manifest::insert(
+        server.name(),
+        Record {
+            version: version.clone(),
+            backend: BACKEND.to_string(),
+            installed_at: manifest::now(),
+            url: None,
+            sha256: None,
+            bin: bin.to_path_buf(),
+            reported_version: reported,
+            held: false,
+            used_at: None,
+            disk_size: None,
+            built_from_source: false,
+            from_file: false,
+        },
+    )?;
+    Ok(version)
+}
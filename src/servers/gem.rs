@@ -0,0 +1,212 @@
+//! Ruby gems, installed with their dependencies into a `GEM_HOME` of their
+//! own inside the install directory so the user's gems are left alone. The
+//! binstub RubyGems writes only finds them with `GEM_HOME` and `GEM_PATH`
+//! set, so editors start a launcher setting both.
+
+use super::{
+    make_executable, newest,
+    process::{self, run_logged},
+    shim,
+    spec::{Gem, Meta},
+    Plan, Runtime, Server,
+};
+use crate::{
+    download,
+    error::{Context, Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+impl Gem {
+    fn gem_home(&self) -> PathBuf {
+        self.dir().join("gems")
+    }
+
+    /// Launcher written into the install directory
+    fn launcher(&self) -> String {
+        if cfg!(windows) {
+            format!("{}.cmd", self.bin)
+        } else {
+            self.bin.clone()
+        }
+    }
+}
+
+/// Fail with what to install when Ruby or RubyGems is missing
+fn check_ruby() -> Result<()> {
+    if !process::has_command("ruby", &["--version"]) {
+        return Err(Error::MissingRuntime {
+            name: String::from("Ruby"),
+            hint: String::from(
+                "install it from https://www.ruby-lang.org/en/documentation/installation",
+            ),
+        });
+    }
+    if !process::has_command("gem", &["--version"]) {
+        return Err(Error::MissingRuntime {
+            name: String::from("RubyGems"),
+            hint: String::from("install the package providing `gem` along with Ruby"),
+        });
+    }
+    Ok(())
+}
+
+impl Server for Gem {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "gem"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://rubygems.org/gems/{}", self.package)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join(self.launcher())
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: String::from("ruby"),
+            hint: "install Ruby, the binstub of the gem runs on the one found on PATH",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => {
+                let response = download::get(&format!(
+                    "https://rubygems.org/api/v1/versions/{}/latest.json",
+                    self.package
+                ))?;
+                let latest: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+                latest["version"]
+                    .as_str()
+                    .filter(|version| *version != "unknown")
+                    .map(str::to_string)
+                    .ok_or_else(|| Error::InvalidResponse(String::from("gem has no version")))?
+            }
+        };
+        Ok(Plan {
+            source: format!("{} -v {}", self.package, version),
+            version,
+            dir: self.dir(),
+            size: None,
+            requires: vec![String::from("gem")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        check_ruby()?;
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!("Running gem install {}", plan.source)));
+            let staged = staging.join("gems");
+            run_logged(
+                Command::new("gem")
+                    .args(["install", "--no-document", &self.package])
+                    .args(["--version", &plan.version])
+                    .arg("--bindir")
+                    .arg(staged.join("bin"))
+                    .env("GEM_HOME", &staged)
+                    .env("GEM_PATH", &staged),
+                report,
+            )?;
+
+            let binstub = staged.join("bin").join(&self.bin);
+            if !binstub.is_file() && !binstub.with_extension("bat").is_file() {
+                return Err(Error::NotFound(format!(
+                    "{} installs no {} executable",
+                    self.package, self.bin
+                )));
+            }
+
+            // Paths of the install once it's swapped in
+            report.send(Report::Log(format!(
+                "Writing the launcher {}",
+                self.launcher()
+            )));
+            let path = staging.join(self.launcher());
+            fs::write(&path, launcher(&self.gem_home(), &self.bin)).at(&path)?;
+            make_executable(&path)?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.plan(None).map(|plan| plan.version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let response = download::get(&format!(
+            "https://rubygems.org/api/v1/versions/{}.json",
+            self.package
+        ))?;
+        let versions: Vec<serde_json::Value> = serde_json::from_reader(response.into_reader())?;
+        Ok(newest(
+            versions
+                .iter()
+                .filter(|version| version["prerelease"] != true)
+                .filter_map(|version| version["number"].as_str().map(str::to_string))
+                .collect(),
+        ))
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        vec![("gem home", self.gem_home().display().to_string())]
+    }
+}
+
+/// Script running the binstub `bin` of the gems in `gem_home`, seeing none
+/// of the user's gems
+#[cfg(not(windows))]
+fn launcher(gem_home: &Path, bin: &str) -> String {
+    let home = shim::quote(&gem_home.display().to_string());
+    let binstub = shim::quote(&gem_home.join("bin").join(bin).display().to_string());
+    let lines = [
+        String::from("#!/bin/sh"),
+        format!(
+            "# Starts {} from its own gems, written by lsp_installer",
+            bin
+        ),
+        format!("GEM_HOME={}", home),
+        format!("GEM_PATH={}", home),
+        String::from("export GEM_HOME GEM_PATH"),
+        format!("exec {} \"$@\"", binstub),
+    ];
+    lines.join("\n") + "\n"
+}
+
+/// `.cmd` variant of the launcher, RubyGems writing `.bat` binstubs there
+#[cfg(windows)]
+fn launcher(gem_home: &Path, bin: &str) -> String {
+    let home = gem_home.display();
+    let lines = [
+        String::from("@echo off"),
+        format!(
+            "rem Starts {} from its own gems, written by lsp_installer",
+            bin
+        ),
+        String::from("setlocal"),
+        format!("set \"GEM_HOME={}\"", home),
+        format!("set \"GEM_PATH={}\"", home),
+        format!(
+            "call \"{}\" %*",
+            gem_home
+                .join("bin")
+                .join(bin)
+                .with_extension("bat")
+                .display()
+        ),
+    ];
+    lines.join("\r\n") + "\r\n"
+}
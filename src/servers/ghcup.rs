@@ -0,0 +1,147 @@
+//! Servers left to ghcup, the Haskell toolchain installer, as
+//! haskell-language-server has to match the GHC of each project and ghcup
+//! knows which builds do. ghcup keeps the install under `~/.ghcup`, the
+//! install directory only links to the executable it put there.
+
+use super::{
+    newest,
+    process::{self, run, run_logged},
+    shim,
+    spec::{Ghcup, Meta},
+    Plan, Runtime, Server,
+};
+use crate::{
+    error::{Context, Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{env, path::PathBuf, process::Command};
+
+impl Ghcup {
+    /// Fail with what to do about it when ghcup isn't installed
+    fn check_ghcup(&self) -> Result<()> {
+        if process::on_path("ghcup") {
+            return Ok(());
+        }
+        Err(Error::MissingRuntime {
+            name: String::from("ghcup"),
+            hint: format!(
+                "install ghcup from https://www.haskell.org/ghcup, which picks the {} build matching each GHC, then install {} again",
+                self.tool, self.meta.name
+            ),
+        })
+    }
+
+    /// Versions ghcup offers of the tool, with the tags it gives each, like
+    /// `recommended` or `latest`
+    fn listed(&self) -> Result<Vec<(String, String)>> {
+        self.check_ghcup()?;
+        let list = run(Command::new("ghcup").args(["list", "--raw-format", "--tool", &self.tool]))?;
+        // Lines like `hls 2.9.0.1 latest,recommended`, after an
+        // installed marker in some versions of ghcup
+        Ok(list
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line
+                    .split_whitespace()
+                    .skip_while(|field| *field != self.tool);
+                fields.next()?;
+                let version = fields.next()?.to_string();
+                Some((version, fields.collect::<Vec<_>>().join(" ")))
+            })
+            .collect())
+    }
+}
+
+impl Server for Ghcup {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "ghcup"
+    }
+
+    fn bin(&self) -> PathBuf {
+        let bin = self.dir().join(&self.bin);
+        if cfg!(windows) {
+            bin.with_extension("cmd")
+        } else {
+            bin
+        }
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: String::from("ghc"),
+            hint: "install a GHC with `ghcup install ghc`, the server works for projects built with it",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => self.latest_version()?,
+        };
+        Ok(Plan {
+            source: format!("ghcup install {} {}", self.tool, version),
+            version,
+            dir: self.dir(),
+            size: None,
+            requires: vec![String::from("ghcup")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        self.check_ghcup()?;
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!("Running {}", plan.source)));
+            run_logged(
+                Command::new("ghcup").args(["install", &self.tool, &plan.version]),
+                report,
+            )?;
+
+            let whereis = run(Command::new("ghcup").args(["whereis", &self.tool, &plan.version]))?;
+            let installed = PathBuf::from(whereis.trim());
+            // Can name another executable of the tool, ghcup keeps one per
+            // version of each, like `haskell-language-server-wrapper-2.9.0.1`
+            let versioned = format!("{}-{}{}", self.bin, plan.version, env::consts::EXE_SUFFIX);
+            let target = match installed.parent() {
+                Some(dir) if dir.join(&versioned).is_file() => dir.join(versioned),
+                _ => installed,
+            };
+            if !target.is_file() {
+                return Err(Error::NotFound(format!(
+                    "ghcup installed {} {} but has no {}",
+                    self.tool, plan.version, self.bin
+                )));
+            }
+            report.send(Report::Log(format!("Linking {}", target.display())));
+            let link = staging.join(self.bin().file_name().unwrap_or_default());
+            shim::create(&target, &link).at(&link)?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        let listed = self.listed()?;
+        let tagged = |tag: &str| {
+            listed
+                .iter()
+                .find(|(_, tags)| tags.split([',', ' ']).any(|t| t == tag))
+                .map(|(version, _)| version.clone())
+        };
+        tagged("recommended")
+            .or_else(|| tagged("latest"))
+            .ok_or_else(|| {
+                Error::InvalidResponse(format!("ghcup recommends no {} version", self.tool))
+            })
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let listed = self.listed()?;
+        Ok(newest(
+            listed.into_iter().map(|(version, _)| version).collect(),
+        ))
+    }
+}
@@ -0,0 +1,568 @@
+use super::{
+    dotnet, fetch, make_executable, probe,
+    process::{self, run_logged},
+    spec::{Detect, GithubRelease, Meta},
+    unpack, Plan, Runtime, Server, BUILT, DOWNLOADED, FROM_FILE, VERSION_COUNT,
+};
+use crate::{
+    download,
+    error::{Context, Error, Result},
+    extract::{self, Format},
+    manifest, metadata,
+    platform::{self, Platform},
+    version,
+    worker::{Report, Reporter},
+};
+use std::{
+    cmp::Ordering,
+    env, fs, iter,
+    path::{self, Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+/// How long a local tool gets to print its version
+const DETECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl GithubRelease {
+    /// Asset name with the platform placeholders filled in, `None` when the
+    /// current platform isn't covered
+    fn asset_template(&self) -> Option<String> {
+        let platform = Platform::current();
+        let template = self.assets.get(&platform.short()).unwrap_or(&self.asset);
+        let mut asset = template
+            .replace("{os}", self.os.get(&platform.os)?)
+            .replace("{arch}", self.arch.get(&platform.arch)?);
+        if asset.contains("{libc}") {
+            let libc = platform
+                .libc
+                .as_ref()
+                .and_then(|libc| self.libc.get(libc))?;
+            asset = asset.replace("{libc}", libc);
+        }
+        if asset.contains("{ext}") {
+            asset = asset.replace("{ext}", self.ext.get(&platform.os)?);
+        }
+        Some(asset)
+    }
+
+    /// Name of the asset matching the current platform in the release `tag`
+    fn asset_name(&self, tag: &str) -> Result<String> {
+        let Some(template) = self.asset_template() else {
+            return Err(Error::Unsupported(format!(
+                "{} is not available for {}, set {} to use the assets of another platform",
+                self.meta.name,
+                Platform::current(),
+                platform::OVERRIDE_VAR
+            )));
+        };
+        let mut asset = template.replace("{version}", tag.trim_start_matches('v'));
+        for (key, detect) in &self.detect {
+            let placeholder = format!("{{{}}}", key);
+            if asset.contains(&placeholder) {
+                asset = asset.replace(&placeholder, &detected(detect)?);
+            }
+        }
+        Ok(asset)
+    }
+
+    /// Error for the release `tag` having no asset for this platform,
+    /// naming the newest release that has one
+    fn unpublished(&self, tag: &str) -> Error {
+        let platform = Platform::current().short();
+        let releases = api(&format!(
+            "https://api.github.com/repos/{}/releases?per_page={}",
+            self.repo, VERSION_COUNT
+        ));
+        let other = releases.ok().and_then(|releases| {
+            releases
+                .as_array()?
+                .iter()
+                .filter(|release| release["draft"] != true && release["prerelease"] != true)
+                .find_map(|release| {
+                    let tag = release_tag(release).ok()?;
+                    let name = self.asset_name(&tag).ok()?;
+                    release_asset(release, &name).ok().map(|_| tag)
+                })
+        });
+        Error::NotFound(match other {
+            Some(other) => format!(
+                "{} is not available for {} at version {}, try version {}",
+                self.meta.name, platform, tag, other
+            ),
+            None => format!(
+                "{} is not available for {} at version {}, nor in its last {} releases",
+                self.meta.name, platform, tag, VERSION_COUNT
+            ),
+        })
+    }
+
+    /// Plan of the release `version`, for the checksum it publishes. The
+    /// plan of the latest release kept in the metadata cache does when it's
+    /// that version, and tags may or may not start with a `v`.
+    fn release_plan(&self, version: &str) -> Option<Plan> {
+        let cached = metadata::plan(&self.meta.name, None)
+            .filter(|plan| version::compare(&plan.version, version) == Ordering::Equal);
+        cached.or_else(|| {
+            [version.to_string(), format!("v{}", version)]
+                .iter()
+                .find_map(|tag| super::planned(self, Some(tag)).ok())
+        })
+    }
+}
+
+impl Server for GithubRelease {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "github-release"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        homepage.or_else(|| self.repository())
+    }
+
+    fn repository(&self) -> Option<String> {
+        let repository = self.meta.repository.clone();
+        Some(repository.unwrap_or_else(|| format!("https://github.com/{}", self.repo)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        let version = self.installed_version().unwrap_or_default();
+        self.dir().join(bin_path(&self.bin, &version))
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let release = match version {
+            Some(tag) => api(&format!(
+                "https://api.github.com/repos/{}/releases/tags/{}",
+                self.repo, tag
+            ))?,
+            None => latest_release(&self.repo)?,
+        };
+        let tag = release_tag(&release)?;
+        let name = self.asset_name(&tag)?;
+        let asset = release_asset(&release, &name).map_err(|_| self.unpublished(&tag))?;
+        let sha256 = match &self.checksums {
+            Some(checksums) => Some(published_checksum(
+                &release,
+                &checksums.replace("{asset}", &name),
+                &name,
+            )?),
+            None => None,
+        };
+        Ok(Plan {
+            source: asset_url(asset)?,
+            size: asset["size"].as_u64(),
+            version: tag,
+            dir: self.dir(),
+            requires: Vec::new(),
+            sha256,
+        })
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        self.dotnet.map(|_| Runtime {
+            program: String::from("dotnet"),
+            hint: "install the .NET runtime from https://dotnet.microsoft.com/download",
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        if let Some(minimum) = self.dotnet {
+            dotnet::check_runtime(minimum)?;
+        }
+        let tag = &plan.version;
+        let asset = self.asset_name(tag)?;
+        super::install_staged(self, report, |staging| {
+            let archive = staging.join(&asset);
+            fetch(&plan.source, &archive, plan.sha256.as_deref(), true, report)?;
+            report.send(Report::Log(format!("Extracting {}", asset)));
+            let bin = bin_path(&self.bin, tag);
+            unpack(&archive, staging, &bin)?;
+
+            let bin = staging.join(bin);
+            if !bin.is_file() {
+                return Err(Error::NotFound(format!("{} has no {}", asset, self.bin)));
+            }
+            make_executable(&bin)?;
+            if let Some(file) = self.files.iter().find(|file| !staging.join(file).exists()) {
+                return Err(Error::NotFound(format!(
+                    "{} has no {}, which {} needs",
+                    asset, file, self.meta.name
+                )));
+            }
+            Ok(tag.to_string())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        release_tag(&latest_release(&self.repo)?)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let releases = api(&format!(
+            "https://api.github.com/repos/{}/releases?per_page={}",
+            self.repo, VERSION_COUNT
+        ))?;
+        Ok(releases
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|release| release["draft"] != true && release["prerelease"] != true)
+            .filter_map(|release| release["tag_name"].as_str().map(str::to_string))
+            .collect())
+    }
+
+    fn is_available(&self) -> bool {
+        self.asset_template().is_some()
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        match manifest::get(&self.meta.name) {
+            Some(record) if record.built_from_source => {
+                vec![("built", String::from("from source"))]
+            }
+            Some(record) if record.from_file => {
+                vec![("source", String::from("local file"))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn can_build(&self) -> bool {
+        self.source.is_some()
+    }
+
+    fn build_tools(&self) -> Vec<String> {
+        let Some(source) = &self.source else {
+            return Vec::new();
+        };
+        iter::once("git")
+            .chain(source.build.first().map(String::as_str))
+            .chain(source.requires.iter().map(String::as_str))
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn build(&self, report: &Reporter) -> Result<()> {
+        let name = &self.meta.name;
+        let Some(source) = &self.source else {
+            return Err(Error::Unsupported(format!(
+                "{} can't be built from source",
+                name
+            )));
+        };
+        let Some((program, args)) = source.build.split_first() else {
+            return Err(Error::Unsupported(format!(
+                "the registry has no build command for {}",
+                name
+            )));
+        };
+        // Before fetching anything, the build taking long enough already
+        for tool in self.build_tools() {
+            if !process::on_path(&tool) {
+                return Err(Error::MissingRuntime {
+                    hint: format!("install {} to build {} from source", tool, name),
+                    name: tool,
+                });
+            }
+        }
+
+        let tag = self.latest_version()?;
+        super::install_staged(self, report, |staging| {
+            let checkout = staging.join("source");
+            report.send(Report::Log(format!("Cloning {} at {}", source.git, tag)));
+            run_logged(
+                Command::new("git")
+                    .args(["clone", "--depth", "1", "--branch", &tag])
+                    .arg(&source.git)
+                    .arg(&checkout),
+                report,
+            )?;
+            report.send(Report::Log(format!("Running {}", source.build.join(" "))));
+            run_logged(
+                Command::new(program).args(args).current_dir(&checkout),
+                report,
+            )?;
+
+            let built = checkout.join(&source.bin);
+            if !built.is_file() {
+                return Err(Error::NotFound(format!("the build made no {}", source.bin)));
+            }
+            let bin = staging.join(bin_path(&self.bin, &tag));
+            if let Some(parent) = bin.parent() {
+                fs::create_dir_all(parent).at(parent)?;
+            }
+            fs::copy(&built, &bin).at(&bin)?;
+            make_executable(&bin)?;
+            // Sources and build output take far more than the server itself
+            fs::remove_dir_all(&checkout).at(&checkout)?;
+            BUILT.set(true);
+            Ok(tag)
+        })
+    }
+
+    fn install_file(&self, file: &Path, verify: bool, report: &Reporter) -> Result<()> {
+        let name = &self.meta.name;
+        if !file.exists() {
+            return Err(Error::NotFound(format!("{} doesn't exist", file.display())));
+        }
+        if !file.is_file() {
+            return Err(Error::NotFound(format!("{} is not a file", file.display())));
+        }
+        let file = path::absolute(file).at(file)?;
+        let file_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // Going by what the file holds, its name may have been changed
+        let template = self.asset_template();
+        if let Some(expected) = template.as_deref().and_then(Format::from_name) {
+            let actual = extract::sniff(&file).at(&file)?;
+            if actual != expected {
+                return Err(Error::Extraction {
+                    archive: file.clone(),
+                    reason: format!(
+                        "{} is published as {} like {}, this is {}",
+                        name,
+                        expected.describe(),
+                        template
+                            .as_deref()
+                            .unwrap_or_default()
+                            .replace("{version}", "*"),
+                        actual.describe()
+                    ),
+                });
+            }
+        }
+        // Like `lua-language-server-3.13.5-linux-x64.tar.gz`, when the asset
+        // names the version
+        let named = template.as_deref().and_then(|template| {
+            let (prefix, suffix) = template.split_once("{version}")?;
+            let version = file_name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            (!version.is_empty()).then(|| version.to_string())
+        });
+        if named.is_none() && self.bin.contains("{version}") {
+            return Err(Error::Unsupported(format!(
+                "the version of {} tells where its executable is, keep the name the release gave it",
+                file_name
+            )));
+        }
+        let digest = download::sha256_file(&file).at(&file)?;
+
+        super::install_staged(self, report, |staging| {
+            // A copy named like the asset, the file stays where it is
+            let asset = match &template {
+                Some(template) => {
+                    template.replace("{version}", named.as_deref().unwrap_or_default())
+                }
+                None => file_name.clone(),
+            };
+            let archive = staging.join(&asset);
+            report.send(Report::Log(format!("Copying {}", file.display())));
+            fs::copy(&file, &archive).at(&archive)?;
+            report.send(Report::Log(format!("Extracting {}", file_name)));
+            let bin = bin_path(&self.bin, named.as_deref().unwrap_or_default());
+            unpack(&archive, staging, &bin)?;
+
+            let bin = staging.join(bin);
+            if !bin.is_file() {
+                return Err(Error::NotFound(format!(
+                    "{} has no {}",
+                    file_name, self.bin
+                )));
+            }
+            make_executable(&bin)?;
+            if let Some(needed) = self.files.iter().find(|file| !staging.join(file).exists()) {
+                return Err(Error::NotFound(format!(
+                    "{} has no {}, which {} needs",
+                    file_name, needed, name
+                )));
+            }
+
+            let probed = match &named {
+                Some(_) => None,
+                None => super::verify(&self.meta, &bin)?,
+            };
+            let found = named.clone().or_else(|| {
+                probed
+                    .as_deref()
+                    .and_then(version::find)
+                    .map(str::to_string)
+            });
+            let Some(mut found) = found else {
+                report.send(Report::Warning(format!(
+                    "couldn't tell which version {} is, updates will offer the latest release",
+                    file_name
+                )));
+                return record_file(&file, digest.clone(), String::from("0"));
+            };
+
+            if !verify {
+                report.send(Report::Log(format!(
+                    "Not checking {} against a published checksum",
+                    file_name
+                )));
+                return record_file(&file, digest.clone(), found);
+            }
+            let plan = self
+                .checksums
+                .is_some()
+                .then(|| self.release_plan(&found))
+                .flatten();
+            match plan {
+                Some(Plan {
+                    sha256: Some(expected),
+                    ..
+                }) if !expected.eq_ignore_ascii_case(&digest) => {
+                    return Err(Error::ChecksumMismatch {
+                        file: file_name.clone(),
+                        expected,
+                        actual: digest.clone(),
+                    });
+                }
+                Some(Plan {
+                    sha256: Some(_),
+                    version,
+                    ..
+                }) => {
+                    report.send(Report::Log(format!(
+                        "{} matches the checksum published for {}",
+                        file_name, version
+                    )));
+                    // Spelled like the tag, as updates compare it with
+                    found = version;
+                }
+                _ => report.send(Report::Warning(format!(
+                    "no checksum of {} {} is known, {} wasn't verified",
+                    name, found, file_name
+                ))),
+            }
+            record_file(&file, digest.clone(), found)
+        })
+    }
+}
+
+/// Keep `file`, whose SHA-256 is `digest`, as what the install of
+/// `version` came from, for its manifest record
+fn record_file(file: &Path, digest: String, version: String) -> Result<String> {
+    DOWNLOADED.set(Some((file.display().to_string(), digest)));
+    FROM_FILE.set(true);
+    Ok(version)
+}
+
+/// Version of the local tool `detect` reads, like `1.15` out of the
+/// `Elixir 1.15.7 (compiled with Erlang/OTP 26)` line of `elixir --version`
+fn detected(detect: &Detect) -> Result<String> {
+    let missing = || Error::MissingRuntime {
+        name: detect.name.clone(),
+        hint: detect.hint.clone(),
+    };
+    let (program, args) = detect.command.split_first().ok_or_else(missing)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let probe = probe(program, &args, DETECT_TIMEOUT).map_err(|_| missing())?;
+    if !probe.status.is_some_and(|status| status.success()) {
+        return Err(missing());
+    }
+    let version = probe
+        .stdout
+        .lines()
+        .chain(probe.stderr.lines())
+        .find_map(|line| {
+            let mut words = line.split_whitespace();
+            words.find(|word| *word == detect.after)?;
+            words.next()
+        })
+        .ok_or_else(|| {
+            Error::InvalidResponse(format!(
+                "`{}` prints no {} version",
+                detect.command.join(" "),
+                detect.after
+            ))
+        })?
+        .trim_start_matches('v');
+    Ok(match detect.components {
+        Some(components) => version
+            .split('.')
+            .take(components)
+            .collect::<Vec<_>>()
+            .join("."),
+        None => version.to_string(),
+    })
+}
+
+/// Path of the executable inside the install of the release `tag`
+fn bin_path(bin: &str, tag: &str) -> String {
+    let exe = if cfg!(windows) { ".exe" } else { "" };
+    bin.replace("{version}", tag.trim_start_matches('v'))
+        .replace("{exe}", exe)
+}
+
+/// Call the GitHub API, authenticating with `GITHUB_TOKEN` when it's set to
+/// get past the rate limit for anonymous requests
+pub(super) fn api(url: &str) -> Result<serde_json::Value> {
+    let mut request = download::request(url)?.set("Accept", "application/vnd.github+json");
+    if let Ok(token) = env::var("GITHUB_TOKEN") {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    let response = download::send(request)?;
+    serde_json::from_reader(response.into_reader()).map_err(Error::from)
+}
+
+/// Latest release of a GitHub repository
+fn latest_release(repo: &str) -> Result<serde_json::Value> {
+    api(&format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        repo
+    ))
+}
+
+/// Tag name of a release returned by `latest_release()`
+fn release_tag(release: &serde_json::Value) -> Result<String> {
+    release["tag_name"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidResponse(String::from("release has no tag name")))
+}
+
+/// Asset called `name` in a release
+fn release_asset<'a>(release: &'a serde_json::Value, name: &str) -> Result<&'a serde_json::Value> {
+    release["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|asset| asset["name"] == name)
+        .ok_or_else(|| Error::NotFound(format!("release has no asset named {}", name)))
+}
+
+/// Download URL of an asset returned by `release_asset()`
+fn asset_url(asset: &serde_json::Value) -> Result<String> {
+    asset["browser_download_url"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidResponse(String::from("asset has no download URL")))
+}
+
+/// SHA-256 of `asset` listed in the release asset called `name`, either a
+/// `sha256sum` listing or a file holding the digest alone
+fn published_checksum(release: &serde_json::Value, name: &str, asset: &str) -> Result<String> {
+    let url = asset_url(release_asset(release, name)?)?;
+    let text = download::get(&url)?.into_string()?;
+
+    text.lines()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let digest = fields.next()?;
+            match fields.next().map(|file| file.trim_start_matches('*')) {
+                Some(file) if file != asset => None,
+                _ => Some(digest.to_string()),
+            }
+        })
+        .ok_or_else(|| Error::NotFound(format!("{} lists no checksum for {}", name, asset)))
+}
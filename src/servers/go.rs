@@ -0,0 +1,109 @@
+use super::{
+    newest,
+    process::{self, run, run_logged},
+    spec::{Go, Meta},
+    Plan, Server,
+};
+use crate::{
+    download,
+    error::{Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{path::PathBuf, process::Command};
+
+impl Go {
+    /// Module path the Go proxy knows the package by
+    fn module(&self) -> &str {
+        self.module.as_deref().unwrap_or(&self.package)
+    }
+}
+
+impl Server for Go {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "go"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://pkg.go.dev/{}", self.package)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join(&self.bin)
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version = match version.or(self.version.as_deref()) {
+            Some(version) => version.to_string(),
+            None => {
+                let response = download::get(&format!(
+                    "https://proxy.golang.org/{}/@latest",
+                    self.module()
+                ))?;
+                let info: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+                info["Version"]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| Error::InvalidResponse(String::from("module has no version")))?
+            }
+        };
+        Ok(Plan {
+            source: format!("{}@{}", self.package, version),
+            version,
+            dir: self.dir(),
+            size: None,
+            requires: vec![String::from("go")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        if !process::has_command("go", &["version"]) {
+            return Err(Error::MissingRuntime {
+                name: String::from("Go"),
+                hint: String::from("install it from https://go.dev/dl"),
+            });
+        }
+
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!("Running go install {}", plan.source)));
+
+            // GOBIN keeps the binary out of ~/go/bin
+            run_logged(
+                Command::new("go")
+                    .arg("install")
+                    .arg(&plan.source)
+                    .env("GOBIN", staging),
+                report,
+            )?;
+
+            // The module line looks like `\tmod\tgolang.org/x/tools/gopls\tv0.16.1\th1:...`
+            let info = run(Command::new("go")
+                .arg("version")
+                .arg("-m")
+                .arg(staging.join(&self.bin)))?;
+            info.lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>())
+                .find(|fields| fields.first() == Some(&"mod"))
+                .and_then(|fields| fields.get(2).map(|version| version.to_string()))
+                .ok_or_else(|| Error::InvalidResponse(String::from("go reported no version")))
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.plan(None).map(|plan| plan.version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let list = download::get(&format!(
+            "https://proxy.golang.org/{}/@v/list",
+            self.module()
+        ))?
+        .into_string()?;
+        Ok(newest(list.lines().map(str::to_string).collect()))
+    }
+}
@@ -0,0 +1,266 @@
+//! Eclipse JDT LS, a Java program rather than an executable. The milestone
+//! build gets unpacked as is, and a launcher script written next to it
+//! starts the right `java` with the options the server needs, a
+//! configuration directory for the OS and a data directory per workspace.
+
+use super::{
+    fetch, make_executable, newest, probe, shim,
+    spec::{Jdtls, Meta},
+    unpack, Plan, Runtime, Server,
+};
+use crate::{
+    download,
+    error::{Context, Error, Result},
+    paths,
+    worker::{Report, Reporter},
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Launcher script written into the install directory
+const LAUNCHER: &str = if cfg!(windows) { "jdtls.cmd" } else { "jdtls" };
+
+/// Options of the JVM running the server, before `-jar`
+const JVM_ARGS: &[&str] = &[
+    "-Declipse.application=org.eclipse.jdt.ls.core.id1",
+    "-Dosgi.bundles.defaultStartLevel=4",
+    "-Declipse.product=org.eclipse.jdt.ls.core.product",
+    "-Dlog.level=ALL",
+    "-Xmx1G",
+    "--add-modules=ALL-SYSTEM",
+    "--add-opens=java.base/java.util=ALL-UNNAMED",
+    "--add-opens=java.base/java.lang=ALL-UNNAMED",
+];
+
+/// How long `java -version` gets to answer
+const JAVA_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl Jdtls {
+    /// Java the launcher starts: the one of `JAVA_HOME`, else `java` on
+    /// `PATH`
+    fn java() -> PathBuf {
+        match env::var_os("JAVA_HOME") {
+            Some(home) => Path::new(&home).join("bin").join("java"),
+            None => PathBuf::from("java"),
+        }
+    }
+
+    /// Fail unless the Java the launcher starts is recent enough
+    fn check_java(&self) -> Result<()> {
+        let java = Jdtls::java();
+        let hint = format!(
+            "install Java {} or newer, like from https://adoptium.net, or point JAVA_HOME at one",
+            self.java
+        );
+        let Some(found) = java_version(&java) else {
+            return Err(Error::MissingRuntime {
+                name: String::from("Java"),
+                hint,
+            });
+        };
+        if found < self.java {
+            return Err(Error::MissingRuntime {
+                name: format!("Java {} or newer", self.java),
+                hint: format!("{} is Java {}, {}", java.display(), found, hint),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Server for Jdtls {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "jdtls"
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join(LAUNCHER)
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: Jdtls::java().display().to_string(),
+            hint:
+                "install a recent Java, like from https://adoptium.net, or point JAVA_HOME at one",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => self.latest_version()?,
+        };
+        // Names the build of the milestone, like `jdt-language-server-1.40.0-202409261450.tar.gz`
+        let build = download::get(&format!("{}/{}/latest.txt", self.url, version))?
+            .into_string()?
+            .trim()
+            .to_string();
+        if build.is_empty() {
+            return Err(Error::NotFound(format!(
+                "milestone {} names no build",
+                version
+            )));
+        }
+        let source = format!("{}/{}/{}", self.url, version, build);
+        let sha256 = download::get(&format!("{}.sha256", source))
+            .and_then(|response| Ok(response.into_string()?))
+            .ok()
+            .and_then(|text| text.split_whitespace().next().map(str::to_string));
+        Ok(Plan {
+            version,
+            source,
+            dir: self.dir(),
+            size: None,
+            requires: Vec::new(),
+            sha256,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        self.check_java()?;
+        super::install_staged(self, report, |staging| {
+            let name = plan.source.rsplit('/').next().unwrap_or("jdtls.tar.gz");
+            let archive = staging.join(name);
+            fetch(&plan.source, &archive, plan.sha256.as_deref(), true, report)?;
+            report.send(Report::Log(format!("Extracting {}", name)));
+            unpack(&archive, staging, LAUNCHER)?;
+
+            let plugins = staging.join("plugins");
+            let jar = fs::read_dir(&plugins)
+                .at(&plugins)?
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .find(|name| {
+                    name.starts_with("org.eclipse.equinox.launcher_") && name.ends_with(".jar")
+                })
+                .ok_or_else(|| Error::NotFound(format!("{} has no Equinox launcher", name)))?;
+            let config = configuration(staging).ok_or_else(|| {
+                Error::NotFound(format!("{} has no configuration for this OS", name))
+            })?;
+
+            // Paths of the install once it's swapped in, not of the staging
+            // directory
+            let dir = self.dir();
+            report.send(Report::Log(format!("Writing the launcher {}", LAUNCHER)));
+            let script = launcher(
+                &dir.join("plugins").join(jar),
+                &dir.join(config),
+                &paths::cache_dir().join("jdtls"),
+            );
+            let path = staging.join(LAUNCHER);
+            fs::write(&path, script).at(&path)?;
+            make_executable(&path)?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.versions()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::InvalidResponse(format!("{} lists no milestone", self.url)))
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        // A directory listing, with one subdirectory per milestone
+        let listing = download::get(&format!("{}/", self.url))?.into_string()?;
+        let mut versions: Vec<String> = listing
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|word| {
+                let parts: Vec<&str> = word.split('.').collect();
+                parts.len() == 3 && parts.iter().all(|part| !part.is_empty())
+            })
+            .map(str::to_string)
+            .collect();
+        versions.sort();
+        versions.dedup();
+        Ok(newest(versions))
+    }
+}
+
+/// Major version of the Java `java` is, like 17 for `openjdk version
+/// "17.0.2"` or 8 for `java version "1.8.0_291"`
+fn java_version(java: &Path) -> Option<u32> {
+    let probe = probe(java, &["-version"], JAVA_TIMEOUT).ok()?;
+    // Printed on stderr, along with the runtime and VM lines
+    let version = probe.stderr.split('"').nth(1)?;
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Configuration directory of the unpacked build in `dir` for this OS and
+/// architecture, relative to `dir`
+fn configuration(dir: &Path) -> Option<String> {
+    let os = match env::consts::OS {
+        "linux" => "linux",
+        "macos" => "mac",
+        "windows" => "win",
+        _ => return None,
+    };
+    let generic = format!("config_{}", os);
+    // Newer builds ship one for ARM, the generic one being for x86_64
+    let arm = format!("{}_arm", generic);
+    [arm, generic]
+        .into_iter()
+        .filter(|name| env::consts::ARCH == "aarch64" || !name.ends_with("_arm"))
+        .find(|name| dir.join(name).is_dir())
+}
+
+/// Script starting the server from `jar` with `config`, and a data
+/// directory under `data` named after the workspace unless `-data` is given
+#[cfg(not(windows))]
+fn launcher(jar: &Path, config: &Path, data: &Path) -> String {
+    let quote = |path: &Path| shim::quote(&path.display().to_string());
+    let mut script = String::from("#!/bin/sh\n# Starts Eclipse JDT LS, written by lsp_installer\n");
+    script.push_str("java=\"${JAVA_HOME:+$JAVA_HOME/bin/}java\"\n");
+    script.push_str("case \" $* \" in\n*\" -data \"*) ;;\n*)\n");
+    script.push_str("    workspace=$(pwd)\n");
+    script.push_str("    key=$(printf '%s' \"$workspace\" | cksum | cut -d ' ' -f 1)\n");
+    script.push_str(&format!(
+        "    set -- -data {}/\"$(basename \"$workspace\")-$key\" \"$@\"\n",
+        quote(data)
+    ));
+    script.push_str("    ;;\nesac\n");
+    script.push_str("exec \"$java\"");
+    for arg in JVM_ARGS {
+        script.push_str(&format!(" \\\n    {}", arg));
+    }
+    script.push_str(&format!(" \\\n    -jar {}", quote(jar)));
+    script.push_str(&format!(" \\\n    -configuration {}", quote(config)));
+    script.push_str(" \\\n    \"$@\"\n");
+    script
+}
+
+/// `.cmd` variant of the launcher, the workspace getting named after its
+/// path with the separators replaced
+#[cfg(windows)]
+fn launcher(jar: &Path, config: &Path, data: &Path) -> String {
+    let lines = [
+        String::from("@echo off"),
+        String::from("rem Starts Eclipse JDT LS, written by lsp_installer"),
+        String::from("setlocal"),
+        String::from("set \"JAVA=java\""),
+        String::from("if defined JAVA_HOME set \"JAVA=%JAVA_HOME%\\bin\\java\""),
+        String::from("set \"WORKSPACE=%CD::=%\""),
+        String::from("set \"WORKSPACE=%WORKSPACE:\\=_%\""),
+        format!("set DATA=-data \"{}\\%WORKSPACE%\"", data.display()),
+        String::from("echo.%* | findstr /C:\"-data\" >nul && set DATA="),
+        format!(
+            "\"%JAVA%\" {} -jar \"{}\" -configuration \"{}\" %DATA% %*",
+            JVM_ARGS.join(" "),
+            jar.display(),
+            config.display()
+        ),
+    ];
+    lines.join("\r\n") + "\r\n"
+}
@@ -0,0 +1,280 @@
+//! Installs kept in a directory per version inside the home of their
+//! server, `<root>/<name>`, next to a `current` link to the one in use and
+//! a `previous` link to the one a rollback goes back to. Switching between
+//! them only replaces a link, so a directory holding a running executable
+//! never gets renamed, which Windows refuses.
+//!
+//! Where symlinks can't be made, as on Windows without the privilege, the
+//! link is a junction, or else a copy of the version directory.
+
+use super::{remove_dir, sibling, VERSION_FILE};
+use crate::error::{Context, Error, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Link to the install in use
+pub(super) const CURRENT: &str = "current";
+
+/// Link to the install the one in use replaced
+pub(super) const PREVIOUS: &str = "previous";
+
+/// File in a copy standing in for a link, naming the directory copied
+#[cfg_attr(unix, allow(dead_code))]
+const COPIED_FROM: &str = ".copied-from";
+
+/// Install directory of the server whose home is `home`: the one `current`
+/// points to, or `home` itself for an install made before there were
+/// versions
+pub(super) fn dir(home: &Path) -> PathBuf {
+    if is_legacy(home) {
+        home.to_path_buf()
+    } else {
+        home.join(CURRENT)
+    }
+}
+
+/// Whether `home` holds an install made before there were versions
+fn is_legacy(home: &Path) -> bool {
+    fs::symlink_metadata(home.join(CURRENT)).is_err() && home.join(VERSION_FILE).is_file()
+}
+
+/// Where an install made before there were versions waits while the
+/// first versioned one replaces it
+pub(super) fn legacy(home: &Path) -> PathBuf {
+    sibling(home, ".legacy")
+}
+
+/// Move the install made before there were versions out of `home`, if it
+/// holds one
+pub(super) fn set_aside(home: &Path) -> Result<()> {
+    if is_legacy(home) {
+        let legacy = legacy(home);
+        remove_dir(&legacy)?;
+        fs::rename(home, &legacy).at(home)?;
+    }
+    Ok(())
+}
+
+/// Name of the version directory `link` in `home` points to
+pub(super) fn target(home: &Path, link: &str) -> Option<PathBuf> {
+    let path = home.join(link);
+    let target = match fs::read_link(&path) {
+        Ok(target) => target,
+        Err(_) => PathBuf::from(fs::read_to_string(path.join(COPIED_FROM)).ok()?.trim()),
+    };
+    target.file_name().map(PathBuf::from)
+}
+
+/// Directory in `home` nothing uses yet to install `version` into, named
+/// after it
+pub(super) fn new_dir(home: &Path, version: &str) -> PathBuf {
+    let name: String = version
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | '+' => c,
+            _ => '_',
+        })
+        .collect();
+    let name = if name.is_empty() { "version" } else { &name };
+    let taken = |dir: &Path| {
+        dir.file_name()
+            .is_some_and(|name| name == CURRENT || name == PREVIOUS)
+            || fs::symlink_metadata(dir).is_ok()
+    };
+    // Reinstalling a version leaves the directory in use alone
+    let mut dir = home.join(name);
+    let mut count = 1;
+    while taken(&dir) {
+        count += 1;
+        dir = home.join(format!("{}-{}", name, count));
+    }
+    dir
+}
+
+/// Make `dir`, a version directory in `home`, the one in use, the one it
+/// replaces becoming the previous one
+pub(super) fn switch(home: &Path, dir: &Path) -> Result<()> {
+    let name = dir
+        .file_name()
+        .ok_or_else(|| Error::NotFound(format!("{} isn't a version", dir.display())))?;
+    if let Some(current) = target(home, CURRENT) {
+        point(home, PREVIOUS, Some(&current))?;
+    }
+    point(home, CURRENT, Some(Path::new(name)))
+}
+
+/// Point `link` in `home` at the version directory `name`, or remove it
+pub(super) fn point(home: &Path, link: &str, name: Option<&Path>) -> Result<()> {
+    let path = home.join(link);
+    match name {
+        Some(name) => replace_link(&home.join(name), name, &path),
+        None => unlink(&path),
+    }
+}
+
+/// Remove the version directories neither link points to. Directories
+/// starting with a dot are left to the installs building in them.
+pub(super) fn prune(home: &Path) -> Result<()> {
+    let keep = [target(home, CURRENT), target(home, PREVIOUS)];
+    let Ok(entries) = fs::read_dir(home) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let name = PathBuf::from(entry.file_name());
+        if name == Path::new(CURRENT)
+            || name == Path::new(PREVIOUS)
+            || name.to_string_lossy().starts_with('.')
+            || keep.contains(&Some(name))
+        {
+            continue;
+        }
+        remove_dir(&entry.path())?;
+    }
+    Ok(())
+}
+
+/// Made next to `path` then renamed over it, replacing it in one step
+#[cfg(unix)]
+fn replace_link(_dir: &Path, name: &Path, path: &Path) -> Result<()> {
+    let temp = sibling(path, ".link");
+    unlink(&temp)?;
+    std::os::unix::fs::symlink(name, &temp).at(&temp)?;
+    fs::rename(&temp, path).at(path)
+}
+
+#[cfg(windows)]
+fn replace_link(dir: &Path, name: &Path, path: &Path) -> Result<()> {
+    unlink(path)?;
+    if std::os::windows::fs::symlink_dir(name, path).is_ok() {
+        return Ok(());
+    }
+    // Needing no privilege, only a local NTFS drive
+    let junction = std::process::Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(path)
+        .arg(dir)
+        .output();
+    if junction.is_ok_and(|output| output.status.success()) {
+        return Ok(());
+    }
+    copy_dir(dir, path)?;
+    let marker = path.join(COPIED_FROM);
+    fs::write(&marker, name.to_string_lossy().as_bytes()).at(&marker)
+}
+
+#[cfg(windows)]
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to).at(to)?;
+    for entry in fs::read_dir(from).at(from)? {
+        let entry = entry.at(from)?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type().at(&entry.path())?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), &dest).at(&dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the link at `path`, or the copy standing in for one, leaving
+/// what it points to alone
+fn unlink(path: &Path) -> Result<()> {
+    match fs::symlink_metadata(path) {
+        Err(_) => Ok(()),
+        // Links to directories are directories to Windows
+        Ok(metadata) if metadata.is_symlink() => fs::remove_file(path)
+            .or_else(|_| fs::remove_dir(path))
+            .at(path),
+        Ok(_) => remove_dir(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Version directory `name` in `home`, holding the version file
+    fn version(home: &Path, name: &str) -> PathBuf {
+        let dir = home.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(VERSION_FILE), name).unwrap();
+        dir
+    }
+
+    #[test]
+    fn new_dir_avoids_taken_names() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        assert_eq!(new_dir(home, "v1.0"), home.join("v1.0"));
+        version(home, "v1.0");
+        assert_eq!(new_dir(home, "v1.0"), home.join("v1.0-2"));
+        assert_eq!(new_dir(home, "../up"), home.join("_up"));
+        assert_eq!(new_dir(home, "current"), home.join("current-2"));
+        assert_eq!(new_dir(home, ""), home.join("version"));
+    }
+
+    #[test]
+    fn switch_keeps_the_replaced_version_as_previous() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        let first = version(home, "1");
+        switch(home, &first).unwrap();
+        assert_eq!(target(home, CURRENT), Some(PathBuf::from("1")));
+        assert_eq!(target(home, PREVIOUS), None);
+        assert_eq!(dir(home), home.join(CURRENT));
+        assert!(home.join(CURRENT).join(VERSION_FILE).is_file());
+
+        let second = version(home, "2");
+        switch(home, &second).unwrap();
+        assert_eq!(target(home, CURRENT), Some(PathBuf::from("2")));
+        assert_eq!(target(home, PREVIOUS), Some(PathBuf::from("1")));
+    }
+
+    #[test]
+    fn prune_keeps_current_previous_and_staging() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        for name in ["1", "2", "3"] {
+            let dir = version(home, name);
+            switch(home, &dir).unwrap();
+        }
+        fs::create_dir(home.join(".new-server")).unwrap();
+        prune(home).unwrap();
+
+        let mut left: Vec<_> = fs::read_dir(home)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, [".new-server", "2", "3", CURRENT, PREVIOUS]);
+    }
+
+    #[test]
+    fn point_removes_the_link_only() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        let dir = version(home, "1");
+        point(home, PREVIOUS, Some(Path::new("1"))).unwrap();
+        point(home, PREVIOUS, None).unwrap();
+        assert!(fs::symlink_metadata(home.join(PREVIOUS)).is_err());
+        assert!(dir.join(VERSION_FILE).is_file());
+    }
+
+    #[test]
+    fn legacy_installs_get_set_aside() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("server");
+        fs::create_dir(&home).unwrap();
+        fs::write(home.join(VERSION_FILE), "1").unwrap();
+        assert_eq!(dir(&home), home);
+
+        set_aside(&home).unwrap();
+        assert!(!home.exists());
+        assert!(legacy(&home).join(VERSION_FILE).is_file());
+        assert_eq!(dir(&home), home.join(CURRENT));
+    }
+}
@@ -1 +1,1005 @@
+mod cargo;
+mod composer;
+mod custom;
+mod dotnet;
+pub mod external;
+mod gem;
+mod ghcup;
+mod github;
+mod go;
+mod jdtls;
+mod layout;
+mod npm;
+mod opam;
+mod pip;
+mod process;
+pub mod remote;
+pub mod shim;
+mod spec;
+mod transaction;
+mod zls;
 
+pub use process::{alive, has_command, probe};
+use serde::{Deserialize, Serialize};
+pub use spec::Category;
+pub(crate) use spec::Meta;
+use spec::{DapAdapter, Spec};
+pub use transaction::recover;
+
+use crate::{
+    cache,
+    download::{self, ChecksumPolicy},
+    error::{Context, Error, Result},
+    extract::{self, Format},
+    manifest::{self, Record},
+    metadata, paths, version,
+    worker::{Report, Reporter},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Whether a server is present in its install directory
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Installed,
+    NotInstalled,
+    /// The binary exists but can't be executed
+    Broken,
+}
+
+/// Program a server needs at runtime besides its own files
+pub struct Runtime {
+    /// Name to look up on `PATH`, or a path
+    pub program: String,
+    /// How to get the program when it's missing
+    pub hint: &'static str,
+}
+
+/// Version of another tool the version of a server has to match, like the
+/// Zig compiler for zls
+pub struct Constraint {
+    /// Name of the tool, like `zig`
+    pub tool: String,
+    /// Version of it found on this machine, `None` when it isn't installed
+    pub found: Option<String>,
+    /// Leading components of both versions that must be the same, 2 for
+    /// `major.minor`
+    pub components: usize,
+}
+
+impl Constraint {
+    /// Whether `version` of the server works with the tool found
+    pub fn allows(&self, version: &str) -> bool {
+        let Some(found) = &self.found else {
+            return false;
+        };
+        let leading = |version: &str| -> Vec<String> {
+            version
+                .trim_start_matches('v')
+                .split(['.', '-', '+'])
+                .take(self.components)
+                .map(str::to_string)
+                .collect()
+        };
+        leading(version) == leading(found)
+    }
+
+    /// Why `version` of the server `name` can't be installed
+    fn refuse(&self, name: &str, version: &str) -> Error {
+        match &self.found {
+            Some(found) => Error::NotFound(format!(
+                "{} {} doesn't work with {} {}, only versions starting with {} do",
+                name,
+                version,
+                self.tool,
+                found,
+                found
+                    .split(['.', '-', '+'])
+                    .take(self.components)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            )),
+            None => Error::MissingRuntime {
+                name: self.tool.clone(),
+                hint: format!("{} gets picked to match the installed {}", name, self.tool),
+            },
+        }
+    }
+}
+
+/// What installing a server would do, resolved without changing anything
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Plan {
+    pub version: String,
+    /// Download URL, or the package spec handed to the package manager
+    pub source: String,
+    /// Directory the server gets installed into
+    pub dir: PathBuf,
+    /// Size of the download in bytes, when the source publishes it
+    pub size: Option<u64>,
+    /// Programs the install runs, which must be on `PATH`
+    pub requires: Vec<String>,
+    /// SHA-256 the download must have
+    pub sha256: Option<String>,
+}
+
+impl Plan {
+    /// Labelled lines describing the plan to the user
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![
+            ("version", self.version.clone()),
+            ("source", self.source.clone()),
+            ("into", self.dir.display().to_string()),
+        ];
+        if let Some(size) = self.size {
+            lines.push(("size", download::format_bytes(size)));
+        }
+        if !self.requires.is_empty() {
+            lines.push(("needs", self.requires.join(", ")));
+        }
+        lines
+    }
+
+    /// Entries of `requires` with nothing on `PATH`, an entry like `npm or
+    /// pnpm` being there when either is
+    pub fn missing(&self) -> Vec<String> {
+        self.requires
+            .iter()
+            .filter(|program| !program.split(" or ").any(process::on_path))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A language server that can be installed for one or more languages
+pub trait Server: Send + Sync {
+    /// Registry data shared by every backend
+    fn meta(&self) -> &Meta;
+
+    /// Name of the server, also used as its install directory
+    fn name(&self) -> &str {
+        &self.meta().name
+    }
+
+    /// Languages served by this server
+    fn languages(&self) -> &[String] {
+        &self.meta().languages
+    }
+
+    fn description(&self) -> &str {
+        &self.meta().description
+    }
+
+    fn category(&self) -> Category {
+        self.meta().category
+    }
+
+    /// Other names the server gets searched by
+    fn aliases(&self) -> &[String] {
+        &self.meta().aliases
+    }
+
+    /// Other names of the languages it serves, by language
+    fn language_aliases(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.meta().language_aliases
+    }
+
+    /// Files telling a project is written in one of its languages, by
+    /// language
+    fn project_files(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.meta().project_files
+    }
+
+    /// Kind of source the server gets installed from, like `npm`
+    fn backend(&self) -> &'static str;
+
+    /// Page to learn more about the server
+    fn homepage(&self) -> Option<String> {
+        self.meta().homepage.clone()
+    }
+
+    /// Source repository, when it's known
+    fn repository(&self) -> Option<String> {
+        self.meta().repository.clone()
+    }
+
+    /// Page listing the releases with what changed in each
+    fn releases(&self) -> Option<String> {
+        let repository = self.repository()?;
+        Some(format!("{}/releases", repository.trim_end_matches('/')))
+    }
+
+    /// Path of the executable once installed
+    fn bin(&self) -> PathBuf;
+
+    /// Arguments passed to `bin()` by editors starting the server
+    fn args(&self) -> &[String] {
+        &self.meta().args
+    }
+
+    /// Name of the server in nvim-lspconfig, when it has one
+    fn lspconfig(&self) -> Option<&str> {
+        self.meta().lspconfig.as_deref()
+    }
+
+    /// VS Code setting pointing its extension at `bin()`
+    fn vscode(&self) -> Option<&str> {
+        self.meta().vscode.as_deref()
+    }
+
+    /// Name of the formatter in conform.nvim, when it has one
+    fn conform(&self) -> Option<&str> {
+        self.meta().conform.as_deref()
+    }
+
+    /// Name of the linter in nvim-lint, when it has one
+    fn nvim_lint(&self) -> Option<&str> {
+        self.meta().nvim_lint.as_deref()
+    }
+
+    /// How nvim-dap starts the debug adapter, when it can
+    fn dap(&self) -> Option<&DapAdapter> {
+        self.meta().dap.as_ref()
+    }
+
+    /// Initialization options editors should send the server, with paths
+    /// into its install directory filled in
+    fn init_options(&self) -> Option<serde_json::Value> {
+        let mut options = self.meta().init_options.clone()?;
+        fill_dir(&mut options, &self.dir().display().to_string());
+        Some(options)
+    }
+
+    /// Tool installed on the machine the version of the server has to
+    /// match, which installs and updates stick to
+    fn constraint(&self) -> Option<Constraint> {
+        None
+    }
+
+    /// Packages installed next to the server that it needs to work, with
+    /// the file showing each one is there
+    fn companions(&self) -> Vec<(String, PathBuf)> {
+        Vec::new()
+    }
+
+    /// Resolve the source of `version`, or of the latest version, only
+    /// reading from the network
+    fn plan(&self, version: Option<&str>) -> Result<Plan>;
+
+    /// Install the server into `dir()` the way `plan` says.
+    ///
+    /// A previous install must keep working until the new one is complete.
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()>;
+
+    /// Download the latest version of the server into `dir()`
+    fn install(&self, report: &Reporter) -> Result<()> {
+        self.execute(&planned(self, None)?, report)
+    }
+
+    /// Install `version` and hold the server at it, so updates leave it
+    /// alone until the latest version gets installed again
+    fn pin(&self, version: &str, report: &Reporter) -> Result<()> {
+        self.execute(&planned(self, Some(version))?, report)?;
+        manifest::hold(self.name())
+    }
+
+    /// Most recent versions published upstream, newest first
+    fn versions(&self) -> Result<Vec<String>> {
+        Err(Error::Unsupported(format!(
+            "{} can't list its versions",
+            self.name()
+        )))
+    }
+
+    /// Whether the installed version got pinned with `pin()`
+    fn is_held(&self) -> bool {
+        manifest::get(self.name()).is_some_and(|record| record.held)
+    }
+
+    /// Latest version published upstream
+    fn latest_version(&self) -> Result<String>;
+
+    /// Version recorded by the last install, in the manifest or else in the
+    /// install directory
+    fn installed_version(&self) -> Option<String> {
+        if let Some(record) = manifest::get(self.name()) {
+            return Some(record.version);
+        }
+        fs::read_to_string(self.dir().join(VERSION_FILE))
+            .ok()
+            .map(|version| version.trim().to_string())
+    }
+
+    /// When the server was last installed or had a task run on it, as
+    /// RFC 3339
+    fn last_used(&self) -> Option<String> {
+        manifest::get(self.name()).map(|record| record.last_used().to_string())
+    }
+
+    /// Bytes taken by the install, once measured
+    fn disk_size(&self) -> Option<u64> {
+        manifest::get(self.name()).and_then(|record| record.disk_size)
+    }
+
+    /// First line printed by the executable when it got verified
+    fn reported_version(&self) -> Option<String> {
+        manifest::get(self.name()).and_then(|record| record.reported_version)
+    }
+
+    /// Run the executable with the arguments from the registry, `--version`
+    /// by default, to check it works on this machine. Gives the first line
+    /// it printed, or `None` for servers declaring `verify = "none"`.
+    fn verify(&self) -> Result<Option<String>> {
+        verify(self.meta(), &self.bin())
+    }
+
+    /// Install the latest version if it's newer than the installed one,
+    /// returning the version that got installed
+    fn update(&self, report: &Reporter) -> Result<Option<String>> {
+        if let Some(bin) = self.external() {
+            return Err(untouchable(self.name(), &bin, "update"));
+        }
+        if self.is_held() {
+            return Err(Error::Held {
+                name: self.name().to_string(),
+                version: self.installed_version().unwrap_or_default(),
+            });
+        }
+        let latest = self.latest_version()?;
+        if let Some(installed) = self.installed_version() {
+            if !version::is_newer(&latest, &installed) {
+                return Ok(None);
+            }
+        }
+
+        self.install(report)?;
+        Ok(self.installed_version())
+    }
+
+    /// Directory holding the installs kept of this server, one per version
+    fn home(&self) -> PathBuf {
+        paths::root().join(self.name())
+    }
+
+    /// Directory holding everything installed for this server, the version
+    /// in use among the ones in `home()`
+    fn dir(&self) -> PathBuf {
+        layout::dir(&self.home())
+    }
+
+    fn status(&self) -> Status {
+        recorded_status(self)
+    }
+
+    fn is_installed(&self) -> bool {
+        self.status() == Status::Installed
+    }
+
+    /// Command uninstalling runs on an environment the installer doesn't
+    /// own, for servers installed into one
+    fn uninstall_command(&self) -> Option<String> {
+        None
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        if let Some(bin) = self.external() {
+            return Err(untouchable(self.name(), &bin, "remove"));
+        }
+        shim::remove(self)?;
+        remove_dir(&self.home())
+    }
+
+    /// Executable of an install made outside the installer and adopted
+    fn external(&self) -> Option<PathBuf> {
+        manifest::get(self.name())
+            .filter(|record| record.backend == external::BACKEND)
+            .map(|record| record.bin)
+    }
+
+    /// Path editors should start the server from: the adopted executable,
+    /// its entry in the bin directory, or `bin()` for installs made before
+    /// there was one
+    fn launcher(&self) -> PathBuf {
+        if let Some(bin) = self.external() {
+            return bin;
+        }
+        let shim = shim::path(self);
+        if fs::symlink_metadata(&shim).is_ok() {
+            shim
+        } else {
+            self.bin()
+        }
+    }
+
+    /// Where the install replaced by the last update is kept
+    fn previous_dir(&self) -> PathBuf {
+        self.home().join(layout::PREVIOUS)
+    }
+
+    /// Version the last update replaced, which `rollback()` goes back to
+    fn previous_version(&self) -> Option<String> {
+        if let Some(record) = manifest::previous(self.name()) {
+            return Some(record.version);
+        }
+        fs::read_to_string(self.previous_dir().join(VERSION_FILE))
+            .ok()
+            .map(|version| version.trim().to_string())
+    }
+
+    /// Go back to the install replaced by the last update, which becomes the
+    /// one to roll back to in turn. Returns the version now installed.
+    ///
+    /// The `current` and `previous` links of `home()` get swapped, the
+    /// paths editors know going through `current`.
+    fn rollback(&self) -> Result<String> {
+        roll_back(self)
+    }
+
+    /// Whether the server can be installed on the current platform
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Whether the server comes from the user's `servers.toml`
+    fn is_custom(&self) -> bool {
+        false
+    }
+
+    /// Interpreter running the server, when it isn't a native executable
+    fn runtime(&self) -> Option<Runtime> {
+        None
+    }
+
+    /// Extra facts about the install worth showing to the user
+    fn details(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Whether the registry tells how to build the server from source
+    fn can_build(&self) -> bool {
+        false
+    }
+
+    /// Programs building the server from source needs on `PATH`
+    fn build_tools(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Build the latest release from source and install it, for platforms
+    /// nothing prebuilt is published for
+    fn build(&self, _report: &Reporter) -> Result<()> {
+        Err(Error::Unsupported(format!(
+            "{} can't be built from source",
+            self.name()
+        )))
+    }
+
+    /// Install from `file`, what the install would download fetched by
+    /// other means. With `verify`, it must match the checksum published for
+    /// its version when one is known.
+    fn install_file(&self, _file: &Path, _verify: bool, _report: &Reporter) -> Result<()> {
+        Err(Error::Unsupported(format!(
+            "{} gets installed by {}, only release downloads can be installed from a file",
+            self.name(),
+            self.backend()
+        )))
+    }
+}
+
+/// File inside a server's directory recording the installed version
+const VERSION_FILE: &str = "version";
+
+thread_local! {
+    /// URL and SHA-256 of the last download of the install running on this
+    /// thread, for its manifest record
+    static DOWNLOADED: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+    /// Whether the install running on this thread got built from source
+    static BUILT: Cell<bool> = const { Cell::new(false) };
+    /// Whether the install running on this thread came from a local file
+    static FROM_FILE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Versions listed by `Server::versions()`
+const VERSION_COUNT: usize = 20;
+
+/// How long a freshly installed server gets to answer its verification
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a server verified as `alive` has to keep running
+pub const ALIVE_WAIT: Duration = Duration::from_secs(3);
+
+/// Server definitions compiled into the binary, checked by `build.rs`
+const REGISTRY: &str = include_str!("registry.json");
+
+/// Every server known to the installer: the embedded registry with the
+/// servers only the fetched one has, merged with the user's custom servers,
+/// which replace the servers with the same name.
+///
+/// Also returns the problems found in the fetched registry and the custom
+/// servers file.
+pub fn registry() -> (Vec<Arc<dyn Server>>, Vec<String>) {
+    let specs: Vec<Spec> =
+        serde_json::from_str(REGISTRY).expect("the embedded registry is validated at build time");
+    let (fetched, mut warnings) = remote::load();
+    let (custom, custom_warnings) = custom::load(&paths::config_dir().join("servers.toml"));
+    warnings.extend(custom_warnings);
+
+    let mut servers = builtin(specs);
+    servers.extend(builtin(fetched));
+    for server in custom {
+        servers.retain(|builtin| builtin.name() != server.name());
+        servers.push(Arc::new(server));
+    }
+
+    (servers, warnings)
+}
+
+fn builtin(specs: Vec<Spec>) -> Vec<Arc<dyn Server>> {
+    specs
+        .into_iter()
+        .map(|spec| -> Arc<dyn Server> {
+            match spec {
+                Spec::GithubRelease(server) => Arc::new(server),
+                Spec::Npm(server) => Arc::new(server),
+                Spec::Pip(server) => Arc::new(server),
+                Spec::Cargo(server) => Arc::new(server),
+                Spec::Go(server) => Arc::new(server),
+                Spec::Composer(server) => Arc::new(server),
+                Spec::Jdtls(server) => Arc::new(server),
+                Spec::Ghcup(server) => Arc::new(server),
+                Spec::Opam(server) => Arc::new(server),
+                Spec::Gem(server) => Arc::new(server),
+                Spec::DotnetTool(server) => Arc::new(server),
+                Spec::Zls(server) => Arc::new(server),
+            }
+        })
+        .collect()
+}
+
+/// The `VERSION_COUNT` newest releases in `versions`, prereleases left out
+fn newest(mut versions: Vec<String>) -> Vec<String> {
+    versions.retain(|version| !version.contains('-'));
+    versions.sort_by(|a, b| version::compare(b, a));
+    versions.truncate(VERSION_COUNT);
+    versions
+}
+
+/// Replace `{dir}` by `dir` in every string of `value`
+fn fill_dir(value: &mut serde_json::Value, dir: &str) {
+    match value {
+        serde_json::Value::String(text) => *text = text.replace("{dir}", dir),
+        serde_json::Value::Array(values) => {
+            values.iter_mut().for_each(|value| fill_dir(value, dir))
+        }
+        serde_json::Value::Object(map) => map.values_mut().for_each(|value| fill_dir(value, dir)),
+        _ => {}
+    }
+}
+
+/// Swap the `current` and `previous` links of the home of `server`, along
+/// with its records, returning the version now installed
+fn roll_back<S: Server + ?Sized>(server: &S) -> Result<String> {
+    if let Some(bin) = server.external() {
+        return Err(untouchable(server.name(), &bin, "roll back"));
+    }
+    let home = server.home();
+    let Some(previous) =
+        layout::target(&home, layout::PREVIOUS).filter(|previous| home.join(previous).is_dir())
+    else {
+        return Err(Error::NotFound(format!(
+            "{} has no previous version to roll back to",
+            server.name()
+        )));
+    };
+    let current = layout::target(&home, layout::CURRENT);
+    layout::point(&home, layout::CURRENT, Some(&previous))?;
+    layout::point(&home, layout::PREVIOUS, current.as_deref())?;
+    manifest::roll_back(server.name())?;
+    shim::write(server)?;
+    Ok(server.installed_version().unwrap_or_default())
+}
+
+/// Whether the executable recorded for `server`, or else its `bin()`, is
+/// there and can be run
+fn recorded_status<S: Server + ?Sized>(server: &S) -> Status {
+    let bin = manifest::get(server.name()).map_or_else(|| server.bin(), |record| record.bin);
+    match fs::metadata(bin) {
+        Ok(metadata) if metadata.is_file() && is_executable(&metadata) => Status::Installed,
+        Ok(_) => Status::Broken,
+        Err(_) => Status::NotInstalled,
+    }
+}
+
+/// Refusal to `task` the adopted server `name`, whose executable is `bin`
+fn untouchable(name: &str, bin: &Path, task: &str) -> Error {
+    Error::Unsupported(format!(
+        "{} at {} was installed outside lsp_installer, {} it the way it was installed",
+        name,
+        bin.display(),
+        task
+    ))
+}
+
+/// Run `bin` the way `Server::verify()` describes, for the server `meta`
+/// is the registry entry of
+fn verify(meta: &Meta, bin: &Path) -> Result<Option<String>> {
+    let args: Vec<&str> = match meta.verify.as_deref() {
+        Some("none") => return Ok(None),
+        Some("alive") => {
+            return match alive(bin, &[], ALIVE_WAIT)? {
+                None => Ok(None),
+                Some(status) => Err(Error::Command {
+                    command: format!("`{}`", bin.display()),
+                    status: format!(
+                        "{} without a client, the server doesn't work on this machine",
+                        status
+                    ),
+                    output: String::new(),
+                }),
+            }
+        }
+        Some(args) => args.split_whitespace().collect(),
+        None => vec!["--version"],
+    };
+    let command = format!("{} {}", bin.display(), args.join(" "));
+    let probe = probe(bin, &args, VERIFY_TIMEOUT)?;
+    match probe.status {
+        Some(status) if status.success() => Ok(Some(
+            probe
+                .stdout
+                .lines()
+                .chain(probe.stderr.lines())
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or_default()
+                .to_string(),
+        )),
+        Some(status) => Err(Error::Command {
+            command: format!("`{}`", command),
+            status: format!("{}, the server doesn't work on this machine", status),
+            output: probe.stderr.trim_end().to_string(),
+        }),
+        None => Err(Error::Command {
+            command: format!("`{}`", command),
+            status: format!("didn't exit within {}s", VERIFY_TIMEOUT.as_secs()),
+            output: String::new(),
+        }),
+    }
+}
+
+/// Record the version that just got installed into `dir`
+fn write_version(dir: &Path, version: &str) -> Result<()> {
+    let path = dir.join(VERSION_FILE);
+    fs::write(&path, version).at(&path)
+}
+
+/// Build a fresh install of `server` in a staging directory of its home,
+/// then move it to a version directory and switch to it. The previous
+/// install comes back if `build` fails or the new one doesn't pass
+/// `Server::verify()`.
+///
+/// `build` returns the version it installed.
+fn install_staged(
+    server: &dyn Server,
+    report: &Reporter,
+    build: impl FnOnce(&Path) -> Result<String>,
+) -> Result<()> {
+    let home = server.home();
+    // Servers sharing a home may install side by side
+    let staging = home.join(format!(".new-{}", server.name()));
+
+    transact(server, Some(&staging), || {
+        layout::set_aside(&home)?;
+        remove_dir(&staging)?;
+        fs::create_dir_all(&staging).at(&staging)?;
+        DOWNLOADED.take();
+        BUILT.take();
+        FROM_FILE.take();
+        let version = build(&staging)?;
+        write_version(&staging, &version)?;
+
+        let dir = layout::new_dir(&home, &version);
+        fs::rename(&staging, &dir).at(&dir)?;
+        layout::switch(&home, &dir)?;
+        check_install(server, &version, report)
+    })
+}
+
+/// Run `install`, which builds the new install of `server` in `staging`,
+/// switches to it and ends with `check_install()`, as a transaction in the
+/// journal: it's rolled back when `install` fails, or on the next start
+/// when the process dies before it's committed
+fn transact(
+    server: &dyn Server,
+    staging: Option<&Path>,
+    install: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    let transaction = transaction::begin(server, staging)?;
+    match install() {
+        Ok(()) => transaction::finish(&transaction),
+        Err(err) => {
+            let _ = transaction::roll_back(server, &transaction);
+            Err(err)
+        }
+    }
+}
+
+/// `dir` with `suffix` appended to its name
+fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+    let mut path = dir.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Verify the fresh install of `version` of `server`, point its bin
+/// directory entry at it, then commit it by recording it in the manifest.
+/// The record of the install it replaces is out meanwhile, for
+/// `transact()` to put back.
+fn check_install(server: &dyn Server, version: &str, report: &Reporter) -> Result<()> {
+    // A record left in place would point `bin()` at the previous version
+    let previous = manifest::remove(server.name())?;
+    let reported = server.verify()?;
+    if let Some(line) = &reported {
+        report.send(Report::Log(format!("Verified {}: {}", server.name(), line)));
+    }
+    if let Err(err) = shim::write(server) {
+        report.send(Report::Warning(format!(
+            "{} couldn't be added to {}: {}",
+            server.name(),
+            paths::bin_dir().display(),
+            err
+        )));
+    }
+
+    let (url, sha256) = DOWNLOADED.take().unzip();
+    manifest::replace(
+        server.name(),
+        Record {
+            version: version.to_string(),
+            backend: server.backend().to_string(),
+            installed_at: manifest::now(),
+            url,
+            sha256,
+            bin: server.bin(),
+            reported_version: reported,
+            held: false,
+            used_at: None,
+            disk_size: None,
+            built_from_source: BUILT.take(),
+            from_file: FROM_FILE.take(),
+        },
+        previous,
+    )
+}
+
+/// Turn the downloaded `archive` into the install's files, `bin` being where
+/// a single compressed or plain binary has to end up
+fn unpack(archive: &Path, dir: &Path, bin: &str) -> Result<()> {
+    if extract::extract(archive, dir)? {
+        return fs::remove_file(archive).at(archive);
+    }
+
+    let bin = dir.join(bin);
+    if extract::detect(archive).at(archive)? == Format::Gz {
+        extract::gunzip(archive, &bin)?;
+        fs::remove_file(archive).at(archive)
+    } else {
+        fs::rename(archive, &bin).at(&bin)
+    }
+}
+
+/// Plan of installing `version` of `server`, or its latest version, kept
+/// for when the network is unreachable. Offline, the plan kept last time
+/// gets used.
+///
+/// Either way the version has to fit the constraint of the server, if it
+/// has one.
+pub fn planned<S: Server + ?Sized>(server: &S, version: Option<&str>) -> Result<Plan> {
+    let plan = match server.plan(version) {
+        Ok(plan) => {
+            metadata::save_plan(server.name(), version, &plan);
+            plan
+        }
+        // Cached before a switch between the project and the global root
+        Err(err) if download::is_offline() => Plan {
+            dir: server.dir(),
+            ..metadata::plan(server.name(), version).ok_or(err)?
+        },
+        Err(err) => return Err(err),
+    };
+    match server.constraint() {
+        Some(constraint) if !constraint.allows(&plan.version) => {
+            Err(constraint.refuse(server.name(), &plan.version))
+        }
+        _ => Ok(plan),
+    }
+}
+
+/// Recent versions of `server` it can be installed at, the ones fitting its
+/// constraint when it has one
+pub fn compatible_versions(server: &dyn Server) -> Result<Vec<String>> {
+    let mut versions = server.versions()?;
+    if let Some(constraint) = server.constraint() {
+        versions.retain(|version| constraint.allows(version));
+        if versions.is_empty() {
+            return Err(Error::NotFound(format!(
+                "none of the recent versions of {} work with {} {}",
+                server.name(),
+                constraint.tool,
+                constraint.found.as_deref().unwrap_or("missing")
+            )));
+        }
+    }
+    Ok(versions)
+}
+
+/// Whether `server` can be installed without the network: it's custom, or
+/// its download is cached along with the plan to use it
+pub fn installable_offline(server: &dyn Server) -> bool {
+    server.is_custom()
+        || metadata::plan(server.name(), None)
+            .is_some_and(|plan| cache::contains(&plan.source, plan.sha256.as_deref()))
+}
+
+/// Download `url` into `dest`, reporting progress as it goes, and check it
+/// against the `expected` SHA-256. Without one the checksum policy decides
+/// whether the download can be used. With `cached`, a download kept from an
+/// earlier install is used instead, and a new one gets kept.
+fn fetch(
+    url: &str,
+    dest: &Path,
+    expected: Option<&str>,
+    cached: bool,
+    report: &Reporter,
+) -> Result<()> {
+    if let Some(digest) = cached
+        .then(|| cache::restore(url, expected, dest))
+        .flatten()
+    {
+        report.send(Report::Log(format!("Using the cached download of {}", url)));
+        DOWNLOADED.set(Some((url.to_string(), digest)));
+        return Ok(());
+    }
+    let digest = download::download(url, dest, &mut |done, total| {
+        report.send(Report::Progress { done, total });
+        report.check()
+    })?;
+    DOWNLOADED.set(Some((url.to_string(), digest.clone())));
+
+    let name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let problem = match (expected, download::checksum_policy()) {
+        (Some(expected), _) if expected.eq_ignore_ascii_case(&digest) => {
+            if cached {
+                cache::store(url, Some(expected), dest, &digest);
+            }
+            return Ok(());
+        }
+        (Some(expected), _) => Error::ChecksumMismatch {
+            file: name.to_string(),
+            expected: expected.to_string(),
+            actual: digest,
+        },
+        (None, ChecksumPolicy::Require) => Error::NoChecksum {
+            file: name.to_string(),
+        },
+        (None, ChecksumPolicy::Warn) => {
+            report.send(Report::Warning(format!(
+                "{} has no published checksum, it wasn't verified",
+                name
+            )));
+            if cached {
+                cache::store(url, None, dest, &digest);
+            }
+            return Ok(());
+        }
+        (None, ChecksumPolicy::Skip) => {
+            if cached {
+                cache::store(url, None, dest, &digest);
+            }
+            return Ok(());
+        }
+    };
+
+    let _ = fs::remove_file(dest);
+    Err(problem)
+}
+
+/// Remove `dir` and everything inside it, reporting entries that couldn't be
+/// removed instead of stopping at the first one
+fn remove_dir(dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let mut failures = Vec::new();
+    remove_tree(dir, &mut failures);
+
+    match failures.len() {
+        0 => Ok(()),
+        n => {
+            let (path, err) = &failures[0];
+            Err(Error::Io {
+                path: Some(dir.to_path_buf()),
+                source: io::Error::new(
+                    err.kind(),
+                    format!(
+                        "only partially removed, {} entries left (first: {}: {})",
+                        n,
+                        path.display(),
+                        err
+                    ),
+                ),
+            })
+        }
+    }
+}
+
+/// Remove `path` and everything below it, carrying on past entries that fail
+fn remove_tree(path: &Path, failures: &mut Vec<(PathBuf, io::Error)>) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => return failures.push((path.to_path_buf(), err)),
+    };
+
+    let before = failures.len();
+    let res = if metadata.is_symlink() {
+        // Links to directories are directories to Windows
+        fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+    } else if metadata.is_dir() {
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => remove_tree(&entry.path(), failures),
+                        Err(err) => failures.push((path.to_path_buf(), err)),
+                    }
+                }
+            }
+            Err(err) => failures.push((path.to_path_buf(), err)),
+        }
+        if failures.len() > before {
+            // The directory can't be empty, the failure has been recorded already
+            return;
+        }
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    };
+
+    if let Err(err) = res {
+        failures.push((path.to_path_buf(), err));
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).at(path)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use super::{
+    check_install, newest, process, remove_dir, shim,
+    spec::{Meta, Npm},
+    Plan, Runtime, Server, Status,
+};
+use crate::{
+    download,
+    error::{Error, Result},
+    manifest::{self, Record},
+    paths,
+    worker::{Report, Reporter},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+/// Node.js package managers able to install into a directory, by preference
+const MANAGERS: &[&str] = &["npm", "pnpm", "yarn"];
+
+/// Held by the tasks of servers sharing their install directory, which run
+/// one at a time: each finds the package the one before installed, and
+/// none switches the directory while another is using it
+static SHARED: Mutex<()> = Mutex::new(());
+
+/// First package manager that can be found on `PATH`
+fn package_manager() -> Result<&'static str> {
+    MANAGERS
+        .iter()
+        .copied()
+        .find(|manager| process::has_command(manager, &["--version"]))
+        .ok_or_else(|| Error::MissingRuntime {
+            name: String::from("Node.js"),
+            hint: String::from("install it from https://nodejs.org, npm comes with it"),
+        })
+}
+
+impl Npm {
+    /// `package.json` of `version`, or of the `latest` tag, as published on
+    /// the registry
+    fn published(&self, version: Option<&str>) -> Result<serde_json::Value> {
+        let response = download::get(&format!(
+            "https://registry.npmjs.org/{}/{}",
+            self.package,
+            version.unwrap_or("latest")
+        ))?;
+        serde_json::from_reader(response.into_reader()).map_err(Error::from)
+    }
+
+    /// Other installed servers using the shared install directory
+    fn dependents(&self) -> Vec<String> {
+        let dir = self.dir();
+        manifest::all()
+            .into_iter()
+            .filter(|(name, record)| *name != self.meta.name && record.bin.starts_with(&dir))
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// Record `version` for the servers in `names`, which their shared install
+/// directory just switched to, keeping what they had to roll back to
+fn follow(names: &[String], version: &str) -> Result<()> {
+    for name in names {
+        let Some(record) = manifest::get(name).filter(|record| record.version != version) else {
+            continue;
+        };
+        let switched = Record {
+            version: version.to_string(),
+            installed_at: manifest::now(),
+            reported_version: None,
+            disk_size: None,
+            ..record.clone()
+        };
+        manifest::replace(name, switched, Some(record))?;
+    }
+    Ok(())
+}
+
+/// Version of `package` installed in `dir`
+fn installed_package(dir: &Path, package: &str) -> Result<String> {
+    let manifest = dir.join("node_modules").join(package).join("package.json");
+    let manifest: serde_json::Value = serde_json::from_slice(&fs::read(manifest)?)?;
+    manifest["version"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidResponse(String::from("package has no version")))
+}
+
+impl Server for Npm {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "npm"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://www.npmjs.com/package/{}", self.package)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.dir().join("node_modules").join(".bin").join(&self.bin)
+    }
+
+    fn home(&self) -> PathBuf {
+        paths::root().join(self.share.as_deref().unwrap_or(&self.meta.name))
+    }
+
+    fn companions(&self) -> Vec<(String, PathBuf)> {
+        let modules = self.dir().join("node_modules");
+        self.companions
+            .iter()
+            .map(|package| (package.clone(), modules.join(package).join("package.json")))
+            .collect()
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: String::from("node"),
+            hint: "install Node.js from https://nodejs.org",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let manifest = self.published(version)?;
+        let version = manifest["version"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidResponse(String::from("package has no version")))?;
+        Ok(Plan {
+            source: format!("{}@{}", self.package, version),
+            version: version.to_string(),
+            dir: self.dir(),
+            // Unpacked, dependencies not included
+            size: manifest["dist"]["unpackedSize"].as_u64(),
+            requires: vec![String::from("node"), MANAGERS.join(" or ")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        let manager = package_manager()?;
+        let _shared = self.share.is_some().then(|| SHARED.lock());
+        let dependents = self.dependents();
+
+        // Another server of the package installed it already
+        if self.share.is_some()
+            && !dependents.is_empty()
+            && self.bin().is_file()
+            && installed_package(&self.dir(), &self.package).is_ok_and(|v| v == plan.version)
+        {
+            report.send(Report::Log(format!(
+                "Using {} {} installed along with {}",
+                self.package,
+                plan.version,
+                dependents.join(", ")
+            )));
+            return super::transact(self, None, || check_install(self, &plan.version, report));
+        }
+
+        super::install_staged(self, report, |staging| {
+            let packages: Vec<&str> = std::iter::once(self.package.as_str())
+                .chain(self.companions.iter().map(String::as_str))
+                .collect();
+            report.send(Report::Log(format!(
+                "Running {} to install {}",
+                manager,
+                packages.join(" and ")
+            )));
+
+            // A cache per server, so installs running side by side never
+            // write to the same one
+            let cache = paths::cache_dir().join(manager).join(&self.meta.name);
+            let mut command = Command::new(manager);
+            match manager {
+                "npm" => command
+                    .arg("install")
+                    .arg("--prefix")
+                    .arg(staging)
+                    .arg("--cache")
+                    .arg(cache),
+                "pnpm" => command
+                    .arg("add")
+                    .arg("--dir")
+                    .arg(staging)
+                    .arg("--store-dir")
+                    .arg(cache),
+                _ => command
+                    .arg("add")
+                    .arg("--cwd")
+                    .arg(staging)
+                    .arg("--cache-folder")
+                    .arg(cache),
+            };
+            // Latest versions of the companions, they're only required to
+            // be there
+            process::run_logged(command.arg(&plan.source).args(&self.companions), report)?;
+            for (package, file) in self.companions() {
+                let file = staging.join(file.strip_prefix(self.dir()).unwrap_or(&file));
+                if !file.is_file() {
+                    return Err(Error::NotFound(format!(
+                        "{} didn't install {}",
+                        manager, package
+                    )));
+                }
+            }
+            installed_package(staging, &self.package)
+        })?;
+        follow(&dependents, &plan.version)
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.plan(None).map(|plan| plan.version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        // The abbreviated document leaves out the readme of every version
+        let response = download::send(
+            download::request(&format!("https://registry.npmjs.org/{}", self.package))?
+                .set("Accept", "application/vnd.npm.install-v1+json"),
+        )?;
+        let package: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+        let versions = package["versions"]
+            .as_object()
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default();
+        Ok(newest(versions))
+    }
+
+    fn status(&self) -> Status {
+        // Installed along with another server isn't installed for this one
+        // until it gets its own record
+        if self.share.is_some() && manifest::get(&self.meta.name).is_none() {
+            return Status::NotInstalled;
+        }
+        super::recorded_status(self)
+    }
+
+    fn rollback(&self) -> Result<String> {
+        if self.share.is_none() {
+            return super::roll_back(self);
+        }
+        let _shared = SHARED.lock();
+        let mut names = self.dependents();
+        super::roll_back(self)?;
+        // Installed along with the others after they got updated, it had no
+        // record of its own to go back to
+        if manifest::get(&self.meta.name).is_none() {
+            if let Some(record) = manifest::previous(&self.meta.name) {
+                manifest::insert(&self.meta.name, record)?;
+            }
+        }
+        let version = installed_package(&self.dir(), &self.package)?;
+        names.push(self.meta.name.clone());
+        follow(&names, &version)?;
+        Ok(version)
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        if let Some(bin) = self.external() {
+            return Err(super::untouchable(self.name(), &bin, "remove"));
+        }
+        let _shared = self.share.is_some().then(|| SHARED.lock());
+        shim::remove(self)?;
+        if self.share.is_some() && !self.dependents().is_empty() {
+            return Ok(());
+        }
+        remove_dir(&self.home())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::servers::{layout, registry};
+    use std::sync::Arc;
+
+    fn server(name: &str) -> Arc<dyn Server> {
+        paths::test_root();
+        let (servers, _) = registry();
+        servers
+            .into_iter()
+            .find(|server| server.name() == name)
+            .unwrap()
+    }
+
+    fn record(server: &dyn Server, version: &str) -> Record {
+        Record {
+            version: version.to_string(),
+            backend: String::from("npm"),
+            installed_at: String::from("2026-01-01T00:00:00Z"),
+            url: None,
+            sha256: None,
+            bin: server.bin(),
+            reported_version: None,
+            held: false,
+            used_at: None,
+            disk_size: None,
+            built_from_source: false,
+            from_file: false,
+        }
+    }
+
+    #[test]
+    fn rolling_back_a_shared_install_moves_every_server_of_it() {
+        let json = server("vscode-json-language-server");
+        let css = server("vscode-css-language-server");
+        let home = json.home();
+        assert_eq!(home, css.home());
+        for version in ["4.9.0", "4.10.0"] {
+            let dir = home.join(version);
+            let package = dir
+                .join("node_modules")
+                .join("vscode-langservers-extracted");
+            fs::create_dir_all(&package).unwrap();
+            let manifest = format!(r#"{{"version": "{}"}}"#, version);
+            fs::write(package.join("package.json"), manifest).unwrap();
+            layout::switch(&home, &dir).unwrap();
+        }
+        // Updated, then installed along with it
+        let (name, other) = (json.name(), css.name());
+        manifest::replace(
+            name,
+            record(json.as_ref(), "4.10.0"),
+            Some(record(json.as_ref(), "4.9.0")),
+        )
+        .unwrap();
+        manifest::insert(other, record(css.as_ref(), "4.10.0")).unwrap();
+
+        assert_eq!(css.rollback().unwrap(), "4.9.0");
+        assert_eq!(manifest::get(name).unwrap().version, "4.9.0");
+        assert_eq!(manifest::get(other).unwrap().version, "4.9.0");
+
+        assert_eq!(json.rollback().unwrap(), "4.10.0");
+        assert_eq!(manifest::get(name).unwrap().version, "4.10.0");
+        assert_eq!(manifest::get(other).unwrap().version, "4.10.0");
+        assert_eq!(manifest::previous(other).unwrap().version, "4.9.0");
+    }
+}
@@ -0,0 +1,180 @@
+//! OCaml packages installed with opam into the current switch, an
+//! environment the installer doesn't own: whether the server is installed
+//! is what opam says, and uninstalling removes the package from the switch.
+//! The install directory only links to the executable in the switch.
+
+use super::{
+    newest,
+    process::{self, run, run_logged},
+    remove_dir, shim,
+    spec::{Meta, Opam},
+    Plan, Server, Status,
+};
+use crate::{
+    error::{Context, Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{fs, path::PathBuf, process::Command};
+
+impl Opam {
+    /// Fail with what to do about it when opam isn't installed
+    fn check_opam(&self) -> Result<()> {
+        if process::on_path("opam") {
+            return Ok(());
+        }
+        Err(Error::MissingRuntime {
+            name: String::from("opam"),
+            hint: String::from(
+                "install opam from https://opam.ocaml.org/doc/Install.html and run `opam init`",
+            ),
+        })
+    }
+
+    /// Version of the package installed in the current switch, `None` when
+    /// it isn't or opam can't tell
+    fn switch_version(&self) -> Option<String> {
+        if !process::on_path("opam") {
+            return None;
+        }
+        let listed = run(Command::new("opam").args([
+            "list",
+            "--installed",
+            "--short",
+            "--columns=version",
+            "--color=never",
+            &self.package,
+        ]))
+        .ok()?;
+        listed
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+    }
+}
+
+impl Server for Opam {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "opam"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(
+            homepage.unwrap_or_else(|| format!("https://opam.ocaml.org/packages/{}", self.package)),
+        )
+    }
+
+    fn bin(&self) -> PathBuf {
+        let bin = self.dir().join(&self.bin);
+        if cfg!(windows) {
+            bin.with_extension("cmd")
+        } else {
+            bin
+        }
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => self.latest_version()?,
+        };
+        Ok(Plan {
+            source: format!("{}.{}", self.package, version),
+            version,
+            dir: self.dir(),
+            size: None,
+            requires: vec![String::from("opam")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        self.check_opam()?;
+        super::install_staged(self, report, |staging| {
+            report.send(Report::Log(format!("Running opam install {}", plan.source)));
+            run_logged(
+                Command::new("opam").args(["install", "--yes", &plan.source]),
+                report,
+            )?;
+
+            let dir = run(Command::new("opam").args(["var", "bin"]))?;
+            let target = PathBuf::from(dir.trim()).join(format!(
+                "{}{}",
+                self.bin,
+                std::env::consts::EXE_SUFFIX
+            ));
+            if !target.is_file() {
+                return Err(Error::NotFound(format!(
+                    "opam installed {} but the switch has no {}",
+                    plan.source, self.bin
+                )));
+            }
+            let link = staging.join(self.bin().file_name().unwrap_or_default());
+            shim::create(&target, &link).at(&link)?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.versions()?.into_iter().next().ok_or_else(|| {
+            Error::InvalidResponse(format!("opam knows no version of {}", self.package))
+        })
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        self.check_opam()?;
+        // Separated by spaces, quoted by some versions of opam
+        let versions = run(Command::new("opam").args([
+            "show",
+            "--field=all-versions",
+            "--color=never",
+            &self.package,
+        ]))?;
+        Ok(newest(
+            versions
+                .split_whitespace()
+                .map(|version| version.trim_matches('"').to_string())
+                .collect(),
+        ))
+    }
+
+    fn installed_version(&self) -> Option<String> {
+        self.switch_version()
+    }
+
+    fn status(&self) -> Status {
+        if self.switch_version().is_none() {
+            return Status::NotInstalled;
+        }
+        // Dangling once the switch it got installed from is gone
+        match fs::metadata(self.bin()) {
+            Ok(metadata) if metadata.is_file() => Status::Installed,
+            _ => Status::Broken,
+        }
+    }
+
+    fn uninstall_command(&self) -> Option<String> {
+        Some(format!("opam remove {}", self.package))
+    }
+
+    fn rollback(&self) -> Result<String> {
+        Err(Error::Unsupported(format!(
+            "a switch holds one version of {}, pin the one to go back to",
+            self.package
+        )))
+    }
+
+    fn uninstall(&self) -> Result<()> {
+        self.check_opam()?;
+        if self.switch_version().is_some() {
+            run(Command::new("opam").args(["remove", "--yes", &self.package]))?;
+        }
+        shim::remove(self)?;
+        remove_dir(&self.home())
+    }
+}
@@ -0,0 +1,173 @@
+use super::{
+    check_install, layout, newest,
+    process::{self, run, run_logged},
+    spec::{Meta, Pip},
+    transact, write_version, Plan, Runtime, Server,
+};
+use crate::{
+    download,
+    error::{Context, Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+impl Pip {
+    fn venv(&self) -> PathBuf {
+        self.dir().join("venv")
+    }
+
+    /// PyPI metadata of `version`, or of the latest version
+    fn project(&self, version: Option<&str>) -> Result<serde_json::Value> {
+        let url = match version {
+            Some(version) => format!("https://pypi.org/pypi/{}/{}/json", self.package, version),
+            None => format!("https://pypi.org/pypi/{}/json", self.package),
+        };
+        let response = download::get(&url)?;
+        serde_json::from_reader(response.into_reader()).map_err(Error::from)
+    }
+
+    /// Create the virtualenv at `venv` and install `spec` into it, returning
+    /// the installed version
+    fn build(&self, python: &str, venv: &Path, spec: &str, report: &Reporter) -> Result<String> {
+        report.send(Report::Log(format!(
+            "Creating a virtualenv for {}",
+            self.meta.name
+        )));
+        run_logged(Command::new(python).arg("-m").arg("venv").arg(venv), report)?;
+
+        report.send(Report::Log(format!(
+            "Running pip to install {}",
+            self.package
+        )));
+        let python = venv_bin(venv).join("python");
+        run_logged(pip(&python).arg("install").arg(spec), report)?;
+
+        let info = run(pip(&python).arg("show").arg(&self.package))?;
+        info.lines()
+            .find_map(|line| line.strip_prefix("Version:"))
+            .map(|version| version.trim().to_string())
+            .ok_or_else(|| Error::InvalidResponse(String::from("pip reported no version")))
+    }
+}
+
+/// Scripts directory of a virtualenv
+fn venv_bin(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts")
+    } else {
+        venv.join("bin")
+    }
+}
+
+/// `python -m pip` isolated from the user's environment
+fn pip(python: &Path) -> Command {
+    let mut command = Command::new(python);
+    command
+        .args(["-m", "pip", "--disable-pip-version-check"])
+        .env_remove("PYTHONPATH")
+        .env_remove("PYTHONHOME")
+        .env("PIP_REQUIRE_VIRTUALENV", "true");
+    command
+}
+
+/// `python3` or `python`, whichever is on `PATH`
+fn system_python() -> Result<&'static str> {
+    ["python3", "python"]
+        .into_iter()
+        .find(|python| process::has_command(python, &["--version"]))
+        .ok_or_else(|| Error::MissingRuntime {
+            name: String::from("Python 3"),
+            hint: String::from("install it from https://www.python.org/downloads"),
+        })
+}
+
+impl Server for Pip {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "pip"
+    }
+
+    fn homepage(&self) -> Option<String> {
+        let homepage = self.meta.homepage.clone();
+        Some(homepage.unwrap_or_else(|| format!("https://pypi.org/project/{}", self.package)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        venv_bin(&self.venv()).join(&self.bin)
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(Runtime {
+            program: venv_bin(&self.venv()).join("python").display().to_string(),
+            hint: "reinstall the server, the Python its virtualenv was made with is gone",
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        let project = self.project(version)?;
+        let version = project["info"]["version"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidResponse(String::from("package has no version")))?;
+        // Of the wheel, when there's one, without the dependencies
+        let files = project["urls"].as_array().cloned().unwrap_or_default();
+        let size = files
+            .iter()
+            .find(|file| file["packagetype"] == "bdist_wheel")
+            .or_else(|| files.first())
+            .and_then(|file| file["size"].as_u64());
+        Ok(Plan {
+            source: format!("{}=={}", self.package, version),
+            version: version.to_string(),
+            dir: self.dir(),
+            size,
+            requires: vec![String::from("python3")],
+            sha256: None,
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        let python = system_python()?;
+        let home = self.home();
+
+        // Virtualenvs can't be moved once created, so the new one gets built
+        // in its version directory rather than staged
+        let dir = layout::new_dir(&home, &plan.version);
+        transact(self, Some(&dir), || {
+            layout::set_aside(&home)?;
+            fs::create_dir_all(&dir).at(&dir)?;
+            let version = self.build(python, &dir.join("venv"), &plan.source, report)?;
+            write_version(&dir, &version)?;
+            layout::switch(&home, &dir)?;
+            check_install(self, &version, report)
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.plan(None).map(|plan| plan.version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let project = self.project(None)?;
+        // Releases without files got removed
+        let versions = project["releases"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, files)| files.as_array().is_some_and(|files| !files.is_empty()))
+            .map(|(version, _)| version.clone())
+            .collect();
+        Ok(newest(versions))
+    }
+
+    fn details(&self) -> Vec<(&'static str, String)> {
+        let python = venv_bin(&self.venv()).join("python");
+        vec![("python", python.display().to_string())]
+    }
+}
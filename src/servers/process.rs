@@ -0,0 +1,224 @@
+use crate::{
+    error::{Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{
+    collections::VecDeque,
+    env,
+    ffi::OsStr,
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Lines of output kept to explain why a command failed
+const TAIL_LINES: usize = 20;
+
+/// Whether `program` can be found on `PATH` and runs successfully with `args`,
+/// usually the ones printing its version
+pub fn has_command(program: impl AsRef<OsStr>, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Whether there's an executable named `program` on `PATH`, without running it
+pub fn on_path(program: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| {
+        let plain = dir.join(program);
+        plain.is_file()
+            || cfg!(windows)
+                && super::external::WINDOWS_EXTENSIONS
+                    .iter()
+                    .any(|extension| plain.with_extension(extension).is_file())
+    })
+}
+
+/// What `probe()` got out of a program
+pub struct Probe {
+    /// `None` when the program had to be killed
+    pub status: Option<ExitStatus>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Start `program` with `args` and an open stdin it never gets anything on,
+/// the way a server waits for its client, and kill it along with its
+/// children after `wait`. `None` when it was still running by then.
+pub fn alive(
+    program: impl AsRef<OsStr>,
+    args: &[&str],
+    wait: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    let mut command = Command::new(program);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let start = Instant::now();
+    while start.elapsed() < wait {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    kill(&mut child);
+    Ok(None)
+}
+
+/// Run `program` with `args` and no input, capturing its output, and kill
+/// it along with its children once `timeout` is over
+pub fn probe(program: impl AsRef<OsStr>, args: &[&str], timeout: Duration) -> io::Result<Probe> {
+    let mut command = Command::new(program);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let read = |mut reader: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut text = Vec::new();
+            let _ = reader.read_to_end(&mut text);
+            String::from_utf8_lossy(&text).into_owned()
+        })
+    };
+    let stdout = read(Box::new(child.stdout.take().expect("stdout is piped")));
+    let stderr = read(Box::new(child.stderr.take().expect("stderr is piped")));
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if start.elapsed() >= timeout {
+            kill(&mut child);
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    Ok(Probe {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Run `command` to completion with its output captured, so it can't draw
+/// over the UI. Fails with the end of stderr when the command does.
+pub fn run(command: &mut Command) -> Result<String> {
+    let output = command.stdin(Stdio::null()).output()?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    Err(Error::Command {
+        command: format!("{:?}", command.get_program()),
+        status: output.status.to_string(),
+        output: lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n"),
+    })
+}
+
+/// How often a running command checks whether its task got cancelled
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Run `command` to completion, sending each line it prints to `report`.
+/// Fails with the last lines of output when the command does, and kills it
+/// along with its own children when the task gets cancelled.
+pub fn run_logged(command: &mut Command, report: &Reporter) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let (tx, rx) = mpsc::channel();
+    let readers: Vec<Box<dyn Read + Send>> = vec![
+        Box::new(child.stdout.take().expect("stdout is piped")),
+        Box::new(child.stderr.take().expect("stderr is piped")),
+    ];
+    for reader in readers {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(io::Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut tail = VecDeque::with_capacity(TAIL_LINES);
+    loop {
+        if report.is_cancelled() {
+            kill(&mut child);
+            return report.check();
+        }
+        let line = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if tail.len() == TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line.clone());
+        report.send(Report::Log(line));
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        return Ok(());
+    }
+
+    Err(Error::Command {
+        command: format!("{:?}", command.get_program()),
+        status: status.to_string(),
+        output: Vec::from(tail).join("\n"),
+    })
+}
+
+/// Stop `child` and whatever it started, like the node processes npm spawns
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // The child leads its own process group, see `run_logged()`
+        // SAFETY: kill() has no memory safety requirements
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
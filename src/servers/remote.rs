@@ -0,0 +1,198 @@
+//! Registry published online, so servers added since the binary got built
+//! can be installed without upgrading it. It's fetched
+//! into the cache directory and merged with the embedded registry, which
+//! it only adds servers to: an entry changing an embedded one is ignored
+//! with a warning.
+//!
+//! The manifest is `{"schema": "1.0", "servers": [...]}`, a newer major
+//! schema meaning entries this binary can't read. It's only kept once it
+//! matches the SHA-256 published next to it.
+//!
+//! That checksum comes from the same origin as the manifest, so it only
+//! catches a transfer gone wrong: it's an integrity check, not a signature.
+//! Whoever controls the release, or can pass for its host, decides the
+//! URLs the fetched entries download and run, which is why they never get
+//! to replace the servers of the binary.
+
+use super::spec::Spec;
+use crate::{
+    download,
+    error::{Context, Error, Result},
+    paths,
+};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Where the manifest is published, as an asset of a release updated with
+/// each new entry. Its checksum is at the same URL followed by `.sha256`.
+const URL: &str =
+    "https://github.com/Khelthal/lsp_installer/releases/download/registry/registry.json";
+
+/// Major schema version this binary reads
+const SCHEMA: u32 = 1;
+
+/// How long the fetched registry is used before refreshing it in the
+/// background
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Deserialize)]
+struct Manifest {
+    schema: String,
+    /// Kept as JSON so an entry with a backend added by a newer minor
+    /// schema is skipped rather than failing the whole manifest
+    servers: Vec<serde_json::Value>,
+}
+
+pub fn path() -> PathBuf {
+    paths::cache_dir().join("registry.json")
+}
+
+/// Entries of the manifest `text`, with a warning for each one that can't
+/// be read, or an error when it's not a manifest of a schema this binary
+/// reads. Entries named like one of `builtin` are left out, with a warning
+/// when they differ from it.
+fn parse(text: &str, builtin: &[serde_json::Value]) -> Result<(Vec<Spec>, Vec<String>)> {
+    let invalid = |reason: String| Error::RegistryParse {
+        path: path().display().to_string(),
+        reason,
+    };
+    let manifest: Manifest = serde_json::from_str(text).map_err(|err| invalid(err.to_string()))?;
+    let major = manifest
+        .schema
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u32>().ok())
+        .ok_or_else(|| invalid(format!("invalid schema version {}", manifest.schema)))?;
+    if major > SCHEMA {
+        return Err(invalid(format!(
+            "the registry uses schema {}, please upgrade lsp_installer to read it",
+            manifest.schema
+        )));
+    }
+
+    let mut specs = Vec::new();
+    let mut warnings = Vec::new();
+    for server in manifest.servers {
+        let name = server["name"].as_str().unwrap_or("?").to_string();
+        if let Some(known) = builtin.iter().find(|known| known["name"] == server["name"]) {
+            if *known != server {
+                warnings.push(format!(
+                    "{}: kept the built-in {}, the fetched entry differs from it",
+                    path().display(),
+                    name
+                ));
+            }
+            continue;
+        }
+        match serde_json::from_value(server) {
+            Ok(spec) => specs.push(spec),
+            Err(err) => warnings.push(format!(
+                "{}: skipped {} ({}), a newer lsp_installer may support it",
+                path().display(),
+                name,
+                err
+            )),
+        }
+    }
+    Ok((specs, warnings))
+}
+
+/// Entries of the fetched registry, none when it was never fetched. A
+/// registry this binary can't read is left out with a warning.
+pub fn load() -> (Vec<Spec>, Vec<String>) {
+    let Ok(text) = fs::read_to_string(path()) else {
+        return (Vec::new(), Vec::new());
+    };
+    let builtin: Vec<serde_json::Value> = serde_json::from_str(super::REGISTRY)
+        .expect("the embedded registry is validated at build time");
+    match parse(&text, &builtin) {
+        Ok(loaded) => loaded,
+        Err(err) => (
+            Vec::new(),
+            vec![format!("{}, using the built-in registry", err)],
+        ),
+    }
+}
+
+/// Fetch the registry and keep it once it matches its checksum, returning
+/// the number of servers it has
+pub fn update() -> Result<usize> {
+    let text = download::get(URL)?.into_string()?;
+    // Like the output of sha256sum, the digest may be followed by a name
+    let published = download::get(&format!("{}.sha256", URL))?.into_string()?;
+    let expected = published.split_whitespace().next().unwrap_or_default();
+
+    let digest = download::sha256(text.as_bytes());
+    if !expected.eq_ignore_ascii_case(&digest) {
+        return Err(Error::ChecksumMismatch {
+            file: String::from("the registry"),
+            expected: expected.to_string(),
+            actual: digest,
+        });
+    }
+    let (specs, _) = parse(&text, &[])?;
+
+    let temp = path().with_extension("json.tmp");
+    fs::create_dir_all(paths::cache_dir()).at(&paths::cache_dir())?;
+    fs::write(&temp, &text).at(&temp)?;
+    fs::rename(&temp, path()).at(&path())?;
+    Ok(specs.len())
+}
+
+/// Whether the fetched registry is missing or old enough to be refreshed
+pub fn is_stale() -> bool {
+    fs::metadata(path())
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_none_or(|age| age > MAX_AGE)
+}
+
+/// Refresh the registry in the background when it's stale, sending the
+/// outcome to `tx`. New entries show up from the next start on.
+pub fn refresh(tx: Sender<Result<usize>>) {
+    if !is_stale() || download::is_offline() {
+        return;
+    }
+    thread::spawn(move || {
+        let _ = tx.send(update());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fetched_entries_only_add_servers() {
+        let embedded: Vec<serde_json::Value> =
+            serde_json::from_str(crate::servers::REGISTRY).unwrap();
+        let builtin: Vec<_> = embedded
+            .into_iter()
+            .filter(|entry| entry["name"] == "shfmt")
+            .collect();
+        let mut tampered = builtin[0].clone();
+        tampered["repo"] = json!("someone/else");
+        let mut added = builtin[0].clone();
+        added["name"] = json!("shfmt-fork");
+        let text = |entries: Vec<serde_json::Value>| {
+            json!({ "schema": "1.0", "servers": entries }).to_string()
+        };
+
+        let (specs, warnings) = parse(&text(vec![builtin[0].clone()]), &builtin).unwrap();
+        assert!(specs.is_empty());
+        assert!(warnings.is_empty());
+
+        let (specs, warnings) = parse(&text(vec![tampered, added]), &builtin).unwrap();
+        assert_eq!(specs.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("kept the built-in shfmt"));
+    }
+}
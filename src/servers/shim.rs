@@ -0,0 +1,163 @@
+//! Entries of the shared bin directory, one per installed server, so editors
+//! get a path that stays the same across updates and rollbacks.
+//!
+//! On Unix an entry is a symlink to the executable. Windows gets a `.cmd`
+//! wrapper instead, as creating symlinks there needs extra privileges.
+//! Servers finding their files next to the executable, or needing to be
+//! started from their install directory, get a script on Unix too.
+
+use super::{spec::Shim, Server};
+use crate::paths;
+use std::{
+    env,
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where the bin directory entry of `server` goes, named after its
+/// executable unless its shim is given a name
+pub fn path<S: Server + ?Sized>(server: &S) -> PathBuf {
+    let bin = server.bin();
+    let name = match server
+        .meta()
+        .shim
+        .as_ref()
+        .and_then(|how| how.name.as_deref())
+    {
+        Some(name) => OsStr::new(name),
+        None => bin.file_name().unwrap_or_else(|| OsStr::new(server.name())),
+    };
+    let path = paths::bin_dir().join(name);
+    if cfg!(windows) {
+        path.with_extension("cmd")
+    } else {
+        path
+    }
+}
+
+/// Point the bin directory entry of `server` at its current executable
+pub fn write<S: Server + ?Sized>(server: &S) -> io::Result<PathBuf> {
+    let shim = path(server);
+    fs::create_dir_all(paths::bin_dir())?;
+    if fs::symlink_metadata(&shim).is_ok() {
+        fs::remove_file(&shim)?;
+    }
+    match &server.meta().shim {
+        Some(how) => {
+            fs::write(&shim, script(server, how))?;
+            super::make_executable(&shim).map_err(io::Error::other)?;
+        }
+        None => create(&server.bin(), &shim)?,
+    }
+    Ok(shim)
+}
+
+/// Remove the bin directory entry of `server`, if there's one
+pub fn remove<S: Server + ?Sized>(server: &S) -> io::Result<()> {
+    let shim = path(server);
+    match fs::symlink_metadata(&shim) {
+        Ok(_) => fs::remove_file(shim),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Whether the bin directory entry of `server` starts its current
+/// executable
+pub fn is_current<S: Server + ?Sized>(server: &S) -> bool {
+    let shim = path(server);
+    if let Some(how) = &server.meta().shim {
+        fs::read_to_string(shim).is_ok_and(|text| text == script(server, how))
+    } else if cfg!(windows) {
+        fs::read_to_string(shim).is_ok_and(|text| text == wrapper(&server.bin()))
+    } else {
+        fs::read_link(shim).is_ok_and(|target| target == server.bin())
+    }
+}
+
+#[cfg(unix)]
+pub(super) fn create(bin: &Path, shim: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(bin, shim)
+}
+
+#[cfg(not(unix))]
+pub(super) fn create(bin: &Path, shim: &Path) -> io::Result<()> {
+    fs::write(shim, wrapper(bin))
+}
+
+/// `.cmd` wrapper passing its arguments on to `bin`, `cmd` exits with the
+/// status of the last command it ran
+#[cfg_attr(unix, allow(dead_code))]
+fn wrapper(bin: &Path) -> String {
+    format!("@echo off\r\n\"{}\" %*\r\n", bin.display())
+}
+
+/// Script starting `server` the way `how` says, from the real path of its
+/// executable so it finds the files installed next to it
+fn script<S: Server + ?Sized>(server: &S, how: &Shim) -> String {
+    let dir = server.dir().display().to_string();
+    let args: Vec<String> = how
+        .args
+        .iter()
+        .map(|arg| arg.replace("{dir}", &dir))
+        .collect();
+    let bin = server.bin().display().to_string();
+    if cfg!(windows) {
+        let mut script = String::from("@echo off\r\n");
+        if how.chdir {
+            script.push_str(&format!("cd /d \"{}\"\r\n", dir));
+        }
+        let args: String = args.iter().map(|arg| format!(" \"{}\"", arg)).collect();
+        script + &format!("\"{}\"{} %*\r\n", bin, args)
+    } else {
+        let mut script = format!(
+            "#!/bin/sh\n# Starts {}, written by lsp_installer\n",
+            server.name()
+        );
+        if how.chdir {
+            script.push_str(&format!("cd {} || exit\n", quote(&dir)));
+        }
+        let args: String = args.iter().map(|arg| format!(" {}", quote(arg))).collect();
+        script + &format!("exec {}{} \"$@\"\n", quote(&bin), args)
+    }
+}
+
+/// `text` as a single word of a POSIX shell command line
+pub(super) fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Whether the bin directory is listed in `PATH`
+pub fn on_path() -> bool {
+    let dir = paths::bin_dir();
+    env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|entry| entry == dir))
+}
+
+/// Shell line adding the bin directory to `PATH`, for the shell in use
+pub fn path_line() -> String {
+    let dir = paths::bin_dir();
+    let shell = env::var("SHELL").unwrap_or_default();
+    if cfg!(windows) {
+        format!("setx PATH \"%PATH%;{}\"", dir.display())
+    } else if shell.ends_with("fish") {
+        format!("fish_add_path {}", dir.display())
+    } else {
+        format!("export PATH=\"{}:$PATH\"", dir.display())
+    }
+}
+
+/// Warning that the bin directory isn't on `PATH`, given once: the first
+/// time it's found missing with something installed in it
+pub fn path_notice() -> Option<String> {
+    let marker = paths::state_dir().join("path-notice");
+    if on_path() || !paths::bin_dir().is_dir() || marker.exists() {
+        return None;
+    }
+    let _ = fs::create_dir_all(paths::state_dir());
+    let _ = fs::write(marker, "");
+    Some(format!(
+        "{} is not on PATH, editors won't find the servers by name until you add `{}` to your shell profile",
+        paths::bin_dir().display(),
+        path_line()
+    ))
+}
@@ -0,0 +1,368 @@
+//! Data format of the server registry.
+//!
+//! Only depends on serde so `build.rs` can check the embedded registry
+//! against it, turning a broken entry into a build failure.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// One registry entry, tagged with the backend installing it
+#[derive(Deserialize)]
+#[serde(tag = "backend", rename_all = "kebab-case")]
+pub enum Spec {
+    GithubRelease(GithubRelease),
+    Npm(Npm),
+    Pip(Pip),
+    Cargo(Cargo),
+    Go(Go),
+    Composer(Composer),
+    Jdtls(Jdtls),
+    Ghcup(Ghcup),
+    Opam(Opam),
+    Gem(Gem),
+    DotnetTool(DotnetTool),
+    Zls(Zls),
+}
+
+/// Kind of tool an entry installs
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    #[default]
+    LanguageServer,
+    Formatter,
+    Linter,
+    DebugAdapter,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [
+        Category::LanguageServer,
+        Category::Formatter,
+        Category::Linter,
+        Category::DebugAdapter,
+    ];
+
+    /// Spelling in the registry and `servers.toml`
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::LanguageServer => "language-server",
+            Category::Formatter => "formatter",
+            Category::Linter => "linter",
+            Category::DebugAdapter => "debug-adapter",
+        }
+    }
+
+    /// Heading of the entries of the category
+    pub fn title(self) -> &'static str {
+        match self {
+            Category::LanguageServer => "Language Servers",
+            Category::Formatter => "Formatters",
+            Category::Linter => "Linters",
+            Category::DebugAdapter => "Debug Adapters",
+        }
+    }
+}
+
+/// Fields shared by every entry whatever its backend
+#[derive(Deserialize)]
+pub struct Meta {
+    pub name: String,
+    pub languages: Vec<String>,
+    pub description: String,
+    /// A language server unless told otherwise
+    #[serde(default)]
+    pub category: Category,
+    /// Other names the server gets searched by, like `pylsp`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Other names of its languages, by language, like `golang` for `go`
+    #[serde(default)]
+    pub language_aliases: BTreeMap<String, Vec<String>>,
+    /// Files found in projects the server gets suggested for, by language,
+    /// like `Cargo.toml` for `rust`, where `*` stands for any part of a name
+    #[serde(default)]
+    pub project_files: BTreeMap<String, Vec<String>>,
+    /// Project page, when it isn't the page of the package the server
+    /// comes from
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// Source repository, publishing the releases and their changelog
+    #[serde(default)]
+    pub repository: Option<String>,
+    /// Name of the server in nvim-lspconfig
+    #[serde(default)]
+    pub lspconfig: Option<String>,
+    /// VS Code setting holding the path of the executable, with a `/`
+    /// between the keys of nested objects
+    #[serde(default)]
+    pub vscode: Option<String>,
+    /// Name of the formatter in conform.nvim
+    #[serde(default)]
+    pub conform: Option<String>,
+    /// Name of the linter in nvim-lint
+    #[serde(default)]
+    pub nvim_lint: Option<String>,
+    /// How nvim-dap starts the debug adapter
+    #[serde(default)]
+    pub dap: Option<DapAdapter>,
+    /// Arguments the executable needs to start speaking LSP on stdio
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Arguments checking a fresh install runs, `--version` when missing,
+    /// `none` for servers that only speak LSP, or `alive` for those that only
+    /// speak LSP and quit when they can't start, which pass by still running
+    /// after a few seconds without input
+    #[serde(default)]
+    pub verify: Option<String>,
+    /// How the bin directory entry starts a server that breaks when its
+    /// executable is merely linked to
+    #[serde(default)]
+    pub shim: Option<Shim>,
+    /// Initialization options editors send when starting the server, where
+    /// `{dir}` in strings gets replaced by the install directory
+    #[serde(default)]
+    pub init_options: Option<serde_json::Value>,
+}
+
+/// Adapter configuration of a debug adapter in nvim-dap
+#[derive(Deserialize)]
+pub struct DapAdapter {
+    /// Name of the adapter, which launch configurations refer to by `type`
+    pub name: String,
+    /// Arguments starting the adapter, where nvim-dap replaces `${port}` by
+    /// the port it picked
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Whether the adapter listens on `${port}` rather than stdio
+    #[serde(default)]
+    pub server: bool,
+}
+
+/// Script standing in for the executable in the bin directory
+#[derive(Deserialize)]
+pub struct Shim {
+    /// Start the server from its install directory
+    #[serde(default)]
+    pub chdir: bool,
+    /// Arguments passed before the editor's, where `{dir}` gets replaced by
+    /// the install directory
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Name of the script, the executable's when missing, for executables
+    /// named too plainly to go on `PATH`
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// A server distributing prebuilt binaries as GitHub release assets
+#[derive(Deserialize)]
+pub struct GithubRelease {
+    #[serde(flatten)]
+    pub meta: Meta,
+    /// `owner/repo` publishing the releases
+    pub repo: String,
+    /// Name of the asset to download, where `{os}`, `{arch}`, `{libc}`,
+    /// `{ext}` and `{version}` get replaced by the values for the current
+    /// platform and release. The spelling of the OS may use `{libc}` too, and
+    /// the keys of `detect` stand for the versions of local tools.
+    pub asset: String,
+    /// Path of the executable relative to the install directory, which may
+    /// contain `{version}` as well, and `{exe}` standing for `.exe` on
+    /// Windows
+    pub bin: String,
+    /// Spelling of each supported `std::env::consts::OS` in asset names
+    pub os: BTreeMap<String, String>,
+    /// Spelling of each supported `std::env::consts::ARCH` in asset names
+    pub arch: BTreeMap<String, String>,
+    /// Spelling of each supported Linux C library, `gnu` or `musl`
+    #[serde(default)]
+    pub libc: BTreeMap<String, String>,
+    /// Archive extension used for each OS
+    #[serde(default)]
+    pub ext: BTreeMap<String, String>,
+    /// Asset names not following `asset`, by platform like `linux-aarch64`
+    #[serde(default)]
+    pub assets: BTreeMap<String, String>,
+    /// Paths the install needs besides the executable, relative to the
+    /// install directory
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Name of the release asset listing SHA-256 checksums in `sha256sum`
+    /// format, where `{asset}` gets replaced by the name of the asset
+    #[serde(default)]
+    pub checksums: Option<String>,
+    /// How to build the server on platforms no asset is published for
+    #[serde(default)]
+    pub source: Option<Box<SourceBuild>>,
+    /// Oldest major version of the .NET runtime the release runs on, for
+    /// builds that don't bundle one
+    #[serde(default)]
+    pub dotnet: Option<u32>,
+    /// Versions of local tools picking the asset, by the placeholder
+    /// standing for each in `asset`
+    #[serde(default)]
+    pub detect: BTreeMap<String, Detect>,
+}
+
+/// Recipe building a server from its repository, checked out at a release
+#[derive(Deserialize)]
+pub struct SourceBuild {
+    /// Git URL of the repository
+    pub git: String,
+    /// Program building the server and its arguments, run in the checkout
+    pub build: Vec<String>,
+    /// Path of the built executable relative to the checkout
+    pub bin: String,
+    /// Programs the build needs on `PATH` besides git and `build`
+    #[serde(default)]
+    pub requires: Vec<String>,
+}
+
+/// How to read the version of a local tool off what it prints
+#[derive(Deserialize)]
+pub struct Detect {
+    /// Program printing the version and its arguments
+    pub command: Vec<String>,
+    /// Word the version follows, like `Elixir` in `Elixir 1.15.7`
+    pub after: String,
+    /// Components of the version kept, all of them when missing
+    #[serde(default)]
+    pub components: Option<usize>,
+    /// What to install when the program is missing
+    pub name: String,
+    pub hint: String,
+}
+
+/// A server published as an npm package, installed into its own prefix
+#[derive(Deserialize)]
+pub struct Npm {
+    #[serde(flatten)]
+    pub meta: Meta,
+    pub package: String,
+    /// Executable provided by the package
+    pub bin: String,
+    /// Name of the install directory, shared by the entries naming the same
+    /// one when a package provides several servers
+    #[serde(default)]
+    pub share: Option<String>,
+    /// Packages installed along with it that the server needs to work, like
+    /// the peer dependencies it doesn't install itself
+    #[serde(default)]
+    pub companions: Vec<String>,
+}
+
+/// A server published on PyPI, installed into a virtualenv of its own
+#[derive(Deserialize)]
+pub struct Pip {
+    #[serde(flatten)]
+    pub meta: Meta,
+    pub package: String,
+    /// Console script provided by the package
+    pub bin: String,
+}
+
+/// A server built from a crate with `cargo install`
+#[derive(Deserialize)]
+pub struct Cargo {
+    #[serde(flatten)]
+    pub meta: Meta,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Version to install instead of the latest one
+    pub version: Option<String>,
+    /// Binary built by the crate
+    pub bin: String,
+}
+
+/// A server built from a Go package with `go install`
+#[derive(Deserialize)]
+pub struct Go {
+    #[serde(flatten)]
+    pub meta: Meta,
+    /// Package path of the command to install
+    pub package: String,
+    /// Module the package belongs to, when it isn't the package itself
+    #[serde(default)]
+    pub module: Option<String>,
+    /// Version to install instead of the latest one, like `v0.16.1`
+    pub version: Option<String>,
+    /// Binary built by the package
+    pub bin: String,
+}
+
+/// A server published on Packagist, installed into its own Composer project
+#[derive(Deserialize)]
+pub struct Composer {
+    #[serde(flatten)]
+    pub meta: Meta,
+    /// Package name, like `phpactor/phpactor`
+    pub package: String,
+    /// Executable provided by the package
+    pub bin: String,
+}
+
+/// Eclipse JDT LS, a Java program started through a launcher script
+#[derive(Deserialize)]
+pub struct Jdtls {
+    #[serde(flatten)]
+    pub meta: Meta,
+    /// Directory listing the milestone builds, a subdirectory per version
+    pub url: String,
+    /// Oldest Java release the server runs on
+    pub java: u32,
+}
+
+/// Tool installed by ghcup, which keeps it under its own directory
+#[derive(Deserialize)]
+pub struct Ghcup {
+    #[serde(flatten)]
+    pub meta: Meta,
+    /// Name ghcup knows it by, like `hls`
+    pub tool: String,
+    /// Executable editors start, among the ones ghcup installs
+    pub bin: String,
+}
+
+/// An opam package, installed into the current switch
+#[derive(Deserialize)]
+pub struct Opam {
+    #[serde(flatten)]
+    pub meta: Meta,
+    pub package: String,
+    /// Executable the package puts in the bin directory of the switch
+    pub bin: String,
+}
+
+/// A Ruby gem, installed with its dependencies into a `GEM_HOME` of its own
+#[derive(Deserialize)]
+pub struct Gem {
+    #[serde(flatten)]
+    pub meta: Meta,
+    pub package: String,
+    /// Executable provided by the gem
+    pub bin: String,
+}
+
+/// A .NET tool from NuGet, installed into a tool path of its own
+#[derive(Deserialize)]
+pub struct DotnetTool {
+    #[serde(flatten)]
+    pub meta: Meta,
+    pub package: String,
+    /// Command the tool provides
+    pub bin: String,
+    /// Oldest major version of the .NET SDK installing it
+    pub sdk: u32,
+}
+
+/// zls, picked to match the local Zig by the zigtools release service
+#[derive(Deserialize)]
+pub struct Zls {
+    #[serde(flatten)]
+    pub meta: Meta,
+    /// Release service answering which build of zls works with a Zig
+    pub url: String,
+    /// `owner/repo` publishing the releases on GitHub
+    pub repo: String,
+}
@@ -0,0 +1,326 @@
+//! Installs in progress, listed in `journal.json` of the install root so
+//! one cut short by a crash or a kill gets undone the next time the
+//! installer starts. Writing its record to the manifest is where an install
+//! commits: before that, the install it was replacing comes back, after
+//! that, the little left to do gets finished.
+
+use super::{
+    layout::{self, CURRENT, PREVIOUS},
+    registry, remove_dir, shim, sibling, Server,
+};
+use crate::{
+    error::{Context, Result},
+    lock,
+    manifest::{self, Record},
+    paths,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// An install in progress
+#[derive(Clone, Deserialize, Serialize)]
+pub(super) struct Transaction {
+    server: String,
+    /// Home of the server, holding its version directories
+    home: PathBuf,
+    /// Where the new install gets built
+    staging: Option<PathBuf>,
+    /// Version directories `current` and `previous` pointed to when the
+    /// transaction began
+    current: Option<PathBuf>,
+    previous_dir: Option<PathBuf>,
+    /// Record of the install being replaced
+    previous: Option<Record>,
+}
+
+/// Held while the journal gets rewritten, as installs run in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn path() -> PathBuf {
+    paths::root().join("journal.json")
+}
+
+fn read() -> Vec<Transaction> {
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Apply `change` to the journal, replacing the file in a single rename
+/// like the manifest, or removing it once nothing is in progress
+fn update(change: impl FnOnce(&mut Vec<Transaction>)) -> Result<()> {
+    let _lock = LOCK.lock();
+    let mut journal = read();
+    change(&mut journal);
+
+    if journal.is_empty() {
+        return match fs::remove_file(path()) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err).at(&path()),
+            _ => Ok(()),
+        };
+    }
+    let text = serde_json::to_string_pretty(&journal)?;
+    let temp = path().with_extension("json.tmp");
+    fs::create_dir_all(paths::root()).at(paths::root())?;
+    fs::write(&temp, text + "\n").at(&temp)?;
+    fs::rename(&temp, path()).at(&path())
+}
+
+/// Note that an install of `server` is starting, built in `staging`
+pub(super) fn begin(server: &dyn Server, staging: Option<&Path>) -> Result<Transaction> {
+    let home = server.home();
+    let transaction = Transaction {
+        server: server.name().to_string(),
+        current: layout::target(&home, CURRENT),
+        previous_dir: layout::target(&home, PREVIOUS),
+        home,
+        staging: staging.map(Path::to_path_buf),
+        previous: manifest::get(server.name()),
+    };
+    let begun = transaction.clone();
+    update(|journal| {
+        journal.retain(|known| known.server != begun.server);
+        journal.push(begun);
+    })?;
+    Ok(transaction)
+}
+
+/// Whether the new install got recorded in the manifest, the record of the
+/// one it replaces being taken out while it's verified
+fn committed(transaction: &Transaction) -> bool {
+    match (manifest::get(&transaction.server), &transaction.previous) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(record), Some(previous)) => {
+            record.installed_at != previous.installed_at || record.version != previous.version
+        }
+    }
+}
+
+/// Put back the install `transaction` was replacing along with its record,
+/// leaving nothing of the new one
+pub(super) fn roll_back(server: &dyn Server, transaction: &Transaction) -> Result<()> {
+    let home = &transaction.home;
+    if let Some(staging) = &transaction.staging {
+        remove_dir(staging)?;
+    }
+    let legacy = layout::legacy(home);
+    if legacy.exists() {
+        // Replacing an install made before there were versions
+        remove_dir(home)?;
+        fs::rename(&legacy, home).at(home)?;
+    } else if home.exists() {
+        layout::point(home, CURRENT, transaction.current.as_deref())?;
+        layout::point(home, PREVIOUS, transaction.previous_dir.as_deref())?;
+        layout::prune(home)?;
+        // Left empty by a first install, unless in use by another server
+        let _ = fs::remove_dir(home);
+    }
+    if let Some(previous) = &transaction.previous {
+        if manifest::get(&transaction.server).is_none() {
+            manifest::insert(&transaction.server, previous.clone())?;
+        }
+    }
+    // The link may point where the new install had its executable
+    if transaction.previous.is_some() {
+        let _ = shim::write(server);
+    } else {
+        let _ = shim::remove(server);
+    }
+    end(transaction)
+}
+
+/// Remove the versions older than the one to roll back to, once the new
+/// install is committed. An install made before there were versions goes
+/// away along with the one it kept to roll back to.
+pub(super) fn finish(transaction: &Transaction) -> Result<()> {
+    let home = &transaction.home;
+    remove_dir(&layout::legacy(home))?;
+    remove_dir(&sibling(home, ".previous"))?;
+    layout::prune(home)?;
+    end(transaction)
+}
+
+fn end(transaction: &Transaction) -> Result<()> {
+    update(|journal| journal.retain(|known| known.server != transaction.server))
+}
+
+/// Undo the installs a previous run left unfinished, or finish the ones
+/// that got committed, returning what was done about each. Left to the
+/// process holding the lock on the install root when another does, its
+/// installs being in progress.
+pub fn recover() -> Vec<String> {
+    if read().is_empty() {
+        return Vec::new();
+    }
+    let Ok(lock) = lock::acquire("recover") else {
+        return Vec::new();
+    };
+    let mut notices = Vec::new();
+    if let Some(holder) = &lock.broken {
+        notices.push(format!("{} exited without finishing", holder));
+    }
+    let journal = read();
+    let (servers, _) = registry();
+    for transaction in journal {
+        let name = &transaction.server;
+        let Some(server) = servers.iter().find(|server| server.name() == name) else {
+            // Custom servers can go away from servers.toml in between
+            let _ = end(&transaction);
+            notices.push(format!(
+                "{} was being installed into {} but isn't known anymore",
+                name,
+                transaction.home.display()
+            ));
+            continue;
+        };
+        let notice = if committed(&transaction) {
+            finish(&transaction).map(|()| format!("Finished the interrupted install of {}", name))
+        } else {
+            roll_back(server.as_ref(), &transaction)
+                .map(|()| format!("Undid the interrupted install of {}", name))
+        };
+        notices.push(notice.unwrap_or_else(|err| {
+            format!(
+                "Couldn't clean up the interrupted install of {}: {}",
+                name, err
+            )
+        }));
+    }
+    notices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::servers::VERSION_FILE;
+    use std::sync::Arc;
+
+    /// Held by the tests recovering, which go through the whole journal
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn server(name: &str) -> Arc<dyn Server> {
+        paths::test_root();
+        let (servers, _) = registry();
+        servers
+            .into_iter()
+            .find(|server| server.name() == name)
+            .unwrap()
+    }
+
+    fn record(version: &str, installed_at: &str) -> Record {
+        Record {
+            version: version.to_string(),
+            backend: String::from("github-release"),
+            installed_at: installed_at.to_string(),
+            url: None,
+            sha256: None,
+            bin: PathBuf::from("bin"),
+            reported_version: None,
+            held: false,
+            used_at: None,
+            disk_size: None,
+            built_from_source: false,
+            from_file: false,
+        }
+    }
+
+    /// Version directory `name` in the home of `server`, made the current
+    /// one
+    fn install(server: &dyn Server, name: &str) -> PathBuf {
+        let dir = server.home().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(VERSION_FILE), name).unwrap();
+        layout::switch(&server.home(), &dir).unwrap();
+        dir
+    }
+
+    fn names(home: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(home)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn crash_before_committing_rolls_back() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let server = server("shfmt");
+        let home = server.home();
+        install(server.as_ref(), "v1");
+        manifest::insert("shfmt", record("v1", "2026-01-01T00:00:00Z")).unwrap();
+
+        let staging = home.join(".new-shfmt");
+        begin(server.as_ref(), Some(&staging)).unwrap();
+        fs::create_dir_all(&staging).unwrap();
+        install(server.as_ref(), "v2");
+        // Verifying takes the record of the replaced install out
+        manifest::remove("shfmt").unwrap();
+
+        let notices = recover();
+        assert_eq!(notices, ["Undid the interrupted install of shfmt"]);
+        assert_eq!(layout::target(&home, CURRENT), Some(PathBuf::from("v1")));
+        assert_eq!(names(&home), [CURRENT, "v1"]);
+        assert_eq!(manifest::get("shfmt").unwrap().version, "v1");
+        assert!(read().is_empty());
+    }
+
+    #[test]
+    fn crash_after_committing_finishes() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let server = server("stylua");
+        let home = server.home();
+        install(server.as_ref(), "v0");
+        install(server.as_ref(), "v1");
+        manifest::insert("stylua", record("v1", "2026-01-01T00:00:00Z")).unwrap();
+
+        begin(server.as_ref(), None).unwrap();
+        install(server.as_ref(), "v2");
+        manifest::insert("stylua", record("v2", "2026-02-01T00:00:00Z")).unwrap();
+
+        let notices = recover();
+        assert_eq!(notices, ["Finished the interrupted install of stylua"]);
+        assert_eq!(layout::target(&home, CURRENT), Some(PathBuf::from("v2")));
+        assert_eq!(layout::target(&home, PREVIOUS), Some(PathBuf::from("v1")));
+        assert_eq!(names(&home), [CURRENT, PREVIOUS, "v1", "v2"]);
+        assert_eq!(manifest::get("stylua").unwrap().version, "v2");
+        assert!(read().is_empty());
+    }
+
+    #[test]
+    fn committed_once_the_record_changed() {
+        let server = server("taplo");
+        let transaction = |previous| Transaction {
+            server: server.name().to_string(),
+            home: server.home(),
+            staging: None,
+            current: None,
+            previous_dir: None,
+            previous,
+        };
+        let old = record("0.9.0", "2026-01-01T00:00:00Z");
+
+        // (no record, _): the new install never got recorded
+        manifest::forget("taplo").unwrap();
+        assert!(!committed(&transaction(None)));
+        assert!(!committed(&transaction(Some(old.clone()))));
+
+        // (record, none): a first install, recorded
+        manifest::insert("taplo", old.clone()).unwrap();
+        assert!(committed(&transaction(None)));
+
+        // (record, previous): committed unless the record is the old one
+        assert!(!committed(&transaction(Some(old.clone()))));
+        manifest::insert("taplo", record("0.9.0", "2026-03-01T00:00:00Z")).unwrap();
+        assert!(committed(&transaction(Some(old.clone()))));
+        manifest::insert("taplo", record("0.10.0", "2026-01-01T00:00:00Z")).unwrap();
+        assert!(committed(&transaction(Some(old))));
+    }
+}
@@ -0,0 +1,167 @@
+//! zls, the Zig language server, which only works with the Zig release it
+//! was made for. The build matching the local `zig version` gets picked by
+//! the zigtools release service, which also knows the nightly zls builds
+//! matching development builds of Zig.
+
+use super::{
+    fetch, github, probe,
+    spec::{Meta, Zls},
+    unpack, Constraint, Plan, Server,
+};
+use crate::{
+    download,
+    error::{Error, Result},
+    worker::{Report, Reporter},
+};
+use std::{env, path::PathBuf, time::Duration};
+
+/// How long `zig version` gets to answer
+const ZIG_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Version printed by `zig version`, like `0.13.0` or
+/// `0.14.0-dev.1911+3bf89f55c` for a development build
+fn zig_version() -> Option<String> {
+    let probe = probe("zig", &["version"], ZIG_TIMEOUT).ok()?;
+    if !probe.status.is_some_and(|status| status.success()) {
+        return None;
+    }
+    let version = probe.stdout.trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+impl Zls {
+    /// Build of zls the release service picks for `zig`, as its version and
+    /// the download for this platform
+    fn select(&self, zig: &str) -> Result<(String, serde_json::Value)> {
+        let response = download::get(&format!(
+            "{}/select-version?zig_version={}&compatibility=only-runtime",
+            self.url, zig
+        ))?;
+        let selected: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+        if let Some(message) = selected["message"].as_str() {
+            return Err(Error::NotFound(format!(
+                "no zls works with zig {}: {}",
+                zig, message
+            )));
+        }
+        let version = selected["version"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidResponse(String::from("the selected zls has no version")))?
+            .to_string();
+        let platform = format!("{}-{}", env::consts::ARCH, env::consts::OS);
+        let build = selected[&platform].clone();
+        if !build.is_object() {
+            return Err(Error::NotFound(format!(
+                "zls {} has no build for {}",
+                version, platform
+            )));
+        }
+        Ok((version, build))
+    }
+
+    fn zig(&self) -> Result<String> {
+        zig_version().ok_or_else(|| Error::MissingRuntime {
+            name: String::from("Zig"),
+            hint: String::from(
+                "install it from https://ziglang.org/download, zls gets picked to match it",
+            ),
+        })
+    }
+}
+
+impl Server for Zls {
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    fn backend(&self) -> &'static str {
+        "zls"
+    }
+
+    fn repository(&self) -> Option<String> {
+        let repository = self.meta.repository.clone();
+        Some(repository.unwrap_or_else(|| format!("https://github.com/{}", self.repo)))
+    }
+
+    fn bin(&self) -> PathBuf {
+        let exe = if cfg!(windows) { ".exe" } else { "" };
+        self.dir().join(format!("zls{}", exe))
+    }
+
+    fn constraint(&self) -> Option<Constraint> {
+        Some(Constraint {
+            tool: String::from("zig"),
+            found: zig_version(),
+            components: 2,
+        })
+    }
+
+    fn plan(&self, version: Option<&str>) -> Result<Plan> {
+        // A release to pin gets looked up as the Zig release it was made
+        // for, which shares its version, a nightly as the local Zig
+        let zig = match version {
+            Some(version) if !version.contains("-dev") => version.to_string(),
+            _ => self.zig()?,
+        };
+        let (selected, build) = self.select(&zig)?;
+        if let Some(version) = version.filter(|version| *version != selected) {
+            return Err(Error::NotFound(format!(
+                "zls {} isn't published, the closest is {}",
+                version, selected
+            )));
+        }
+        let source = build["tarball"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidResponse(String::from("the zls build has no tarball")))?
+            .to_string();
+        Ok(Plan {
+            version: selected,
+            source,
+            dir: self.dir(),
+            // Given as a string of digits
+            size: build["size"]
+                .as_u64()
+                .or_else(|| build["size"].as_str().and_then(|size| size.parse().ok())),
+            requires: Vec::new(),
+            sha256: build["shasum"].as_str().map(str::to_string),
+        })
+    }
+
+    fn execute(&self, plan: &Plan, report: &Reporter) -> Result<()> {
+        super::install_staged(self, report, |staging| {
+            let name = plan.source.rsplit('/').next().unwrap_or("zls.tar.xz");
+            let archive = staging.join(name);
+            fetch(&plan.source, &archive, plan.sha256.as_deref(), true, report)?;
+            report.send(Report::Log(format!("Extracting {}", name)));
+            unpack(&archive, staging, "zls")?;
+            Ok(plan.version.clone())
+        })
+    }
+
+    fn latest_version(&self) -> Result<String> {
+        self.select(&self.zig()?).map(|(version, _)| version)
+    }
+
+    fn versions(&self) -> Result<Vec<String>> {
+        let releases = github::api(&format!(
+            "https://api.github.com/repos/{}/releases?per_page={}",
+            self.repo,
+            super::VERSION_COUNT
+        ))?;
+        let mut versions: Vec<String> = releases
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|release| release["draft"] != true && release["prerelease"] != true)
+            .filter_map(|release| release["tag_name"].as_str().map(str::to_string))
+            .collect();
+        // Nightlies aren't released on GitHub, the one for the local
+        // development build of Zig gets offered first
+        if let Some(zig) = zig_version().filter(|zig| zig.contains("-dev")) {
+            if let Ok((nightly, _)) = self.select(&zig) {
+                versions.insert(0, nightly);
+            }
+        }
+        Ok(versions)
+    }
+}
@@ -0,0 +1,44 @@
+//! Background check of the latest version of each installed server, run at
+//! startup. Versions fetched less than a day ago come from the metadata
+//! cache, so restarting doesn't query every registry again.
+
+use crate::{download, metadata, servers::Server};
+use std::{
+    sync::{mpsc::Sender, Arc},
+    thread,
+    time::Duration,
+};
+
+/// How long a checked version is trusted before it's checked again
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Check the latest version of each of `servers`, by index, sending the
+/// results to `tx` as they come. Servers are checked one after the
+/// other so registries don't get a burst of requests.
+///
+/// Offline, the last versions fetched are sent instead, and servers never
+/// checked are left out without an error.
+pub fn spawn(servers: Vec<(usize, Arc<dyn Server>)>, tx: Sender<(usize, Result<String, String>)>) {
+    thread::spawn(move || {
+        for (i, server) in servers {
+            let cached = metadata::latest(server.name());
+            let result = match cached {
+                Some((version, age)) if age < MAX_AGE || download::is_offline() => Ok(version),
+                _ => match server.latest_version() {
+                    Ok(version) => {
+                        metadata::save_latest(server.name(), &version);
+                        Ok(version)
+                    }
+                    Err(_) if download::is_offline() => match cached {
+                        Some((version, _)) => Ok(version),
+                        None => continue,
+                    },
+                    Err(err) => Err(err.to_string()),
+                },
+            };
+            if tx.send((i, result)).is_err() {
+                return;
+            }
+        }
+    });
+}
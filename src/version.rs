@@ -0,0 +1,89 @@
+use std::cmp::Ordering;
+
+/// Compare two version strings as published by upstream projects.
+///
+/// Accepts `v`-prefixed tags (`v1.2.3`), date tags (`2024-11-04`) and
+/// prerelease suffixes (`1.2.3-rc.1`), which sort before the release itself.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let (a_core, a_pre) = split(a);
+    let (b_core, b_pre) = split(b);
+
+    compare_identifiers(&a_core, &b_core, usize::MAX).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_identifiers(
+            &a.split('.').collect::<Vec<_>>(),
+            &b.split('.').collect::<Vec<_>>(),
+            0,
+        ),
+    })
+}
+
+/// First word of `line` that looks like a version, like `v0.16.1` in
+/// `gopls v0.16.1` or `1.83.0` in `rust-analyzer 1.83.0 (90b35a6 2024-11-26)`
+pub fn find(line: &str) -> Option<&str> {
+    line.split_whitespace().find(|word| {
+        word.trim_start_matches('v')
+            .starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+/// Whether `latest` is a newer version than `installed`
+pub fn is_newer(latest: &str, installed: &str) -> bool {
+    compare(latest, installed) == Ordering::Greater
+}
+
+/// Split a version into its core components and optional prerelease part.
+///
+/// A `-` only starts the prerelease when it's followed by a non-digit, so
+/// date tags like `2024-11-04` keep all three components in the core.
+fn split(version: &str) -> (Vec<&str>, Option<&str>) {
+    let version = version.trim();
+    let version = version
+        .strip_prefix('v')
+        .or_else(|| version.strip_prefix('V'))
+        .unwrap_or(version);
+    let version = version.split('+').next().unwrap_or_default();
+
+    let pre_start =
+        version
+            .match_indices('-')
+            .find_map(|(i, _)| match version[i + 1..].chars().next() {
+                Some(c) if c.is_ascii_digit() => None,
+                _ => Some(i),
+            });
+    let (core, pre) = match pre_start {
+        Some(i) => (&version[..i], Some(&version[i + 1..])),
+        None => (version, None),
+    };
+
+    (core.split(['.', '-']).collect(), pre)
+}
+
+/// Compare identifiers pairwise, numerically when both are numbers.
+///
+/// Missing identifiers count as `0` for the first `pad` positions, past that
+/// the shorter list sorts first.
+fn compare_identifiers(a: &[&str], b: &[&str], pad: usize) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) => (*x, *y),
+            (Some(_), None) if i >= pad => return Ordering::Greater,
+            (None, Some(_)) if i >= pad => return Ordering::Less,
+            (x, y) => (x.copied().unwrap_or("0"), y.copied().unwrap_or("0")),
+        };
+
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
@@ -0,0 +1,355 @@
+use crate::{
+    disk,
+    error::{Error, Result},
+    logfile::{self, Level},
+    manifest,
+    servers::Server,
+};
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Work done on a server outside of the UI thread
+#[derive(Clone)]
+pub enum Task {
+    Install,
+    /// Install the given version and hold the server at it
+    Pin(String),
+    Update,
+    /// Build the latest release from source and install it
+    Build,
+    /// Install from a file instead of downloading it, checking it against
+    /// the published checksum with `verify`
+    Local {
+        file: PathBuf,
+        verify: bool,
+    },
+    /// Go back to the install replaced by the last update
+    Rollback,
+    Uninstall,
+}
+
+impl Task {
+    /// Present participle shown while the task runs
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Task::Install | Task::Pin(_) | Task::Local { .. } => "Installing",
+            Task::Update => "Updating",
+            Task::Build => "Building",
+            Task::Rollback => "Rolling back",
+            Task::Uninstall => "Removing",
+        }
+    }
+
+    /// Infinitive used in error messages, like "to install"
+    fn verb(&self) -> &'static str {
+        match self {
+            Task::Install | Task::Pin(_) | Task::Local { .. } => "to install",
+            Task::Update => "to update",
+            Task::Build => "to build",
+            Task::Rollback => "to roll back",
+            Task::Uninstall => "to remove",
+        }
+    }
+}
+
+/// Sent by a running task back to the UI loop
+pub enum Report {
+    /// The worker picked the task up
+    Started,
+    /// Bytes done so far, out of `total` when it's known
+    Progress {
+        done: u64,
+        total: Option<u64>,
+    },
+    Log(String),
+    /// Something the user should know about once the task is done
+    Warning(String),
+    Completed(String),
+    Failed(Failure),
+    /// The task stopped early because it got cancelled
+    Cancelled(String),
+}
+
+/// Why a task failed
+pub struct Failure {
+    /// What went wrong, possibly followed by the output of a command
+    pub message: String,
+    /// Errors underneath, outermost first
+    pub causes: Vec<String>,
+    /// What the user can do about it
+    pub hint: Option<String>,
+}
+
+impl Failure {
+    pub fn new(message: String, err: &Error) -> Failure {
+        let mut causes = Vec::new();
+        let mut source = std::error::Error::source(err);
+        while let Some(err) = source {
+            causes.push(err.to_string());
+            source = err.source();
+        }
+        Failure {
+            message,
+            causes,
+            hint: err.hint(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)?;
+        for cause in &self.causes {
+            write!(f, "\ncaused by: {}", cause)?;
+        }
+        match &self.hint {
+            Some(hint) => write!(f, "\nhint: {}", hint),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Handle a running task reports through, which also tells it when it got
+/// cancelled
+pub struct Reporter {
+    id: usize,
+    /// Name of the server, which lines of the log file start with
+    server: String,
+    tx: Sender<(usize, Report)>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Reporter {
+    pub fn send(&self, report: Report) {
+        match &report {
+            Report::Started | Report::Progress { .. } => {}
+            Report::Log(text) | Report::Completed(text) | Report::Cancelled(text) => {
+                logfile::write(Level::Info, &self.server, text)
+            }
+            Report::Warning(text) => logfile::write(Level::Warning, &self.server, text),
+            Report::Failed(failure) => {
+                logfile::write(Level::Error, &self.server, &failure.message);
+                for cause in &failure.causes {
+                    logfile::write(Level::Error, &self.server, &format!("caused by: {}", cause));
+                }
+                if let Some(hint) = &failure.hint {
+                    logfile::write(Level::Error, &self.server, &format!("hint: {}", hint));
+                }
+            }
+        }
+        let _ = self.tx.send((self.id, report));
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fail with `Error::Cancelled` once the task got cancelled, to be
+    /// called between steps of long running work
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Run `task` on `server` in a background thread, reporting through `tx`
+/// with `id` attached to every report. Setting `cancelled` asks the task to
+/// stop as soon as it can.
+pub fn spawn(
+    id: usize,
+    server: Arc<dyn Server>,
+    task: Task,
+    tx: Sender<(usize, Report)>,
+    cancelled: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let name = server.name();
+        let report = Reporter {
+            id,
+            server: name.to_string(),
+            tx,
+            cancelled,
+        };
+        report.send(Report::Started);
+        report.send(Report::Log(format!("{} {}", task.describe(), name)));
+
+        let result = match &task {
+            Task::Install => server
+                .install(&report)
+                .map(|()| format!("Installed {}", name)),
+            Task::Pin(version) => server
+                .pin(version, &report)
+                .map(|()| format!("Installed {} {}, holding it there", name, version)),
+            Task::Update => server.update(&report).map(|version| match version {
+                Some(version) => format!("Updated {} to {}", name, version),
+                None => format!("{} is up to date", name),
+            }),
+            Task::Build => server
+                .build(&report)
+                .map(|()| format!("Built {} from source", name)),
+            Task::Local { file, verify } => server
+                .install_file(file, *verify, &report)
+                .map(|()| format!("Installed {} from {}", name, file.display())),
+            Task::Rollback => server
+                .rollback()
+                .map(|version| format!("Rolled {} back to {}", name, version)),
+            Task::Uninstall => server
+                .uninstall()
+                .and_then(|()| manifest::forget(name))
+                .map(|()| format!("Removed {}", name)),
+        };
+        if result.is_ok() && !matches!(task, Task::Uninstall) {
+            // Only for sorting and showing, not worth failing the task
+            let _ = manifest::touch(name);
+            if server.external().is_none() {
+                let _ = manifest::set_disk_size(name, disk::size(&server.dir()));
+            }
+        }
+        report.send(match result {
+            Ok(text) => Report::Completed(text),
+            Err(_) if report.is_cancelled() => Report::Cancelled(format!(
+                "Cancelled {} {}",
+                task.describe().to_lowercase(),
+                name
+            )),
+            Err(err) => Report::Failed(Failure::new(
+                format!("Failed {} {}: {}", task.verb(), name, err),
+                &err,
+            )),
+        });
+    })
+}
+
+/// Workers of the tasks spawned, kept so quitting can stop them instead of
+/// leaving a download or a command behind
+#[derive(Default)]
+pub struct Pool {
+    workers: Vec<(Arc<AtomicBool>, JoinHandle<()>)>,
+}
+
+impl Pool {
+    /// Like [`spawn`], keeping track of the worker
+    pub fn spawn(
+        &mut self,
+        id: usize,
+        server: Arc<dyn Server>,
+        task: Task,
+        tx: Sender<(usize, Report)>,
+        cancelled: Arc<AtomicBool>,
+    ) {
+        self.workers.retain(|(_, worker)| !worker.is_finished());
+        let worker = spawn(id, server, task, tx, cancelled.clone());
+        self.workers.push((cancelled, worker));
+    }
+
+    /// Number of workers still running
+    pub fn running(&self) -> usize {
+        self.workers
+            .iter()
+            .filter(|(_, worker)| !worker.is_finished())
+            .count()
+    }
+
+    /// Cancel the tasks still running and wait for their workers to be
+    /// done, each one cleaning up what it left half written
+    pub fn shutdown(self) {
+        for (cancelled, _) in &self.workers {
+            cancelled.store(true, Ordering::SeqCst);
+        }
+        for (_, worker) in self.workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        paths,
+        servers::{Meta, Plan},
+    };
+    use std::{sync::mpsc, time::Duration};
+
+    /// Server whose install runs until it gets cancelled
+    struct Endless {
+        meta: Meta,
+    }
+
+    impl Server for Endless {
+        fn meta(&self) -> &Meta {
+            &self.meta
+        }
+
+        fn backend(&self) -> &'static str {
+            "test"
+        }
+
+        fn bin(&self) -> PathBuf {
+            self.dir().join("endless")
+        }
+
+        fn plan(&self, _: Option<&str>) -> Result<Plan> {
+            unreachable!("installing doesn't plan")
+        }
+
+        fn execute(&self, _: &Plan, _: &Reporter) -> Result<()> {
+            unreachable!("installing doesn't execute a plan")
+        }
+
+        fn latest_version(&self) -> Result<String> {
+            Ok(String::from("1.0.0"))
+        }
+
+        fn install(&self, report: &Reporter) -> Result<()> {
+            loop {
+                report.check()?;
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+    }
+
+    #[test]
+    fn shutdown_cancels_and_joins() {
+        paths::test_root();
+        let meta = serde_json::from_value(serde_json::json!({
+            "name": "endless",
+            "languages": [],
+            "description": "Never done installing",
+        }))
+        .unwrap();
+        let server: Arc<dyn Server> = Arc::new(Endless { meta });
+        let (tx, rx) = mpsc::channel();
+        let mut pool = Pool::default();
+        pool.spawn(7, server, Task::Install, tx, Arc::default());
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok((7, Report::Started))
+        ));
+        assert_eq!(pool.running(), 1);
+
+        pool.shutdown();
+        // Joined, so the worker sent everything and let go of its sender
+        let reports: Vec<_> = rx.try_iter().map(|(_, report)| report).collect();
+        assert!(matches!(reports.last(), Some(Report::Cancelled(_))));
+        assert!(rx.recv().is_err());
+    }
+}